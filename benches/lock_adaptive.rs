@@ -0,0 +1,70 @@
+//! Compares `SharedMutex::lock` with `SharedMutex::lock_adaptive` under brief but frequent contention.
+//!
+//! Run with `cargo bench --bench lock_adaptive`.
+
+use std::time::{Duration, Instant};
+
+use libc::{fork, waitpid};
+use process_sync::{SharedBarrier, SharedMemoryObject, SharedMutex};
+
+const PROCESSES: usize = 4;
+const ITERATIONS: u64 = 200_000;
+
+fn run(spin_count: u32, adaptive: bool) -> Duration {
+    let mut mutex = SharedMutex::new()
+        .expect("cannot create SharedMutex")
+        .with_spin_count(spin_count);
+    let mut counter = SharedMemoryObject::new(0u64).expect("cannot create SharedMemoryObject");
+    let mut barrier =
+        SharedBarrier::new(PROCESSES as u32 + 1).expect("cannot create SharedBarrier");
+
+    let mut pids = Vec::new();
+    for _ in 0..PROCESSES {
+        let pid = unsafe { fork() };
+        assert!(pid >= 0, "fork failed");
+        if pid == 0 {
+            barrier.wait().expect("wait() failed");
+            for _ in 0..ITERATIONS {
+                if adaptive {
+                    mutex.lock_adaptive().expect("lock_adaptive() failed");
+                } else {
+                    mutex.lock().expect("lock() failed");
+                }
+                // brief critical section
+                unsafe { *counter.get_mut() += 1 };
+                mutex.unlock().expect("unlock() failed");
+            }
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    barrier.wait().expect("wait() failed");
+    let start = Instant::now();
+    for pid in pids {
+        assert_eq!(unsafe { waitpid(pid, std::ptr::null_mut(), 0) }, pid);
+    }
+    let elapsed = start.elapsed();
+    assert_eq!(unsafe { *counter.get() }, PROCESSES as u64 * ITERATIONS);
+    elapsed
+}
+
+fn main() {
+    let total = PROCESSES as f64 * ITERATIONS as f64;
+    let report = |name: &str, elapsed: Duration| {
+        println!(
+            "{:<24} {:>8.1} ms {:>8.1} ns/lock",
+            name,
+            elapsed.as_secs_f64() * 1e3,
+            elapsed.as_secs_f64() * 1e9 / total
+        );
+    };
+
+    report("lock", run(0, false));
+    for spin_count in [10, 100, 1000] {
+        report(
+            &format!("lock_adaptive({})", spin_count),
+            run(spin_count, true),
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Measures lock/unlock and condvar wakeup latency, with contending process where it matters.
+//!
+//! Run with `cargo bench --bench primitives`, or e.g. `cargo bench --bench primitives -- mutex/contended` to run
+//! some of benchmarks.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use libc::{fork, waitpid};
+use process_sync::{SharedMemoryObject, SharedMonitor, SharedMutex};
+
+/// Spin counts of [`SharedMutex::lock_adaptive`] to compare.
+const SPIN_COUNTS: [u32; 4] = [0, 10, 100, 1000];
+
+/// Child process running `f` in a loop until dropped.
+struct Contender {
+    pid: libc::pid_t,
+    stop: SharedMemoryObject<AtomicBool>,
+}
+
+impl Contender {
+    fn spawn(mut f: impl FnMut()) -> Self {
+        let stop = SharedMemoryObject::new(AtomicBool::new(false))
+            .expect("cannot create SharedMemoryObject");
+        let pid = unsafe { fork() };
+        assert!(pid >= 0, "fork failed");
+        if pid == 0 {
+            // child
+            while !unsafe { stop.get() }.load(Ordering::Relaxed) {
+                f();
+            }
+            // skip exit handlers, so that output buffered by criterion is not flushed twice
+            unsafe { libc::_exit(0) };
+        }
+        Self { pid, stop }
+    }
+}
+
+impl Drop for Contender {
+    fn drop(&mut self) {
+        unsafe { self.stop.get() }.store(true, Ordering::Relaxed);
+        assert_eq!(
+            unsafe { waitpid(self.pid, std::ptr::null_mut(), 0) },
+            self.pid
+        );
+    }
+}
+
+fn mutex_uncontended(c: &mut Criterion) {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    c.bench_function("mutex/uncontended", |b| {
+        b.iter(|| {
+            mutex.lock().expect("lock() failed");
+            mutex.unlock().expect("unlock() failed");
+        })
+    });
+}
+
+fn mutex_contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mutex/contended");
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let contender = Contender::spawn(|| {
+        mutex.lock().expect("lock() failed");
+        mutex.unlock().expect("unlock() failed");
+    });
+    group.bench_function("lock", |b| {
+        b.iter(|| {
+            mutex.lock().expect("lock() failed");
+            mutex.unlock().expect("unlock() failed");
+        })
+    });
+    drop(contender);
+
+    for spin_count in SPIN_COUNTS {
+        let mut mutex = SharedMutex::new_adaptive(spin_count).expect("cannot create SharedMutex");
+        let contender = Contender::spawn(|| {
+            mutex.lock_adaptive().expect("lock_adaptive() failed");
+            mutex.unlock().expect("unlock() failed");
+        });
+        group.bench_with_input(
+            BenchmarkId::new("lock_adaptive", spin_count),
+            &spin_count,
+            |b, _| {
+                b.iter(|| {
+                    mutex.lock_adaptive().expect("lock_adaptive() failed");
+                    mutex.unlock().expect("unlock() failed");
+                })
+            },
+        );
+        drop(contender);
+    }
+
+    group.finish();
+}
+
+fn condvar_wakeup(c: &mut Criterion) {
+    // odd value is request from parent, even value is reply from child
+    const STOP: u64 = u64::MAX;
+    let mut monitor = SharedMonitor::new(0u64).expect("cannot create SharedMonitor");
+
+    let pid = unsafe { fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        // child
+        loop {
+            let guard = monitor.lock().expect("lock() failed");
+            let mut guard = guard
+                .wait_while(|value| *value % 2 == 0)
+                .expect("wait_while() failed");
+            if *guard == STOP {
+                break;
+            }
+            *guard += 1;
+            guard.notify_all().expect("notify_all() failed");
+        }
+        // skip exit handlers, so that output buffered by criterion is not flushed twice
+        unsafe { libc::_exit(0) };
+    }
+
+    // each iteration is round trip: parent wakes up child, then child wakes up parent
+    c.bench_function("condvar/round_trip", |b| {
+        b.iter_custom(|iters| {
+            let start = Instant::now();
+            for _ in 0..iters {
+                let mut guard = monitor.lock().expect("lock() failed");
+                *guard += 1;
+                guard.notify_all().expect("notify_all() failed");
+                let guard = guard
+                    .wait_while(|value| *value % 2 == 1)
+                    .expect("wait_while() failed");
+                drop(guard);
+            }
+            start.elapsed()
+        })
+    });
+
+    let mut guard = monitor.lock().expect("lock() failed");
+    *guard = STOP;
+    guard.notify_all().expect("notify_all() failed");
+    drop(guard);
+    assert_eq!(unsafe { waitpid(pid, std::ptr::null_mut(), 0) }, pid);
+}
+
+criterion_group!(benches, mutex_uncontended, mutex_contended, condvar_wakeup);
+criterion_main!(benches);
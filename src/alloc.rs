@@ -0,0 +1,72 @@
+use std::{alloc::Layout, ptr::NonNull};
+
+use libc::ENOMEM;
+
+use crate::{
+    error::{OsError, SyncError},
+    shared_memory::{allocate_aligned_shared_memory, free_shared_memory},
+};
+
+/// Allocator of memory regions backing primitives, see [`SharedMutex::new_in`](crate::SharedMutex::new_in) and
+/// [`SharedMemoryObject::new_in`](crate::SharedMemoryObject::new_in).
+///
+/// Primitives created with ordinary constructors always use shared memory, just like with [`SharedAllocator`].
+/// Other allocators exist for testing: e.g. [`HeapAllocator`] allows to exercise locking logic in single process
+/// without mapping any memory.
+pub trait RegionAllocator: Send + Sync {
+    /// Allocates zeroed region of memory fitting `layout`.
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, SyncError>;
+
+    /// Frees region returned by [`allocate`](#tymethod.allocate).
+    ///
+    /// # Safety
+    /// `ptr` must be returned by [`allocate`](#tymethod.allocate) of this allocator with the same `layout`, and must
+    /// not be freed twice.
+    ///
+    /// # Errors
+    /// If freeing fails returns corresponding [`SyncError`].
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) -> Result<(), SyncError>;
+}
+
+/// Allocator of shared memory, which is used by ordinary constructors of primitives.
+///
+/// Regions are mapped with `MAP_SHARED | MAP_ANONYMOUS`, so they are seen by child processes after `fork()`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SharedAllocator;
+
+impl RegionAllocator for SharedAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, SyncError> {
+        let addr = allocate_aligned_shared_memory(layout.size(), layout.align())?;
+        Ok(NonNull::new(addr.cast()).expect("mmap() returned null"))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) -> Result<(), SyncError> {
+        free_shared_memory(ptr.as_ptr().cast(), layout.size())
+    }
+}
+
+/// Allocator of private heap memory, for testing in single process.
+///
+/// **Regions are not shared**: after `fork()` each process works with its own copy, so primitives allocated with it
+/// don't synchronize processes at all. It is only useful to test logic built on top of primitives deterministically,
+/// without forking.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapAllocator;
+
+impl RegionAllocator for HeapAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, SyncError> {
+        if layout.size() == 0 {
+            return Err(SyncError::InvalidInput("cannot allocate empty region"));
+        }
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        NonNull::new(ptr).ok_or(SyncError::MmapFailed(OsError::new("alloc_zeroed", ENOMEM)))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) -> Result<(), SyncError> {
+        std::alloc::dealloc(ptr.as_ptr(), layout);
+        Ok(())
+    }
+}
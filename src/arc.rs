@@ -0,0 +1,149 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use libc::pid_t;
+
+use crate::{error::SyncError, shared_memory::SharedMemoryObject, util::getpid};
+
+/// Primitive which lifetime can be managed by [`SharedArc`].
+///
+/// This trait is sealed and implemented for all primitives of this crate.
+pub trait SharedPrimitive: sealed::Sealed {
+    /// Sets process which destroys primitive on drop. No process is chosen when `pid` is `0`.
+    #[doc(hidden)]
+    fn set_owner_pid(&mut self, pid: pid_t);
+
+    /// Disables destroying primitive when this handle is dropped, intentionally leaking it.
+    ///
+    /// By default creating process destroys primitive (`pthread_mutex_destroy()`, etc.) on drop, which is undefined
+    /// behaviour if other processes still use it. When lifetimes can't be ordered, e.g. because workers are killed
+    /// with `SIGKILL` and supervisor may exit first, this lets kernel clean up instead: primitive is never destroyed,
+    /// and its memory is released once no process maps it anymore.
+    ///
+    /// Handle still unmaps its memory on drop, since it only affects calling process. Use [`std::mem::forget`] to
+    /// skip that too.
+    fn forget_on_drop(&mut self) {
+        self.set_owner_pid(0);
+    }
+}
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// Reference-counted handle that destroys wrapped primitive when last process holding it drops it.
+///
+/// By default primitives are destroyed by creating process, so it must outlive all other users. [`SharedArc`] instead
+/// keeps count of processes holding the primitive in shared memory, and primitive is destroyed by whichever of them
+/// drops it last.
+///
+/// Each process referencing primitive must be counted: call [`attach`](#method.attach) before `fork()` and
+/// [`adopt`](#method.adopt) in child process after it. Copies inherited by child process without calling
+/// [`adopt`](#method.adopt) can be used as usual, but do not affect the count.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{SharedArc, SharedMutex};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut mutex = SharedArc::new(SharedMutex::new()?)?;
+///
+/// mutex.attach();
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     mutex.adopt();
+///     sleep(Duration::from_millis(40));
+///     // parent has already dropped its handle, but mutex is still alive
+///     mutex.lock()?;
+///     mutex.unlock()?;
+///     // mutex is destroyed here
+/// } else {
+///     mutex.lock()?;
+///     mutex.unlock()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedArc<T: SharedPrimitive> {
+    value: T,
+    count: SharedMemoryObject<AtomicUsize>,
+    pid: pid_t,
+}
+
+impl<T: SharedPrimitive> SharedArc<T> {
+    /// Wraps `value`, counting calling process as the only one holding it.
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new(mut value: T) -> Result<Self, SyncError> {
+        let count = SharedMemoryObject::new(AtomicUsize::new(1))?;
+        value.set_owner_pid(0);
+
+        Ok(Self {
+            value,
+            count,
+            pid: getpid(),
+        })
+    }
+
+    /// Counts one more process holding the primitive.
+    ///
+    /// Must be called before `fork()` for each child process, which then calls [`adopt`](#method.adopt). Calling it
+    /// before `fork()` guarantees that primitive is not destroyed before child is counted.
+    pub fn attach(&self) {
+        self.count().fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Makes inherited handle count for calling process, taking reference added by [`attach`](#method.attach).
+    ///
+    /// Must be called once in child process after `fork()`.
+    pub fn adopt(&mut self) {
+        self.pid = getpid();
+    }
+
+    /// Returns number of processes holding the primitive
+    pub fn ref_count(&self) -> usize {
+        self.count().load(Ordering::Relaxed)
+    }
+
+    fn count(&self) -> &AtomicUsize {
+        // atomic is safe to access concurrently
+        unsafe { self.count.get() }
+    }
+}
+
+impl<T: SharedPrimitive> Deref for SharedArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: SharedPrimitive> DerefMut for SharedArc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: SharedPrimitive> Drop for SharedArc<T> {
+    fn drop(&mut self) {
+        let pid = getpid();
+        // wrapped value is dropped right after this, destroying primitive in last process
+        if pid == self.pid && self.count().fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.value.set_owner_pid(pid);
+        }
+    }
+}
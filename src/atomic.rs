@@ -0,0 +1,155 @@
+use std::sync::atomic::{
+    AtomicI32, AtomicI64, AtomicIsize, AtomicU32, AtomicU64, AtomicUsize, Ordering,
+};
+
+use crate::{error::SyncError, shared_memory::SharedMemoryObject};
+
+macro_rules! shared_atomic {
+    ($name:ident, $atomic:ident, $int:ident) => {
+        #[doc = concat!("Integer type which can be safely shared between processes, wrapping [`", stringify!($atomic), "`].")]
+        ///
+        /// This is the lightest-weight primitive of this crate: atomic is placed in [`SharedMemoryObject`] and
+        /// operations on it are atomic across all processes sharing the mapping. No destruction is needed, so there
+        /// are no restrictions on which process drops it.
+        ///
+        /// Mapping is ordinary page-aligned memory, so operations compile to the same lock-free instructions as for
+        #[doc = concat!("in-process [`", stringify!($atomic), "`], and `order` arguments have the same meaning across processes")]
+        /// as across threads. E.g. `Release` store in one process followed by `Acquire` load of stored value in another
+        /// makes all writes to shared memory made before the store visible after the load.
+        ///
+        /// # Example
+        /// ```rust
+        /// # use std::error::Error;
+        /// # use std::sync::atomic::Ordering;
+        /// #
+        /// # use libc::{fork, waitpid};
+        /// #
+        #[doc = concat!("# use process_sync::", stringify!($name), ";")]
+        /// #
+        /// # fn main() -> Result<(), Box<dyn Error>> {
+        /// #
+        #[doc = concat!("let counter = ", stringify!($name), "::new(0)?;")]
+        ///
+        /// let pid = unsafe { fork() };
+        /// assert!(pid >= 0);
+        ///
+        /// counter.fetch_add(1, Ordering::Relaxed);
+        ///
+        /// if pid == 0 {
+        ///     std::process::exit(0);
+        /// }
+        /// unsafe { waitpid(pid, std::ptr::null_mut(), 0) };
+        /// assert_eq!(counter.load(Ordering::Relaxed), 2);
+        /// #
+        /// #     Ok(())
+        /// # }
+        /// ```
+        pub struct $name {
+            atomic: SharedMemoryObject<$atomic>,
+        }
+
+        impl $name {
+            #[doc = concat!("Creates new [`", stringify!($name), "`] with given initial value")]
+            ///
+            /// # Errors
+            /// If allocation fails returns corresponding [`SyncError`].
+            pub fn new(value: $int) -> Result<Self, SyncError> {
+                Ok(Self {
+                    atomic: SharedMemoryObject::new($atomic::new(value))?,
+                })
+            }
+
+            #[doc = concat!("Loads value. See [`", stringify!($atomic), "::load`].")]
+            pub fn load(&self, order: Ordering) -> $int {
+                self.atomic().load(order)
+            }
+
+            #[doc = concat!("Stores value. See [`", stringify!($atomic), "::store`].")]
+            pub fn store(&self, value: $int, order: Ordering) {
+                self.atomic().store(value, order)
+            }
+
+            #[doc = concat!("Stores value, returning previous one. See [`", stringify!($atomic), "::swap`].")]
+            pub fn swap(&self, value: $int, order: Ordering) -> $int {
+                self.atomic().swap(value, order)
+            }
+
+            #[doc = concat!("Adds to value with wrap around on overflow, returning previous value. See [`", stringify!($atomic), "::fetch_add`].")]
+            pub fn fetch_add(&self, value: $int, order: Ordering) -> $int {
+                self.atomic().fetch_add(value, order)
+            }
+
+            #[doc = concat!("Subtracts from value with wrap around on overflow, returning previous value. See [`", stringify!($atomic), "::fetch_sub`].")]
+            pub fn fetch_sub(&self, value: $int, order: Ordering) -> $int {
+                self.atomic().fetch_sub(value, order)
+            }
+
+            /// Stores `new` if value is equal to `current`.
+            ///
+            /// Returns `Ok` with previous value if it was replaced and `Err` with actual value otherwise.
+            #[doc = concat!("See [`", stringify!($atomic), "::compare_exchange`].")]
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.atomic().compare_exchange(current, new, success, failure)
+            }
+
+            fn atomic(&self) -> &$atomic {
+                // atomic is safe to access concurrently
+                unsafe { self.atomic.get() }
+            }
+        }
+    };
+}
+
+/// Memory fence, which orders accesses to shared memory across processes.
+///
+/// Shared memory is ordinary memory, so this is exactly [`std::sync::atomic::fence`], which works across processes
+/// just like across threads. It allows to publish data written through
+/// [`SharedMemoryObject::get_mut`] with `Relaxed` atomic flag: `fence(Release)` before setting the flag in one process
+/// pairs with `fence(Acquire)` after observing it in another.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::sync::atomic::Ordering;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{fence, SharedAtomicU32, SharedMemoryObject};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut data = SharedMemoryObject::new([0u64; 4])?;
+/// let ready = SharedAtomicU32::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     unsafe { *data.get_mut() = [1, 2, 3, 4] };
+///     fence(Ordering::Release);
+///     ready.store(1, Ordering::Relaxed);
+///     std::process::exit(0);
+/// }
+/// while ready.load(Ordering::Relaxed) == 0 {}
+/// fence(Ordering::Acquire);
+/// assert_eq!(unsafe { *data.get() }, [1, 2, 3, 4]);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub fn fence(order: Ordering) {
+    std::sync::atomic::fence(order);
+}
+
+shared_atomic!(SharedAtomicI32, AtomicI32, i32);
+shared_atomic!(SharedAtomicI64, AtomicI64, i64);
+shared_atomic!(SharedAtomicIsize, AtomicIsize, isize);
+shared_atomic!(SharedAtomicU32, AtomicU32, u32);
+shared_atomic!(SharedAtomicU64, AtomicU64, u64);
+shared_atomic!(SharedAtomicUsize, AtomicUsize, usize);
@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+
+use libc::{c_uint, pid_t};
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    SharedCondvar, SharedMutex,
+};
+
+/// Barrier that can be shared between processes.
+///
+/// Barrier blocks processes calling [`wait`](#method.wait) until `count` of them reach it, then releases all of them at once.
+/// Barrier is reusable: once released, next `count` processes reaching it are gathered again.
+///
+/// Unlike `pthread_barrier_t`, barrier is built from [`SharedMutex`] and [`SharedCondvar`], so waiting can be bounded
+/// with [`wait_timeout`](#method.wait_timeout). Process which gives up waiting withdraws from barrier, so one missing
+/// participant doesn't wedge others forever.
+///
+/// Dropping barrier in creating process while it being waited will cause undefined behaviour.
+/// It is recommended to drop this barrier in creating process only after no other process has access to it.
+///
+/// For more information see [`SharedMutex`], [`SharedCondvar`] and [`SharedMemoryObject`](crate::SharedMemoryObject).
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedBarrier;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut barrier = SharedBarrier::new(2)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     println!("child wait()");
+///     barrier.wait()?;
+///     println!("child released");
+/// } else {
+///     sleep(Duration::from_millis(40));
+///     println!("parent wait()");
+///     barrier.wait()?;
+///     println!("parent released");
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// Output:
+/// ```txt
+/// child wait()
+/// parent wait()
+/// child released
+/// parent released
+/// ```
+pub struct SharedBarrier {
+    state: SharedMutex<BarrierState>,
+    released: SharedCondvar,
+}
+
+struct BarrierState {
+    /// Number of processes that must reach barrier to release it.
+    count: c_uint,
+    /// Number of processes currently waiting on barrier.
+    arrived: c_uint,
+    /// Incremented each time barrier releases waiting processes.
+    generation: u64,
+}
+
+impl BarrierState {
+    /// Registers arrival of calling process. Returns `true` if it is the last one, in which case barrier is released.
+    fn arrive(&mut self) -> bool {
+        self.arrived += 1;
+        if self.arrived < self.count {
+            return false;
+        }
+        self.arrived = 0;
+        self.generation = self.generation.wrapping_add(1);
+        true
+    }
+}
+
+impl SharedBarrier {
+    /// Creates new [`SharedBarrier`] which releases waiting processes once `count` of them called [`wait`](#method.wait)
+    ///
+    /// # Errors
+    /// If `count` is zero, returns [`SyncError::InvalidInput`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new(count: c_uint) -> Result<Self, SyncError> {
+        if count == 0 {
+            return Err(SyncError::InvalidInput("barrier count must be positive"));
+        }
+        Ok(Self {
+            state: SharedMutex::with_data(BarrierState {
+                count,
+                arrived: 0,
+                generation: 0,
+            })?,
+            released: SharedCondvar::new()?,
+        })
+    }
+
+    /// Waits until all processes reach this barrier.
+    ///
+    /// Exactly one of released processes gets [`BarrierWaitResult`] for which [`is_leader`](BarrierWaitResult::is_leader)
+    /// returns `true`.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn wait(&mut self) -> Result<BarrierWaitResult, SyncError> {
+        let mut state = self.state.guard()?;
+        if state.arrive() {
+            drop(state);
+            self.released.notify_all()?;
+            return Ok(BarrierWaitResult(true));
+        }
+        let generation = state.generation;
+        drop(
+            self.released
+                .wait_while_guard(state, |state| state.generation == generation)?,
+        );
+        Ok(BarrierWaitResult(false))
+    }
+
+    /// Waits until all processes reach this barrier, giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(true)` if barrier was released before timeout and `Ok(false)` if timeout expired. In the latter case
+    /// calling process withdraws from barrier, so it stays usable: it is released once `count` processes reach it,
+    /// whether or not they include this one. Use [`arrived`](#method.arrived) to find out how many processes are
+    /// still waiting, e.g. to detect which participant is missing.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<bool, SyncError> {
+        let mut state = self.state.guard()?;
+        if state.arrive() {
+            drop(state);
+            self.released.notify_all()?;
+            return Ok(true);
+        }
+        let generation = state.generation;
+        // deadline that doesn't fit into Instant is effectively infinite
+        let deadline = Instant::now().checked_add(timeout);
+        while state.generation == generation {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => timeout,
+            };
+            if remaining.is_zero() {
+                state.arrived -= 1;
+                return Ok(false);
+            }
+            state = self.released.wait_timeout_guard(state, remaining)?.0;
+        }
+        Ok(true)
+    }
+
+    /// Returns number of processes currently waiting on this barrier.
+    ///
+    /// **Result is only a snapshot**, processes may arrive or give up waiting right after this function returns.
+    ///
+    /// # Errors
+    /// If locking fails, returns corresponding [`SyncError`].
+    pub fn arrived(&mut self) -> Result<c_uint, SyncError> {
+        Ok(self.state.guard()?.arrived)
+    }
+}
+
+/// Result of [`SharedBarrier::wait`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` for exactly one process released by barrier, which is the last one to reach it.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl sealed::Sealed for SharedBarrier {}
+
+impl SharedPrimitive for SharedBarrier {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.state.set_owner_pid(pid);
+        self.released.set_owner_pid(pid);
+    }
+}
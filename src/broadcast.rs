@@ -0,0 +1,195 @@
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    shared_memory::SharedMemoryObject,
+    SharedCondvar, SharedMutex,
+};
+
+/// Broadcast channel that can be shared between processes, where every consumer receives every message.
+///
+/// Unlike [`SharedChannel`](crate::SharedChannel), messages are not consumed by receiving. They are stored in ring
+/// of `capacity` slots, each tagged with its sequence number, protected by [`SharedMutex`]. Every process keeps its
+/// own read cursor, which is inherited by forked children, and [`recv`](#method.recv) blocks on [`SharedCondvar`]
+/// until message newer than cursor is sent.
+///
+/// [`send`](#method.send) never blocks: when ring is full, the oldest message is overwritten. Consumer which fell
+/// more than `capacity` messages behind gets [`SyncError::Lagged`] with number of missed messages, and continues
+/// from the oldest message still available.
+///
+/// Dropping broadcast in creating process while it being used by another process will cause undefined behaviour.
+/// It is recommended to drop it in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedBroadcast;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut broadcast = SharedBroadcast::new(16, 64)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     broadcast.send(b"hello")?;
+/// } else {
+///     // every consumer process receives its own copy
+///     let mut buf = [0; 64];
+///     let len = broadcast.recv(&mut buf)?;
+///     assert_eq!(&buf[..len], b"hello");
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedBroadcast {
+    state: SharedMutex<BroadcastState>,
+    /// Message lengths, one per slot.
+    lengths: SharedMemoryObject<[usize]>,
+    /// Message bytes, `message_size` per slot.
+    buffer: SharedMemoryObject<[u8]>,
+    sent: SharedCondvar,
+    capacity: usize,
+    message_size: usize,
+    /// Sequence number of the next message this process will receive.
+    cursor: u64,
+}
+
+struct BroadcastState {
+    /// Sequence number of the next sent message. Message `seq` is stored in slot `seq % capacity`.
+    next: u64,
+}
+
+impl SharedBroadcast {
+    /// Creates new [`SharedBroadcast`] which keeps last `capacity` messages of at most `message_size` bytes each
+    ///
+    /// Receive cursor of creating process starts at the first message.
+    ///
+    /// # Errors
+    /// If `capacity` or `message_size` is zero, returns [`SyncError::InvalidInput`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new(capacity: usize, message_size: usize) -> Result<Self, SyncError> {
+        if capacity == 0 {
+            return Err(SyncError::InvalidInput(
+                "broadcast capacity must be positive",
+            ));
+        }
+        if message_size == 0 {
+            return Err(SyncError::InvalidInput(
+                "broadcast message size must be positive",
+            ));
+        }
+        let buffer_len = capacity
+            .checked_mul(message_size)
+            .ok_or(SyncError::InvalidInput("broadcast buffer is too large"))?;
+
+        Ok(Self {
+            state: SharedMutex::with_data(BroadcastState { next: 0 })?,
+            lengths: SharedMemoryObject::new_slice(capacity, |_| 0)?,
+            buffer: SharedMemoryObject::new_slice(buffer_len, |_| 0)?,
+            sent: SharedCondvar::new()?,
+            capacity,
+            message_size,
+            cursor: 0,
+        })
+    }
+
+    /// Returns maximum number of messages kept by broadcast
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns maximum length of single message
+    pub fn message_size(&self) -> usize {
+        self.message_size
+    }
+
+    /// Sends `message` to all consumers, overwriting the oldest message if ring is full.
+    ///
+    /// # Errors
+    /// If `message` is longer than [`message_size`](#method.message_size), returns [`SyncError::InvalidInput`].
+    /// If locking or notifying fails, returns corresponding [`SyncError`].
+    pub fn send(&mut self, message: &[u8]) -> Result<(), SyncError> {
+        if message.len() > self.message_size {
+            return Err(SyncError::InvalidInput(
+                "message is longer than broadcast message size",
+            ));
+        }
+
+        let capacity = self.capacity() as u64;
+        let mut state = self.state.guard()?;
+        let slot = (state.next % capacity) as usize;
+        let start = slot * self.message_size;
+        // slots are only accessed while mutex is locked
+        unsafe {
+            self.buffer.as_mut_slice()[start..start + message.len()].copy_from_slice(message);
+            self.lengths.as_mut_slice()[slot] = message.len();
+        }
+        state.next += 1;
+        drop(state);
+
+        self.sent.notify_all()
+    }
+
+    /// Receives next message into `buf`, blocking until it is sent.
+    ///
+    /// Returns length of received message.
+    ///
+    /// # Errors
+    /// If this process missed messages which were overwritten, returns [`SyncError::Lagged`] with their number and
+    /// moves cursor to the oldest available message, so next call succeeds.
+    /// If `buf` is shorter than received message, returns [`SyncError::InvalidInput`] and doesn't move cursor.
+    /// If locking or waiting fails, returns corresponding [`SyncError`].
+    pub fn recv(&mut self, buf: &mut [u8]) -> Result<usize, SyncError> {
+        let capacity = self.capacity() as u64;
+        let mut state = self.state.guard()?;
+        while state.next == self.cursor {
+            state = self.sent.wait_guard(state)?;
+        }
+
+        let oldest = state.next.saturating_sub(capacity);
+        if self.cursor < oldest {
+            let missed = oldest - self.cursor;
+            self.cursor = oldest;
+            return Err(SyncError::Lagged(missed));
+        }
+
+        let slot = (self.cursor % capacity) as usize;
+        let start = slot * self.message_size;
+        // slots are only accessed while mutex is locked
+        let len = unsafe { self.lengths.as_slice()[slot] };
+        if buf.len() < len {
+            return Err(SyncError::InvalidInput(
+                "buffer is shorter than received message",
+            ));
+        }
+        buf[..len].copy_from_slice(unsafe { &self.buffer.as_slice()[start..start + len] });
+        self.cursor += 1;
+        Ok(len)
+    }
+
+    /// Returns number of messages sent but not yet received by this process, including missed ones.
+    ///
+    /// **Result is only a snapshot**, more messages may be sent right after this function returns.
+    ///
+    /// # Errors
+    /// If locking fails, returns corresponding [`SyncError`].
+    pub fn pending(&mut self) -> Result<u64, SyncError> {
+        Ok(self.state.guard()?.next - self.cursor)
+    }
+}
+
+impl sealed::Sealed for SharedBroadcast {}
+
+impl SharedPrimitive for SharedBroadcast {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.state.set_owner_pid(pid);
+        self.sent.set_owner_pid(pid);
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::{Duration, Instant};
+
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    shared_memory::SharedMemoryObject,
+    SharedCondvar, SharedMutex,
+};
+
+/// Bounded byte channel that can be shared between processes.
+///
+/// Bytes are stored in ring buffer of fixed capacity, protected by [`SharedMutex`]. [`send`](#method.send) blocks
+/// while buffer is full and [`recv`](#method.recv) blocks while it is empty, waiting on [`SharedCondvar`].
+///
+/// Channel transfers stream of bytes, message boundaries are not preserved. Messages not longer than capacity are
+/// written atomically, so messages of several producers never interleave. Longer messages are split into chunks.
+///
+/// Dropping channel in creating process while it being used by another process will cause undefined behaviour.
+/// It is recommended to drop this channel in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedChannel;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut channel = SharedChannel::new(16)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     channel.send(b"hello")?;
+/// } else {
+///     let mut buf = [0; 5];
+///     let mut received = 0;
+///     while received < buf.len() {
+///         received += channel.recv(&mut buf[received..])?;
+///     }
+///     assert_eq!(&buf, b"hello");
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedChannel {
+    state: SharedMutex<RingState>,
+    buffer: SharedMemoryObject<[u8]>,
+    not_empty: SharedCondvar,
+    not_full: SharedCondvar,
+}
+
+struct RingState {
+    /// Offset of the first unread byte.
+    read: usize,
+    /// Number of unread bytes.
+    len: usize,
+}
+
+impl SharedChannel {
+    /// Creates new [`SharedChannel`] which buffers up to `capacity` bytes
+    ///
+    /// # Errors
+    /// If `capacity` is zero, returns [`SyncError::InvalidInput`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new(capacity: usize) -> Result<Self, SyncError> {
+        if capacity == 0 {
+            return Err(SyncError::InvalidInput("channel capacity must be positive"));
+        }
+
+        Ok(Self {
+            state: SharedMutex::with_data(RingState { read: 0, len: 0 })?,
+            buffer: SharedMemoryObject::new_slice(capacity, |_| 0)?,
+            not_empty: SharedCondvar::new()?,
+            not_full: SharedCondvar::new()?,
+        })
+    }
+
+    /// Returns maximum number of bytes buffered by channel
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Sends all of `data`, blocking while buffer is full.
+    ///
+    /// If `data` is not longer than [`capacity`](#method.capacity), it is written at once, after enough space is freed.
+    /// Otherwise it is written in chunks of at most capacity bytes, which may interleave with data of other producers.
+    ///
+    /// # Errors
+    /// If locking, waiting or notifying fails, returns corresponding [`SyncError`]. Part of `data` may be sent in
+    /// this case.
+    pub fn send(&mut self, mut data: &[u8]) -> Result<(), SyncError> {
+        let capacity = self.capacity();
+        while !data.is_empty() {
+            let chunk = data.len().min(capacity);
+
+            let mut state = self.state.guard()?;
+            while capacity - state.len < chunk {
+                state = self.not_full.wait_guard(state)?;
+            }
+
+            // free space starts right after unread bytes and may wrap around the end of buffer
+            let start = (state.read + state.len) % capacity;
+            let first = chunk.min(capacity - start);
+            // buffer is only accessed while mutex is locked
+            let buffer = unsafe { self.buffer.as_mut_slice() };
+            buffer[start..start + first].copy_from_slice(&data[..first]);
+            buffer[..chunk - first].copy_from_slice(&data[first..chunk]);
+            state.len += chunk;
+            drop(state);
+
+            self.not_empty.notify_all()?;
+            data = &data[chunk..];
+        }
+        Ok(())
+    }
+
+    /// Receives bytes into `buf`, blocking while buffer is empty.
+    ///
+    /// Returns number of received bytes, which is at least one and at most `buf.len()`, or zero if `buf` is empty.
+    ///
+    /// # Errors
+    /// If locking, waiting or notifying fails, returns corresponding [`SyncError`].
+    pub fn recv(&mut self, buf: &mut [u8]) -> Result<usize, SyncError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut state = self.state.guard()?;
+        while state.len == 0 {
+            state = self.not_empty.wait_guard(state)?;
+        }
+        // buffer is only accessed while mutex is locked
+        let count = read_ring(&mut state, unsafe { self.buffer.as_slice() }, buf);
+        drop(state);
+
+        self.not_full.notify_all()?;
+        Ok(count)
+    }
+
+    /// Receives bytes into `buf`, blocking while buffer is empty, but giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(Some(count))` like [`recv`](#method.recv) or `Ok(None)` if timeout expired while buffer was empty.
+    /// Spurious wakeups don't extend total wait. Buffer is checked once more after timeout expires, so data sent right
+    /// at the deadline is received rather than left in the channel.
+    ///
+    /// # Errors
+    /// If locking, waiting or notifying fails, returns corresponding [`SyncError`]. It can be converted to
+    /// [`std::io::Error`] with `?`.
+    pub fn recv_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<Option<usize>, SyncError> {
+        if buf.is_empty() {
+            return Ok(Some(0));
+        }
+        // deadline that doesn't fit into Instant is effectively infinite
+        let deadline = Instant::now().checked_add(timeout);
+
+        let mut state = self.state.guard()?;
+        while state.len == 0 {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => timeout,
+            };
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            // result is ignored, since buffer is checked again in both cases
+            self.not_empty.wait_timeout(state.mutex(), remaining)?;
+        }
+        // buffer is only accessed while mutex is locked
+        let count = read_ring(&mut state, unsafe { self.buffer.as_slice() }, buf);
+        drop(state);
+
+        self.not_full.notify_all()?;
+        Ok(Some(count))
+    }
+}
+
+impl sealed::Sealed for SharedChannel {}
+
+impl SharedPrimitive for SharedChannel {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.state.set_owner_pid(pid);
+        self.not_empty.set_owner_pid(pid);
+        self.not_full.set_owner_pid(pid);
+    }
+}
+
+/// Moves unread bytes of ring `buffer` into `buf`, returning number of moved bytes.
+fn read_ring(state: &mut RingState, buffer: &[u8], buf: &mut [u8]) -> usize {
+    let capacity = buffer.len();
+
+    // unread bytes may wrap around the end of buffer
+    let count = buf.len().min(state.len);
+    let first = count.min(capacity - state.read);
+    buf[..first].copy_from_slice(&buffer[state.read..state.read + first]);
+    buf[first..count].copy_from_slice(&buffer[..count - first]);
+    state.read = (state.read + count) % capacity;
+    state.len -= count;
+    count
+}
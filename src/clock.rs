@@ -0,0 +1,22 @@
+use libc::{clockid_t, CLOCK_MONOTONIC, CLOCK_REALTIME};
+
+/// Clock against which timeouts are measured.
+///
+/// For more information see [`clock_gettime`](https://man7.org/linux/man-pages/man3/clock_gettime.3.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockId {
+    /// System-wide wall clock (`CLOCK_REALTIME`). Affected by system time changes, e.g. by NTP.
+    #[default]
+    Realtime,
+    /// Monotonic clock (`CLOCK_MONOTONIC`). Not affected by system time changes.
+    Monotonic,
+}
+
+impl ClockId {
+    pub(crate) fn as_raw(self) -> clockid_t {
+        match self {
+            ClockId::Realtime => CLOCK_REALTIME,
+            ClockId::Monotonic => CLOCK_MONOTONIC,
+        }
+    }
+}
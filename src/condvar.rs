@@ -1,18 +1,30 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
 use libc::{
-    pid_t, pthread_cond_broadcast, pthread_cond_destroy, pthread_cond_init, pthread_cond_signal,
-    pthread_cond_t, pthread_cond_wait, pthread_condattr_destroy, pthread_condattr_init,
-    pthread_condattr_setpshared, pthread_condattr_t, PTHREAD_COND_INITIALIZER,
-    PTHREAD_PROCESS_SHARED,
+    c_int, pid_t, pthread_cond_broadcast, pthread_cond_destroy, pthread_cond_init,
+    pthread_cond_signal, pthread_cond_t, pthread_cond_wait, pthread_condattr_destroy,
+    pthread_condattr_init, pthread_condattr_setpshared, pthread_condattr_t, pthread_mutex_t,
+    EOWNERDEAD, EPERM, ETIMEDOUT, PTHREAD_PROCESS_SHARED,
 };
 
 use crate::{
+    arc::{sealed, SharedPrimitive},
+    clock::ClockId,
+    error::{report_primitive_drop_error, OsError, SyncError},
     shared_memory::SharedMemoryObject,
-    util::{check_libc_err, getpid},
-    SharedMutex,
+    util::{check_pshared_err, check_pthread_err, getpid},
+    LockStatus, SharedMutex, SharedMutexGuard,
 };
 
 /// Simple conditional variable that can be shared between processes and used with [`SharedMutex`]
 ///
+/// Methods taking [`SharedMutexGuard`] ([`wait_guard`](#method.wait_guard), [`wait_while_guard`](#method.wait_while_guard)
+/// and [`wait_timeout_guard`](#method.wait_timeout_guard)) can only be called with mutex locked, which is checked at
+/// compile time. Methods taking [`SharedMutex`] directly rely on caller to lock it first.
+///
 /// Dropping conditional variable in creating process while it being used by another process will cause undefined behaviour.
 /// It is recommended to drop this conditional variable in creating process only after no other process has access to it.
 ///
@@ -66,84 +78,507 @@ use crate::{
 /// ```
 pub struct SharedCondvar {
     condvar: SharedMemoryObject<pthread_cond_t>,
+    waiters: Option<SharedMemoryObject<AtomicUsize>>,
     owner_pid: pid_t,
+    clock: ClockId,
 }
 
 impl SharedCondvar {
+    /// Size of wrapped `pthread_cond_t` in bytes.
+    ///
+    /// Can be used to statically check that layout matches `pthread_cond_t` of C code sharing the mapping.
+    pub const CONDVAR_SIZE: usize = std::mem::size_of::<pthread_cond_t>();
+
+    /// Alignment of wrapped `pthread_cond_t` in bytes.
+    pub const CONDVAR_ALIGN: usize = std::mem::align_of::<pthread_cond_t>();
+
     /// Creates new [`SharedCondvar`]
     ///
     /// # Errors
-    /// If allocation or initialization fails returns error from [`last_os_error`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Self::new_with_clock(ClockId::Realtime)
+    }
+
+    /// Creates new [`SharedCondvar`] which measures timeouts of [`wait_timeout`](#method.wait_timeout) against `clock`
+    ///
+    /// Use [`ClockId::Monotonic`] to make timed waits immune to system time changes. macOS has no
+    /// `pthread_condattr_setclock()`, so only default [`ClockId::Realtime`] is supported there.
+    ///
+    /// For more information see [`pthread_condattr_setclock`](https://man7.org/linux/man-pages/man3/pthread_condattr_setclock.3p.html).
+    ///
+    /// # Errors
+    /// If `clock` is not supported by platform, returns [`SyncError::InvalidInput`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_with_clock(clock: ClockId) -> Result<Self, SyncError> {
+        // mapping is zeroed, which is valid value to pass to pthread_cond_init(), so no initializer is needed
+        let mut condvar = SharedMemoryObject::<pthread_cond_t>::new_uninit()?;
+        initialize_condvar(unsafe { condvar.get_mut().assume_init_mut() }, clock)?;
+        let condvar = unsafe { condvar.assume_init() };
+
+        let owner_pid = getpid();
+        Ok(Self {
+            condvar,
+            waiters: None,
+            owner_pid,
+            clock,
+        })
+    }
+
+    /// Creates new [`SharedCondvar`] in memory provided by caller, instead of allocating separate mapping
+    ///
+    /// This allows to pack condvar together with [`SharedMutex::new_in_place`] and data into one shared memory
+    /// mapping. `ptr` must have size and alignment of [`CONDVAR_SIZE`](#associatedconstant.CONDVAR_SIZE) and
+    /// [`CONDVAR_ALIGN`](#associatedconstant.CONDVAR_ALIGN). Memory is **not** unmapped when condvar is dropped, but
+    /// condvar is destroyed by creating process as usual.
+    ///
+    /// # Safety
+    /// `ptr` must point to memory mapped with `MAP_SHARED`, so that other processes see the same condvar, and must not
+    /// be accessed other than through returned condvar (or its copies in other processes). Memory must stay mapped
+    /// until all processes drop the condvar.
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn new() -> std::io::Result<Self> {
-        let mut condvar = SharedMemoryObject::new(PTHREAD_COND_INITIALIZER)?;
-        initialize_condvar(condvar.get_mut())?;
+    /// # Errors
+    /// If `ptr` is null or not aligned, returns [`SyncError::InvalidInput`].
+    /// If initialization fails returns corresponding [`SyncError`].
+    pub unsafe fn new_in_place(ptr: *mut pthread_cond_t) -> Result<Self, SyncError> {
+        if ptr.is_null() || !ptr.is_aligned() {
+            return Err(SyncError::InvalidInput(
+                "condvar pointer must be non-null and aligned",
+            ));
+        }
+        let clock = ClockId::Realtime;
+        initialize_condvar(&mut *ptr, clock)?;
+        let condvar = SharedMemoryObject::from_raw_ptr(ptr);
 
         let owner_pid = getpid();
-        Ok(Self { condvar, owner_pid })
+        Ok(Self {
+            condvar,
+            waiters: None,
+            owner_pid,
+            clock,
+        })
+    }
+
+    /// Creates new counted [`SharedCondvar`]
+    ///
+    /// Counted condvar additionally maintains number of waiting processes in separate shared memory mapping, which
+    /// can be queried with [`waiters`](#method.waiters) and [`notify_all_counted`](#method.notify_all_counted).
+    /// Count is best-effort and racy, so it must only be used for diagnostics and telemetry. Condvars created with
+    /// other constructors do not pay for it.
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_counted() -> Result<Self, SyncError> {
+        let mut condvar = Self::new()?;
+        condvar.waiters = Some(SharedMemoryObject::new(AtomicUsize::new(0))?);
+        Ok(condvar)
+    }
+
+    /// Returns clock against which timeouts are measured
+    pub fn clock(&self) -> ClockId {
+        self.clock
     }
 
     /// Waits on given mutex
     ///
-    /// This function will block until notified by another process
+    /// This function will block until notified by another process. It is not interrupted by signals, but may wake up
+    /// spuriously after signal handler returns, so condition must be checked in a loop, see
+    /// [`wait_while`](#method.wait_while).
+    ///
+    /// For robust mutexes returns [`LockStatus::AcquiredInconsistent`] if owner of the mutex died while this process
+    /// was reacquiring it, see [`SharedMutex::mark_consistent`].
+    ///
+    /// Mutex must be locked by calling process, otherwise behaviour is undefined. For tracked
+    /// (see [`SharedMutex::new_tracked`]), error-checking and robust mutexes this is detected and
+    /// [`SyncError::NotOwner`] is returned instead.
     ///
     /// # Errors
-    /// If any pthread call fails, returns error from [`last_os_error`]. For possible errors see [`pthread_cond_wait`](https://man7.org/linux/man-pages/man3/pthread_cond_wait.3p.html).
+    /// If mutex is not held by calling process, returns [`SyncError::NotOwner`], see above. If pthread call fails
+    /// otherwise, returns corresponding [`SyncError`]. For possible errors see [`pthread_cond_wait`](https://man7.org/linux/man-pages/man3/pthread_cond_wait.3p.html).
+    pub fn wait<T: Send>(&mut self, mutex: &mut SharedMutex<T>) -> Result<LockStatus, SyncError> {
+        mutex.check_held("pthread_cond_wait")?;
+        self.count_waiter(true);
+        let ret = unsafe { pthread_cond_wait(self.condvar.raw_ptr(), mutex.get_mut()) };
+        self.count_waiter(false);
+        // mutex was released while waiting, so whoever locked it in between overwrote tracked owner
+        if matches!(ret, 0 | EOWNERDEAD) {
+            mutex.track(getpid());
+        }
+        match ret {
+            0 => Ok(LockStatus::Acquired),
+            EOWNERDEAD => Ok(LockStatus::AcquiredInconsistent),
+            EPERM => Err(SyncError::NotOwner(OsError::new(
+                "pthread_cond_wait",
+                EPERM,
+            ))),
+            err => Err(SyncError::from_errno("pthread_cond_wait", err)),
+        }
+    }
+
+    /// Waits on mutex locked by `guard`, returning guard which holds the lock again.
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn wait(&mut self, mutex: &mut SharedMutex) -> std::io::Result<()> {
-        check_libc_err(unsafe { pthread_cond_wait(self.condvar.get_mut(), mutex.get_mut()) })?;
-        Ok(())
+    /// Mutex is atomically unlocked while waiting, just like in [`std::sync::Condvar::wait`]. Unlike
+    /// [`wait`](#method.wait), mutex can't be passed unlocked, since guard always holds the lock.
+    ///
+    /// For robust mutexes [`SharedMutexGuard::status`] of returned guard is [`LockStatus::AcquiredInconsistent`] if
+    /// owner of the mutex died while this process was reacquiring it.
+    ///
+    /// Guard acts as a token proving that mutex is locked, so waiting on unlocked mutex doesn't compile:
+    /// ```compile_fail
+    /// # use process_sync::{SharedCondvar, SharedMutex};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut mutex = SharedMutex::new()?;
+    /// let mut condvar = SharedCondvar::new()?;
+    /// condvar.wait_guard(&mut mutex)?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Same as [`wait`](#method.wait). Mutex is unlocked when error is returned, since guard is dropped.
+    pub fn wait_guard<'a, T: Send>(
+        &mut self,
+        mut guard: SharedMutexGuard<'a, T>,
+    ) -> Result<SharedMutexGuard<'a, T>, SyncError> {
+        let status = self.wait(guard.mutex())?;
+        guard.set_status(status);
+        Ok(guard)
+    }
+
+    /// Waits on mutex locked by `guard` while `condition` returns `true` for data protected by it.
+    ///
+    /// Guard-based counterpart of [`wait_while`](#method.wait_while). If robust mutex is reacquired in inconsistent
+    /// state, guard is returned immediately with [`LockStatus::AcquiredInconsistent`] status, without checking
+    /// `condition`.
+    ///
+    /// # Errors
+    /// Same as [`wait_guard`](#method.wait_guard).
+    pub fn wait_while_guard<'a, T: Send>(
+        &mut self,
+        mut guard: SharedMutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> Result<SharedMutexGuard<'a, T>, SyncError> {
+        while condition(&mut guard) {
+            guard = self.wait_guard(guard)?;
+            if guard.status() == LockStatus::AcquiredInconsistent {
+                break;
+            }
+        }
+        Ok(guard)
+    }
+
+    /// Waits on mutex locked by `guard`, giving up after `timeout` elapses.
+    ///
+    /// Guard-based counterpart of [`wait_timeout`](#method.wait_timeout). Returns guard which holds the lock again,
+    /// and `true` if woken up before timeout (which includes spurious wakeups) or `false` if timeout expired.
+    ///
+    /// If owner of robust mutex died while this process was reacquiring it, guard has
+    /// [`LockStatus::AcquiredInconsistent`] status.
+    ///
+    /// # Errors
+    /// Same as [`wait_timeout`](#method.wait_timeout). Mutex is unlocked when error is returned, since guard is
+    /// dropped.
+    pub fn wait_timeout_guard<'a, T: Send>(
+        &mut self,
+        mut guard: SharedMutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> Result<(SharedMutexGuard<'a, T>, bool), SyncError> {
+        match self.wait_timeout(guard.mutex(), timeout)? {
+            Some(status) => {
+                guard.set_status(status);
+                Ok((guard, true))
+            }
+            None => Ok((guard, false)),
+        }
+    }
+
+    /// Waits on given mutex while `condition` returns `true`.
+    ///
+    /// `condition` is checked before each wait with mutex locked, so it can safely inspect data protected by mutex.
+    /// This handles spurious wakeups, just like [`std::sync::Condvar::wait_while`].
+    ///
+    /// If robust mutex is reacquired in inconsistent state, returns [`LockStatus::AcquiredInconsistent`] immediately,
+    /// without checking `condition`.
+    ///
+    /// # Errors
+    /// Same as [`wait`](#method.wait).
+    pub fn wait_while<T: Send, F: FnMut() -> bool>(
+        &mut self,
+        mutex: &mut SharedMutex<T>,
+        mut condition: F,
+    ) -> Result<LockStatus, SyncError> {
+        while condition() {
+            if self.wait(mutex)? == LockStatus::AcquiredInconsistent {
+                return Ok(LockStatus::AcquiredInconsistent);
+            }
+        }
+        Ok(LockStatus::Acquired)
+    }
+
+    /// Waits on given mutex, giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(Some(status))` if woken up before timeout (which includes spurious wakeups) and `Ok(None)` if timeout
+    /// expired. In both cases mutex is locked again when this function returns. For robust mutexes status is
+    /// [`LockStatus::AcquiredInconsistent`] if previous owner died while holding the lock, same as in
+    /// [`wait`](#method.wait). Deadline is measured against [`clock`](#method.clock)
+    /// of the condvar. On macOS, which only supports default clock, relative wait (`pthread_cond_timedwait_relative_np`)
+    /// is used instead, so timeout is not affected by system time changes.
+    ///
+    /// # Errors
+    /// Mutex which is not held by calling process is handled the same way as in [`wait`](#method.wait).
+    /// If pthread call fails with anything other than `ETIMEDOUT`, returns corresponding [`SyncError`]. For possible errors see [`pthread_cond_timedwait`](https://man7.org/linux/man-pages/man3/pthread_cond_timedwait.3p.html).
+    pub fn wait_timeout<T: Send>(
+        &mut self,
+        mutex: &mut SharedMutex<T>,
+        timeout: Duration,
+    ) -> Result<Option<LockStatus>, SyncError> {
+        mutex.check_held(TIMED_WAIT)?;
+        self.count_waiter(true);
+        let ret = timed_wait(self.condvar.raw_ptr(), mutex.get_mut(), self.clock, timeout);
+        self.count_waiter(false);
+        let ret = ret?;
+        // see wait()
+        if matches!(ret, 0 | ETIMEDOUT | EOWNERDEAD) {
+            mutex.track(getpid());
+        }
+        match ret {
+            0 => Ok(Some(LockStatus::Acquired)),
+            ETIMEDOUT => Ok(None),
+            EOWNERDEAD => Ok(Some(LockStatus::AcquiredInconsistent)),
+            EPERM => Err(SyncError::NotOwner(OsError::new(TIMED_WAIT, EPERM))),
+            err => Err(SyncError::from_errno(TIMED_WAIT, err)),
+        }
+    }
+
+    /// Waits on given mutex while `condition` returns `true`, giving up after `timeout` elapses in total.
+    ///
+    /// Like [`wait_while`](#method.wait_while), `condition` is checked before each wait with mutex locked. Time spent
+    /// in previous waits is deducted from the remaining timeout, so spurious wakeups don't extend total wait.
+    /// This mirrors [`std::sync::Condvar::wait_timeout_while`].
+    ///
+    /// Returns `Ok(Some(status))` if `condition` returned `false` before timeout and `Ok(None)` if timeout expired while
+    /// `condition` still returned `true`. In both cases mutex is locked again when this function returns. If robust
+    /// mutex is reacquired in inconsistent state, returns [`LockStatus::AcquiredInconsistent`] immediately, without
+    /// checking `condition`.
+    ///
+    /// # Errors
+    /// Same as [`wait_timeout`](#method.wait_timeout).
+    pub fn wait_timeout_while<T: Send, F: FnMut() -> bool>(
+        &mut self,
+        mutex: &mut SharedMutex<T>,
+        timeout: Duration,
+        mut condition: F,
+    ) -> Result<Option<LockStatus>, SyncError> {
+        // deadline that doesn't fit into Instant is effectively infinite
+        let deadline = Instant::now().checked_add(timeout);
+        while condition() {
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => timeout,
+            };
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            if self.wait_timeout(mutex, remaining)? == Some(LockStatus::AcquiredInconsistent) {
+                return Ok(Some(LockStatus::AcquiredInconsistent));
+            }
+        }
+        Ok(Some(LockStatus::Acquired))
     }
 
     /// Notifies one of processes that are waiting on this condvar
     ///
     /// # Errors
-    /// If any pthread call fails, returns error from [`last_os_error`]. For possible errors see [`pthread_cond_signal`](https://man7.org/linux/man-pages/man3/pthread_cond_broadcast.3p.html).
+    /// If any pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_cond_signal`](https://man7.org/linux/man-pages/man3/pthread_cond_broadcast.3p.html).
+    pub fn notify_one(&self) -> Result<(), SyncError> {
+        check_pthread_err("pthread_cond_signal", unsafe {
+            pthread_cond_signal(self.condvar.raw_ptr())
+        })?;
+        Ok(())
+    }
+
+    /// Destroys condvar explicitly, returning error instead of panicking like [`Drop`] does.
+    ///
+    /// Unlike [`Drop`], condvar is destroyed regardless of which process created it, so ownership can be transferred
+    /// to any process. Only one process must destroy the condvar, and other processes must not use it afterwards.
+    /// Memory is unmapped in calling process in any case, and condvar is not destroyed again on drop.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_cond_destroy`](https://man7.org/linux/man-pages/man3/pthread_cond_destroy.3p.html).
+    pub fn into_destroyed(mut self) -> Result<(), SyncError> {
+        // no process has pid 0, so condvar is not destroyed again when self is dropped
+        self.owner_pid = 0;
+        match unsafe { pthread_cond_destroy(self.condvar.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_cond_destroy", err)),
+        }
+    }
+
+    /// Notifies up to `n` of processes that are waiting on this condvar
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn notify_one(&mut self) -> std::io::Result<()> {
-        check_libc_err(unsafe { pthread_cond_signal(self.condvar.get_mut()) })?;
+    /// Implemented as `n` successive [`notify_one`](#method.notify_one) calls, so **it is only a hint**: under races
+    /// fewer processes may be woken (e.g. if some of signals arrive before processes start waiting), or more of them
+    /// (due to spurious wakeups). Waiters must still check condition they are waiting for, see
+    /// [`wait_while`](#method.wait_while).
+    ///
+    /// # Errors
+    /// Same as [`notify_one`](#method.notify_one). Processes notified before failure stay notified.
+    pub fn notify_n(&self, n: usize) -> Result<(), SyncError> {
+        for _ in 0..n {
+            self.notify_one()?;
+        }
         Ok(())
     }
 
     /// Notifies all processes that are waiting on this condvar
     ///
     /// # Errors
-    /// If any pthread call fails, returns error from [`last_os_error`]. For possible errors see [`pthread_cond_broadcast`](https://man7.org/linux/man-pages/man3/pthread_cond_broadcast.3p.html).
-    ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn notify_all(&mut self) -> std::io::Result<()> {
-        check_libc_err(unsafe { pthread_cond_broadcast(self.condvar.get_mut()) })?;
+    /// If any pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_cond_broadcast`](https://man7.org/linux/man-pages/man3/pthread_cond_broadcast.3p.html).
+    pub fn notify_all(&self) -> Result<(), SyncError> {
+        check_pthread_err("pthread_cond_broadcast", unsafe {
+            pthread_cond_broadcast(self.condvar.raw_ptr())
+        })?;
         Ok(())
     }
+
+    /// Notifies all processes that are waiting on this condvar, returning number of waiting processes.
+    ///
+    /// **Result is only a snapshot and is racy**, see [`new_counted`](#method.new_counted). Number is read right
+    /// before notifying, so processes which start waiting concurrently may or may not be counted, and processes which
+    /// woke up spuriously are not. Always returns `None` for condvars which are not counted.
+    ///
+    /// # Errors
+    /// Same as [`notify_all`](#method.notify_all).
+    pub fn notify_all_counted(&self) -> Result<Option<usize>, SyncError> {
+        let waiters = self.waiters();
+        self.notify_all()?;
+        Ok(waiters)
+    }
+
+    /// Returns number of processes waiting on counted condvar.
+    ///
+    /// **Result is only a snapshot and is racy**, see [`new_counted`](#method.new_counted). Always returns `None` for
+    /// condvars which are not counted.
+    pub fn waiters(&self) -> Option<usize> {
+        let waiters = self.waiters.as_ref()?;
+        // atomic is safe to access concurrently
+        Some(unsafe { waiters.get() }.load(Ordering::Relaxed))
+    }
+
+    fn count_waiter(&self, waiting: bool) {
+        if let Some(waiters) = &self.waiters {
+            // atomic is safe to access concurrently
+            let waiters = unsafe { waiters.get() };
+            if waiting {
+                waiters.fetch_add(1, Ordering::Relaxed);
+            } else {
+                waiters.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
 }
 
 impl Drop for SharedCondvar {
     fn drop(&mut self) {
         if getpid() == self.owner_pid {
-            check_libc_err(unsafe { pthread_cond_destroy(self.condvar.get_mut()) })
-                .expect("cannot destroy mutex");
+            match unsafe { pthread_cond_destroy(self.condvar.raw_ptr()) } {
+                0 => {}
+                err => report_primitive_drop_error(
+                    "destroy",
+                    "condvar",
+                    self.condvar.as_ptr(),
+                    self.owner_pid,
+                    SyncError::from_errno("pthread_cond_destroy", err),
+                ),
+            }
         }
     }
 }
 
-fn initialize_condvar(condvar: &mut pthread_cond_t) -> std::io::Result<()> {
+impl sealed::Sealed for SharedCondvar {}
+
+impl SharedPrimitive for SharedCondvar {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
+    }
+}
+
+fn initialize_condvar(condvar: &mut pthread_cond_t, clock: ClockId) -> Result<(), SyncError> {
     let mut attr: pthread_condattr_t = unsafe { std::mem::zeroed() };
-    check_libc_err(unsafe { pthread_condattr_init(&mut attr) })?;
+    check_pthread_err("pthread_condattr_init", unsafe {
+        pthread_condattr_init(&mut attr)
+    })?;
+
+    if let Err(err) = check_pshared_err("pthread_condattr_setpshared", unsafe {
+        pthread_condattr_setpshared(&mut attr, PTHREAD_PROCESS_SHARED)
+    }) {
+        destroy_condattr(attr).expect("cannot destroy condattr");
+        return Err(err);
+    }
 
-    check_libc_err(unsafe { pthread_condattr_setpshared(&mut attr, PTHREAD_PROCESS_SHARED) })
-        .expect("cannot set PTHREAD_PROCESS_SHARED");
+    // macOS has no pthread_condattr_setclock(), only default clock is supported there
+    #[cfg(not(target_os = "macos"))]
+    match unsafe { libc::pthread_condattr_setclock(&mut attr, clock.as_raw()) } {
+        0 => {}
+        err => {
+            destroy_condattr(attr).expect("cannot destroy condattr");
+            return Err(match err {
+                libc::EINVAL => SyncError::InvalidInput("clock is not supported by condvar"),
+                err => SyncError::from_errno("pthread_condattr_setclock", err),
+            });
+        }
+    }
+    #[cfg(target_os = "macos")]
+    if clock != ClockId::Realtime {
+        destroy_condattr(attr).expect("cannot destroy condattr");
+        return Err(SyncError::InvalidInput("clock is not supported by condvar"));
+    }
 
-    let ret = check_libc_err(unsafe { pthread_cond_init(condvar, &mut attr) });
+    let ret = check_pthread_err("pthread_cond_init", unsafe {
+        pthread_cond_init(condvar, &attr)
+    });
 
     destroy_condattr(attr).expect("cannot destroy condattr");
 
-    ret.map(|_| ())
+    ret
+}
+
+#[cfg(not(target_os = "macos"))]
+const TIMED_WAIT: &str = "pthread_cond_timedwait";
+#[cfg(target_os = "macos")]
+const TIMED_WAIT: &str = "pthread_cond_timedwait_relative_np";
+
+#[cfg(not(target_os = "macos"))]
+fn timed_wait(
+    condvar: *mut pthread_cond_t,
+    mutex: *mut pthread_mutex_t,
+    clock: ClockId,
+    timeout: Duration,
+) -> Result<c_int, SyncError> {
+    let deadline = crate::util::deadline_after(clock.as_raw(), timeout)?;
+    Ok(unsafe { libc::pthread_cond_timedwait(condvar, mutex, &deadline) })
+}
+
+#[cfg(target_os = "macos")]
+fn timed_wait(
+    condvar: *mut pthread_cond_t,
+    mutex: *mut pthread_mutex_t,
+    clock: ClockId,
+    timeout: Duration,
+) -> Result<c_int, SyncError> {
+    if clock != ClockId::Realtime {
+        return Err(SyncError::InvalidInput("clock is not supported by condvar"));
+    }
+    let timeout = libc::timespec {
+        tv_sec: libc::time_t::try_from(timeout.as_secs()).unwrap_or(libc::time_t::MAX),
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    Ok(unsafe { libc::pthread_cond_timedwait_relative_np(condvar, mutex, &timeout) })
 }
 
-fn destroy_condattr(mut attr: pthread_condattr_t) -> std::io::Result<()> {
-    check_libc_err(unsafe { pthread_condattr_destroy(&mut attr) })?;
+fn destroy_condattr(mut attr: pthread_condattr_t) -> Result<(), SyncError> {
+    check_pthread_err("pthread_condattr_destroy", unsafe {
+        pthread_condattr_destroy(&mut attr)
+    })?;
     Ok(())
 }
@@ -0,0 +1,269 @@
+use std::{
+    fmt,
+    sync::{PoisonError, RwLock},
+};
+
+use libc::c_int;
+#[cfg(unix)]
+use libc::pid_t;
+#[cfg(unix)]
+use libc::{EAGAIN, EBUSY, EDEADLK, EINTR, ENOTRECOVERABLE, EOWNERDEAD, ETIMEDOUT};
+
+#[cfg(unix)]
+use crate::util::{getpid, last_errno};
+
+/// Failed system call: name of the call and raw `errno` value it returned.
+///
+/// Displayed as e.g. `pthread_mutex_timedlock failed: Connection timed out (os error 110)`. Calls operating on memory
+/// region also carry its size, e.g. `mmap of 4194304 bytes failed: Cannot allocate memory (os error 12)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsError {
+    operation: &'static str,
+    errno: c_int,
+    bytes: Option<usize>,
+}
+
+impl OsError {
+    pub(crate) fn new(operation: &'static str, errno: c_int) -> Self {
+        Self {
+            operation,
+            errno,
+            bytes: None,
+        }
+    }
+
+    /// Records size of memory region the call was made for.
+    #[cfg(unix)]
+    pub(crate) fn with_bytes(self, bytes: usize) -> Self {
+        Self {
+            bytes: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Returns name of failed call, e.g. `"pthread_cond_timedwait"`.
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    /// Returns raw `errno` value, e.g. `ETIMEDOUT`.
+    pub fn errno(&self) -> c_int {
+        self.errno
+    }
+
+    /// Returns size of memory region in bytes, if the call operated on one, e.g. length of requested `mmap()`.
+    pub fn bytes(&self) -> Option<usize> {
+        self.bytes
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.operation)?;
+        if let Some(bytes) = self.bytes {
+            write!(f, " of {} bytes", bytes)?;
+        }
+        write!(
+            f,
+            " failed: {}",
+            std::io::Error::from_raw_os_error(self.errno)
+        )
+    }
+}
+
+/// Error returned by synchronization primitives.
+///
+/// Every variant caused by failed system call carries [`OsError`] with name of the call and raw `errno` value, see
+/// [`raw_os_error`](#method.raw_os_error) and [`operation`](#method.operation). Can be converted into [`std::io::Error`], so `?` works in functions returning [`std::io::Result`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SyncError {
+    /// Mapping or unmapping shared memory failed. Size of the region is available via [`OsError::bytes`].
+    MmapFailed(OsError),
+    /// Mapping with `MAP_HUGETLB` failed, most likely because no huge pages are reserved on the host
+    /// (see `/proc/sys/vm/nr_hugepages`).
+    HugePagesUnavailable(OsError),
+    /// Operation timed out (`ETIMEDOUT`).
+    Timeout(OsError),
+    /// Operation would block (`EBUSY`, `EAGAIN`).
+    WouldBlock(OsError),
+    /// Blocking call was interrupted by signal handler (`EINTR`).
+    ///
+    /// Only semaphore waits can be interrupted, pthread calls resume waiting after signal handler returns.
+    Interrupted(OsError),
+    /// Owner of robust mutex died while holding it (`EOWNERDEAD`).
+    ///
+    /// Lock **is** acquired by caller, see [`SharedMutex::mark_consistent`](crate::SharedMutex::mark_consistent).
+    OwnerDead(OsError),
+    /// Primitive is permanently unusable (`ENOTRECOVERABLE`), e.g. robust mutex was unlocked without being marked
+    /// consistent.
+    Destroyed(OsError),
+    /// Locking would deadlock (`EDEADLK`), e.g. relock of error-checking mutex.
+    Deadlock(OsError),
+    /// Primitive was unlocked by process which does not hold it (`EPERM`), e.g. unlock of error-checking or robust
+    /// mutex from non-owner.
+    NotOwner(OsError),
+    /// Platform doesn't support process-shared primitives (`PTHREAD_PROCESS_SHARED`), e.g. some embedded libcs.
+    ///
+    /// [`SharedMutex::new_thread_local`](crate::SharedMutex::new_thread_local) can be used by threads of single process
+    /// instead.
+    ProcessSharedUnsupported(OsError),
+    /// Invalid argument, detected before any system call was made.
+    InvalidInput(&'static str),
+    /// Consumer of [`SharedBroadcast`](crate::SharedBroadcast) fell behind the ring buffer and missed given number of
+    /// messages. Next receive returns the oldest message still available.
+    Lagged(u64),
+    /// Any other failure of system call.
+    Os(OsError),
+}
+
+impl SyncError {
+    /// Classifies raw `errno` value returned by `operation`.
+    #[cfg(unix)]
+    pub(crate) fn from_errno(operation: &'static str, errno: c_int) -> Self {
+        let err = OsError::new(operation, errno);
+        match errno {
+            ETIMEDOUT => SyncError::Timeout(err),
+            EBUSY | EAGAIN => SyncError::WouldBlock(err),
+            EINTR => SyncError::Interrupted(err),
+            EOWNERDEAD => SyncError::OwnerDead(err),
+            ENOTRECOVERABLE => SyncError::Destroyed(err),
+            EDEADLK => SyncError::Deadlock(err),
+            _ => SyncError::Os(err),
+        }
+    }
+
+    /// Creates error from current value of `errno` set by `operation`.
+    #[cfg(unix)]
+    pub(crate) fn last_os_error(operation: &'static str) -> Self {
+        Self::from_errno(operation, last_errno())
+    }
+
+    /// Returns failed system call, if error was caused by one.
+    pub fn os_error(&self) -> Option<&OsError> {
+        match self {
+            SyncError::MmapFailed(err)
+            | SyncError::HugePagesUnavailable(err)
+            | SyncError::Timeout(err)
+            | SyncError::WouldBlock(err)
+            | SyncError::Interrupted(err)
+            | SyncError::OwnerDead(err)
+            | SyncError::Destroyed(err)
+            | SyncError::Deadlock(err)
+            | SyncError::NotOwner(err)
+            | SyncError::ProcessSharedUnsupported(err)
+            | SyncError::Os(err) => Some(err),
+            SyncError::InvalidInput(_) | SyncError::Lagged(_) => None,
+        }
+    }
+
+    /// Returns raw `errno` value, if error was caused by failed system call.
+    pub fn raw_os_error(&self) -> Option<c_int> {
+        self.os_error().map(OsError::errno)
+    }
+
+    /// Returns name of failed system call, if error was caused by one.
+    pub fn operation(&self) -> Option<&'static str> {
+        self.os_error().map(OsError::operation)
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            SyncError::MmapFailed(_) => "shared memory mapping failed",
+            SyncError::HugePagesUnavailable(_) => {
+                "huge pages are not available, check that they are reserved"
+            }
+            SyncError::Timeout(_) => "operation timed out",
+            SyncError::WouldBlock(_) => "operation would block",
+            SyncError::Interrupted(_) => "operation was interrupted by signal",
+            SyncError::OwnerDead(_) => "owner of robust mutex died",
+            SyncError::Destroyed(_) => "primitive is not recoverable",
+            SyncError::Deadlock(_) => "deadlock would occur",
+            SyncError::NotOwner(_) => "primitive is not held by calling process",
+            SyncError::ProcessSharedUnsupported(_) => {
+                "process-shared primitives are not supported on this platform"
+            }
+            SyncError::InvalidInput(message) => return write!(f, "invalid input: {}", message),
+            SyncError::Lagged(missed) => {
+                return write!(f, "consumer lagged behind and missed {} messages", missed)
+            }
+            SyncError::Os(_) => "system call failed",
+        };
+        match self.os_error() {
+            Some(err) => write!(f, "{}: {}", description, err),
+            None => f.write_str(description),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// Function called when primitive cannot be cleaned up on drop, see [`set_drop_error_hook`].
+///
+/// Receives description of failed operation and error. For primitives description identifies primitive by its type and
+/// address, along with pid of process owning it and pid of calling process, e.g.
+/// `cannot destroy condvar at 0x7f0c2a8e5000 (owner pid 1234, current pid 1234)`. Error carries name of failed call
+/// and error number, see [`SyncError::operation`] and [`SyncError::raw_os_error`].
+pub type DropErrorHook = fn(&str, &SyncError);
+
+static DROP_ERROR_HOOK: RwLock<DropErrorHook> = RwLock::new(print_drop_error);
+
+/// Sets function called when primitive cannot be destroyed, unlocked or unmapped on drop.
+///
+/// [`Drop`] can't return errors, and panicking in it aborts process if it is already unwinding, so such errors are
+/// passed to hook instead. Default hook prints them to stderr. To handle destruction errors as [`Result`], destroy
+/// primitive explicitly, e.g. with [`SharedMutex::into_destroyed`](crate::SharedMutex::into_destroyed).
+pub fn set_drop_error_hook(hook: DropErrorHook) {
+    *DROP_ERROR_HOOK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = hook;
+}
+
+pub(crate) fn report_drop_error(operation: &str, err: SyncError) {
+    let hook = *DROP_ERROR_HOOK
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    hook(operation, &err);
+}
+
+/// Reports failure to `action` (e.g. "destroy") `primitive` located at `addr` on drop.
+#[cfg(unix)]
+pub(crate) fn report_primitive_drop_error<P>(
+    action: &str,
+    primitive: &str,
+    addr: *const P,
+    owner_pid: pid_t,
+    err: SyncError,
+) {
+    report_drop_error(
+        &format!(
+            "cannot {} {} at {:p} (owner pid {}, current pid {})",
+            action,
+            primitive,
+            addr,
+            owner_pid,
+            getpid()
+        ),
+        err,
+    );
+}
+
+fn print_drop_error(operation: &str, err: &SyncError) {
+    eprintln!("process-sync: {}: {}", operation, err);
+}
+
+impl From<SyncError> for std::io::Error {
+    fn from(err: SyncError) -> Self {
+        match err {
+            SyncError::InvalidInput(message) => {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+            }
+            SyncError::Lagged(_) => std::io::Error::other(err),
+            err => std::io::Error::from_raw_os_error(
+                err.raw_os_error().expect("error must have errno"),
+            ),
+        }
+    }
+}
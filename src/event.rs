@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    shared_memory::SharedMemoryObject,
+    SharedCondvar, SharedMutex,
+};
+
+/// Event flag that can be shared between processes.
+///
+/// One process sets event with [`set`](#method.set), and processes calling [`wait`](#method.wait) block until it is
+/// set. Event created with [`new`](#method.new) stays set until [`reset`](#method.reset) is called, releasing all
+/// waiting processes (manual-reset event). Event created with [`new_auto_reset`](#method.new_auto_reset) releases
+/// exactly one waiting process and is reset by it (auto-reset event).
+///
+/// Dropping event in creating process while it being waited will cause undefined behaviour.
+/// It is recommended to drop this event in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedEvent;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut started = SharedEvent::new()?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     println!("child wait()");
+///     started.wait()?;
+///     println!("child released");
+/// } else {
+///     sleep(Duration::from_millis(40));
+///     println!("parent set()");
+///     started.set()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// Output:
+/// ```txt
+/// child wait()
+/// parent set()
+/// child released
+/// ```
+pub struct SharedEvent {
+    mutex: SharedMutex,
+    condvar: SharedCondvar,
+    flag: SharedMemoryObject<AtomicBool>,
+    auto_reset: bool,
+}
+
+impl SharedEvent {
+    /// Creates new manual-reset [`SharedEvent`], which is not set
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Self::with_auto_reset(false)
+    }
+
+    /// Creates new auto-reset [`SharedEvent`], which is not set
+    ///
+    /// Each [`set`](#method.set) releases one waiting process, which resets the event. If no process is waiting,
+    /// event stays set until next process calls [`wait`](#method.wait).
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_auto_reset() -> Result<Self, SyncError> {
+        Self::with_auto_reset(true)
+    }
+
+    fn with_auto_reset(auto_reset: bool) -> Result<Self, SyncError> {
+        Ok(Self {
+            mutex: SharedMutex::new()?,
+            condvar: SharedCondvar::new()?,
+            flag: SharedMemoryObject::new(AtomicBool::new(false))?,
+            auto_reset,
+        })
+    }
+
+    /// Sets event, waking up all waiting processes, or one of them for auto-reset event.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn set(&mut self) -> Result<(), SyncError> {
+        self.mutex.lock()?;
+        self.flag().store(true, Ordering::Release);
+        let ret = if self.auto_reset {
+            self.condvar.notify_one()
+        } else {
+            self.condvar.notify_all()
+        };
+        self.mutex.unlock()?;
+        ret
+    }
+
+    /// Resets event, so that processes calling [`wait`](#method.wait) block again.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn reset(&mut self) -> Result<(), SyncError> {
+        self.mutex.lock()?;
+        self.flag().store(false, Ordering::Release);
+        self.mutex.unlock()
+    }
+
+    /// Waits until event is set.
+    ///
+    /// Returns immediately if event is already set. For auto-reset event, resets it before returning.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn wait(&mut self) -> Result<(), SyncError> {
+        self.mutex.lock()?;
+        // atomic is safe to access concurrently
+        let flag = unsafe { self.flag.get() };
+        let ret = self
+            .condvar
+            .wait_while(&mut self.mutex, || !flag.load(Ordering::Acquire));
+        if ret.is_ok() && self.auto_reset {
+            flag.store(false, Ordering::Release);
+        }
+        self.mutex.unlock()?;
+        ret.map(|_| ())
+    }
+
+    /// Returns `true` if event is set.
+    ///
+    /// **Result is only a snapshot**, event may be set or reset by another process right after this function returns.
+    pub fn is_set(&self) -> bool {
+        self.flag().load(Ordering::Acquire)
+    }
+
+    fn flag(&self) -> &AtomicBool {
+        // atomic is safe to access concurrently
+        unsafe { self.flag.get() }
+    }
+}
+
+impl sealed::Sealed for SharedEvent {}
+
+impl SharedPrimitive for SharedEvent {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.mutex.set_owner_pid(pid);
+        self.condvar.set_owner_pid(pid);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    os::unix::process::ExitStatusExt,
+    panic::{catch_unwind, AssertUnwindSafe},
+    process::ExitStatus,
+};
+
+use libc::{c_int, fork, pid_t, waitpid, WNOHANG};
+
+use crate::{error::SyncError, util::check_libc_err};
+
+/// Forks process, runs `child` in child process and `parent` in parent process, then waits for child to exit.
+///
+/// Both closures get mutable access to `state`, which usually holds primitives and shared objects used by both
+/// processes, e.g. tuple of references to them. Since only one of closures runs in each process, this is safe.
+///
+/// Child process exits right after `child` returns, with code `0`, or with code `101` if `child` panics. Destructors
+/// of values owned by the caller are not run in child process, which is what primitives of this crate expect.
+///
+/// Returns value returned by `parent` and exit status of child process.
+///
+/// # Errors
+/// If `fork()` or `waitpid()` fails, returns corresponding [`SyncError`]. `parent` is not called if `fork()` fails.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use process_sync::{fork_scope, SharedMutex};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut mutex = SharedMutex::with_data(0)?;
+///
+/// let (_, status) = fork_scope(
+///     &mut mutex,
+///     |mutex| *mutex.guard().unwrap() += 1,
+///     |_| (),
+/// )?;
+/// assert!(status.success());
+/// assert_eq!(*mutex.guard()?, 1);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub fn fork_scope<S: ?Sized, R>(
+    state: &mut S,
+    child: impl FnOnce(&mut S),
+    parent: impl FnOnce(&mut S) -> R,
+) -> Result<(R, ExitStatus), SyncError> {
+    let pid = check_libc_err("fork", unsafe { fork() })?;
+    if pid == 0 {
+        let code = match catch_unwind(AssertUnwindSafe(|| child(state))) {
+            Ok(()) => 0,
+            Err(_) => 101,
+        };
+        std::process::exit(code);
+    }
+
+    let ret = parent(state);
+    Ok((ret, reap_child(pid)?))
+}
+
+/// Waits for child process `pid` to exit and returns its exit status.
+///
+/// Child which exited stays zombie until it is reaped by parent, so every forked child must be reaped eventually.
+/// Exit code is available with [`ExitStatus::code`], and signal which terminated child with
+/// [`ExitStatusExt::signal`]. Waiting is restarted if it is interrupted by signal handler.
+///
+/// # Errors
+/// If `waitpid()` fails, e.g. `pid` is not a child of calling process or is already reaped, returns corresponding
+/// [`SyncError`]. For possible errors see [`waitpid`](https://man7.org/linux/man-pages/man3/waitpid.3p.html).
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::reap_child;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     std::process::exit(3);
+/// }
+/// assert_eq!(reap_child(pid)?.code(), Some(3));
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub fn reap_child(pid: pid_t) -> Result<ExitStatus, SyncError> {
+    loop {
+        match wait_child(pid, 0) {
+            Err(SyncError::Interrupted(_)) => continue,
+            ret => return ret.map(|status| status.expect("waitpid() must wait for child")),
+        }
+    }
+}
+
+/// Reaps child process `pid` if it has already exited, without blocking.
+///
+/// Returns `Ok(Some(status))` if child was reaped and `Ok(None)` if it is still running, see [`reap_child`].
+///
+/// # Errors
+/// Same as [`reap_child`].
+pub fn try_reap(pid: pid_t) -> Result<Option<ExitStatus>, SyncError> {
+    loop {
+        match wait_child(pid, WNOHANG) {
+            Err(SyncError::Interrupted(_)) => continue,
+            ret => return ret,
+        }
+    }
+}
+
+/// Calls `waitpid()`, returning `None` if child didn't exit yet with `WNOHANG`.
+fn wait_child(pid: pid_t, options: c_int) -> Result<Option<ExitStatus>, SyncError> {
+    let mut status: c_int = 0;
+    match check_libc_err("waitpid", unsafe { waitpid(pid, &mut status, options) })? {
+        0 => Ok(None),
+        _ => Ok(Some(ExitStatus::from_raw(status))),
+    }
+}
@@ -1,21 +1,115 @@
 //! Synchronization primitives for using in multiprocess environments.
 //!
-//! Implementation is based on `pthread` bindings.
+//! Implementation is based on `pthread` bindings. On Windows only [`SharedMutex`] is available, implemented with
+//! named kernel mutex objects.
+//!
+//! All primitives are [`Send`] and [`Sync`] (given that protected data is), so they can be used by several threads
+//! of each process as well. Locks are owned by threads, as `pthread` requires: guards are not [`Send`], and lock
+//! acquired by one thread must be released by the same thread.
 
 #![warn(missing_docs)]
 // #![deny(missing_doc_code_examples)]
 
+#[cfg(unix)]
+mod alloc;
+#[cfg(unix)]
+mod arc;
+#[cfg(unix)]
+mod atomic;
+#[cfg(unix)]
+mod barrier;
+#[cfg(unix)]
+mod broadcast;
+#[cfg(unix)]
+mod channel;
+#[cfg(unix)]
+mod clock;
+#[cfg(unix)]
 mod condvar;
+mod error;
+#[cfg(unix)]
+mod event;
+#[cfg(unix)]
+mod fork;
+#[cfg(unix)]
+mod monitor;
+#[cfg(unix)]
 mod mutex;
+#[cfg(unix)]
+mod notify;
+#[cfg(unix)]
+mod once;
+#[cfg(unix)]
+mod rwlock;
+#[cfg(unix)]
+mod semaphore;
+#[cfg(unix)]
 mod shared_memory;
+#[cfg(unix)]
+mod spinlock;
+#[cfg(unix)]
+mod spsc;
+#[cfg(unix)]
+mod ticket_lock;
+#[cfg(unix)]
+mod upgradable_rwlock;
+#[cfg(unix)]
 mod util;
+#[cfg(windows)]
+mod windows;
 
+#[cfg(unix)]
 #[doc(hidden)]
 pub mod private {
     pub use crate::shared_memory::SharedMemoryObject;
     pub use crate::util::check_libc_err;
 }
 
+#[cfg(unix)]
+pub use alloc::{HeapAllocator, RegionAllocator, SharedAllocator};
+#[cfg(unix)]
+pub use arc::{SharedArc, SharedPrimitive};
+#[cfg(unix)]
+pub use atomic::{
+    fence, SharedAtomicI32, SharedAtomicI64, SharedAtomicIsize, SharedAtomicU32, SharedAtomicU64,
+    SharedAtomicUsize,
+};
+#[cfg(unix)]
+pub use barrier::{BarrierWaitResult, SharedBarrier};
+#[cfg(unix)]
+pub use broadcast::SharedBroadcast;
+#[cfg(unix)]
+pub use channel::SharedChannel;
+#[cfg(unix)]
+pub use clock::ClockId;
+#[cfg(unix)]
 pub use condvar::SharedCondvar;
-pub use mutex::SharedMutex;
-pub use shared_memory::SharedMemoryObject;
+pub use error::{set_drop_error_hook, DropErrorHook, OsError, SyncError};
+#[cfg(unix)]
+pub use event::SharedEvent;
+#[cfg(unix)]
+pub use fork::{fork_scope, reap_child, try_reap};
+#[cfg(unix)]
+pub use monitor::{SharedMonitor, SharedMonitorGuard};
+#[cfg(unix)]
+pub use mutex::{LockStatus, MutexKind, MutexProtocol, PoisonError, SharedMutex, SharedMutexGuard};
+#[cfg(unix)]
+pub use notify::SharedNotify;
+#[cfg(unix)]
+pub use once::SharedOnce;
+#[cfg(unix)]
+pub use rwlock::SharedRwLock;
+#[cfg(unix)]
+pub use semaphore::SharedSemaphore;
+#[cfg(unix)]
+pub use shared_memory::{unlink_named, SharedMemoryObject};
+#[cfg(unix)]
+pub use spinlock::SharedSpinlock;
+#[cfg(unix)]
+pub use spsc::SharedSpscQueue;
+#[cfg(unix)]
+pub use ticket_lock::SharedTicketLock;
+#[cfg(unix)]
+pub use upgradable_rwlock::SharedUpgradableRwLock;
+#[cfg(windows)]
+pub use windows::{LockStatus, SharedMutex};
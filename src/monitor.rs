@@ -0,0 +1,156 @@
+use std::ops::{Deref, DerefMut};
+
+use libc::{pid_t, pthread_cond_t};
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    SharedCondvar, SharedMutex, SharedMutexGuard,
+};
+
+/// Monitor that can be shared between processes: data protected by mutex, paired with condvar.
+///
+/// [`SharedCondvar::wait`] accepts any mutex, and waiting with different mutexes on the same condvar is undefined
+/// behaviour. Monitor owns both mutex and condvar, and condvar can only be waited through
+/// [`SharedMonitorGuard`], so they are always used together.
+///
+/// Mutex, protected data and condvar are placed in a single shared memory mapping, so monitor costs one `mmap()`
+/// call and one page (for small data), half of creating [`SharedMutex`] and [`SharedCondvar`] separately.
+///
+/// Dropping monitor in creating process while it being locked or waited will cause undefined behaviour.
+/// It is recommended to drop this monitor in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedMonitor;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut monitor = SharedMonitor::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     let guard = monitor.lock()?;
+///     let guard = guard.wait_while(|value| *value == 0)?;
+///     assert_eq!(*guard, 42);
+/// } else {
+///     let mut guard = monitor.lock()?;
+///     *guard = 42;
+///     guard.notify_all()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedMonitor<T: Send = ()> {
+    // condvar lives in mapping owned by mutex, so it must be dropped first
+    condvar: SharedCondvar,
+    mutex: SharedMutex<T>,
+}
+
+impl<T: Send> SharedMonitor<T> {
+    /// Creates new [`SharedMonitor`] protecting `data`
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new(data: T) -> Result<Self, SyncError> {
+        let (mutex, condvar) = SharedMutex::with_data_packed::<pthread_cond_t>(data)?;
+        let condvar = unsafe { SharedCondvar::new_in_place(condvar.cast()) }?;
+        Ok(Self { condvar, mutex })
+    }
+
+    /// Locks monitor and returns guard that unlocks it when dropped.
+    ///
+    /// # Errors
+    /// Same as [`SharedMutex::lock`].
+    pub fn lock(&mut self) -> Result<SharedMonitorGuard<'_, T>, SyncError> {
+        Ok(SharedMonitorGuard {
+            guard: self.mutex.guard()?,
+            condvar: &mut self.condvar,
+        })
+    }
+}
+
+impl<T: Send> sealed::Sealed for SharedMonitor<T> {}
+
+impl<T: Send> SharedPrimitive for SharedMonitor<T> {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.mutex.set_owner_pid(pid);
+        self.condvar.set_owner_pid(pid);
+    }
+}
+
+/// Scope guard returned by [`SharedMonitor::lock`].
+///
+/// Dereferences to data protected by monitor. Monitor is unlocked when guard is dropped.
+#[must_use = "if unused the monitor will immediately unlock"]
+pub struct SharedMonitorGuard<'a, T: Send = ()> {
+    guard: SharedMutexGuard<'a, T>,
+    condvar: &'a mut SharedCondvar,
+}
+
+impl<'a, T: Send> SharedMonitorGuard<'a, T> {
+    /// Waits for notification, returning guard which holds the lock again.
+    ///
+    /// Monitor is unlocked while waiting, see [`SharedCondvar::wait_guard`]. Spurious wakeups are possible, prefer
+    /// [`wait_while`](#method.wait_while).
+    ///
+    /// # Errors
+    /// Same as [`SharedCondvar::wait_guard`].
+    pub fn wait(self) -> Result<Self, SyncError> {
+        Ok(Self {
+            guard: self.condvar.wait_guard(self.guard)?,
+            condvar: self.condvar,
+        })
+    }
+
+    /// Waits for notifications while `condition` returns `true` for protected data.
+    ///
+    /// # Errors
+    /// Same as [`SharedCondvar::wait_guard`].
+    pub fn wait_while(
+        mut self,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> Result<Self, SyncError> {
+        while condition(&mut self.guard) {
+            self = self.wait()?;
+        }
+        Ok(self)
+    }
+
+    /// Notifies one of processes waiting on this monitor.
+    ///
+    /// # Errors
+    /// Same as [`SharedCondvar::notify_one`].
+    pub fn notify_one(&self) -> Result<(), SyncError> {
+        self.condvar.notify_one()
+    }
+
+    /// Notifies all processes waiting on this monitor.
+    ///
+    /// # Errors
+    /// Same as [`SharedCondvar::notify_all`].
+    pub fn notify_all(&self) -> Result<(), SyncError> {
+        self.condvar.notify_all()
+    }
+}
+
+impl<T: Send> Deref for SharedMonitorGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: Send> DerefMut for SharedMonitorGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
@@ -1,22 +1,64 @@
+use std::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+    time::Duration,
+};
+
+use libc::{
+    c_int, clockid_t, pid_t, pthread_mutex_destroy, pthread_mutex_init, pthread_mutex_lock,
+    pthread_mutex_t, pthread_mutex_trylock, pthread_mutex_unlock, pthread_mutexattr_destroy,
+    pthread_mutexattr_init, pthread_mutexattr_setpshared, pthread_mutexattr_settype,
+    pthread_mutexattr_t, timespec, CLOCK_MONOTONIC, CLOCK_REALTIME, EBUSY, EOWNERDEAD, EPERM,
+    ETIMEDOUT, PTHREAD_MUTEX_DEFAULT, PTHREAD_MUTEX_ERRORCHECK, PTHREAD_MUTEX_NORMAL,
+    PTHREAD_MUTEX_RECURSIVE, PTHREAD_PROCESS_SHARED,
+};
+#[cfg(not(target_os = "macos"))]
 use libc::{
-    pid_t, pthread_mutex_destroy, pthread_mutex_init, pthread_mutex_lock, pthread_mutex_t,
-    pthread_mutex_unlock, pthread_mutexattr_destroy, pthread_mutexattr_init,
-    pthread_mutexattr_setpshared, pthread_mutexattr_t, PTHREAD_MUTEX_INITIALIZER,
-    PTHREAD_PROCESS_SHARED,
+    pthread_mutex_consistent, pthread_mutex_timedlock, pthread_mutexattr_setrobust,
+    PTHREAD_MUTEX_ROBUST, PTHREAD_MUTEX_STALLED,
 };
+#[cfg(not(target_os = "macos"))]
+use libc::{PTHREAD_PRIO_INHERIT, PTHREAD_PRIO_NONE, PTHREAD_PRIO_PROTECT};
+
+// not exposed by libc crate for macOS, values from <pthread.h>
+#[cfg(target_os = "macos")]
+const PTHREAD_PRIO_NONE: c_int = 0;
+#[cfg(target_os = "macos")]
+const PTHREAD_PRIO_INHERIT: c_int = 1;
+#[cfg(target_os = "macos")]
+const PTHREAD_PRIO_PROTECT: c_int = 2;
 
 use crate::{
+    alloc::{HeapAllocator, RegionAllocator},
+    arc::{sealed, SharedPrimitive},
+    error::{report_primitive_drop_error, OsError, SyncError},
     shared_memory::SharedMemoryObject,
-    util::{check_libc_err, getpid},
+    util::{check_pshared_err, check_pthread_err, deadline_after, getpid},
 };
 
 /// Simple mutex that can be shared between processes.
 ///
 /// This mutex is **NOT** recursive, so it will deadlock on relock.
 ///
+/// Mutex is not fair: waiting processes are not woken up in any particular order, and process which just unlocked
+/// mutex may lock it again before them. Use [`SharedTicketLock`](crate::SharedTicketLock) if waiting processes must
+/// not be starved.
+///
+/// Mutex is destroyed only when it is dropped in creating process. Handles inherited by child processes, and by their
+/// children in turn, carry pid of creating process too, so dropping them only unmaps memory, regardless of order in
+/// which processes exit. Use [`SharedArc`](crate::SharedArc) to destroy mutex in whichever process drops it last, or
+/// [`forget_on_drop`](crate::SharedPrimitive::forget_on_drop) to never destroy it.
+///
 /// Dropping mutex in creating process while mutex being locked or waited will cause undefined behaviour.
 /// It is recommended to drop this mutex in creating process only after no other process has access to it.
 ///
+/// Mutex is [`Send`] and [`Sync`], since `PTHREAD_PROCESS_SHARED` mutex can be used by any thread of any process.
+/// Note that `pthread` mutexes are owned by threads rather than processes: mutex must be unlocked by the same thread
+/// which locked it.
+///
 /// For more information see [`pthread_mutex_init`](https://man7.org/linux/man-pages/man3/pthread_mutex_destroy.3p.html), [`pthread_mutex_lock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html) and [`SharedMemoryObject`].
 ///
 /// # Example
@@ -67,82 +109,1354 @@ use crate::{
 /// parent locked
 /// parent unlock()
 /// ```
-pub struct SharedMutex {
-    mutex: SharedMemoryObject<pthread_mutex_t>,
+///
+/// # Protecting data
+/// Mutex can also own data which is only accessible while mutex is locked, similarly to [`std::sync::Mutex`].
+/// [`guard`](#method.guard) is the recommended way to lock mutex even without data, since guard unlocks mutex when
+/// dropped, so it can't be left locked by mistake.
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedMutex;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut counter = SharedMutex::with_data(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// *counter.guard()? += 1;
+/// counter.with_locked(|counter| *counter += 1)?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedMutex<T: Send = ()> {
+    state: SharedMemoryObject<MutexState<T>>,
+    tracker: Option<SharedMemoryObject<AtomicI32>>,
+    poison: Option<SharedMemoryObject<AtomicBool>>,
+    level: Option<u32>,
+    config: MutexConfig,
+    /// Number of `try_lock` attempts made by [`lock_adaptive`](SharedMutex::lock_adaptive) before blocking.
+    spin_count: u32,
     owner_pid: pid_t,
 }
 
+#[repr(C)]
+struct MutexState<T> {
+    mutex: pthread_mutex_t,
+    data: UnsafeCell<T>,
+}
+
+// data is only accessed while mutex is locked
+unsafe impl<T: Send> Sync for MutexState<T> {}
+
+/// Default number of attempts to lock without blocking made by [`SharedMutex::lock_adaptive`].
+const DEFAULT_SPIN_COUNT: u32 = 100;
+
 impl SharedMutex {
+    /// Size of wrapped `pthread_mutex_t` in bytes.
+    ///
+    /// Can be used to statically check that layout matches `pthread_mutex_t` of C code sharing the mapping.
+    pub const MUTEX_SIZE: usize = std::mem::size_of::<pthread_mutex_t>();
+
+    /// Alignment of wrapped `pthread_mutex_t` in bytes.
+    pub const MUTEX_ALIGN: usize = std::mem::align_of::<pthread_mutex_t>();
+
     /// Creates new [`SharedMutex`]
     ///
     /// # Errors
-    /// If allocation or initialization fails returns error from [`last_os_error`].
+    /// If platform doesn't support process-shared mutexes, returns [`SyncError::ProcessSharedUnsupported`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Self::with_attr((), MutexConfig::Default)
+    }
+
+    /// Creates new [`SharedMutex`] which makes `spin_count` attempts to lock without blocking in
+    /// [`lock_adaptive`](#method.lock_adaptive)
+    ///
+    /// See [`with_spin_count`](#method.with_spin_count).
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_adaptive(spin_count: u32) -> Result<Self, SyncError> {
+        Ok(Self::new()?.with_spin_count(spin_count))
+    }
+
+    /// Creates new [`SharedMutex`] in memory provided by caller, instead of allocating separate mapping
+    ///
+    /// This allows to pack many primitives and data into one shared memory mapping. `ptr` must have size and alignment
+    /// of [`MUTEX_SIZE`](#associatedconstant.MUTEX_SIZE) and [`MUTEX_ALIGN`](#associatedconstant.MUTEX_ALIGN).
+    /// Memory is **not** unmapped when mutex is dropped, but mutex is destroyed by creating process as usual.
+    ///
+    /// # Safety
+    /// `ptr` must point to memory mapped with `MAP_SHARED`, so that other processes see the same mutex, and must not
+    /// be accessed other than through returned mutex (or its copies in other processes). Memory must stay mapped
+    /// until all processes drop the mutex.
+    ///
+    /// # Errors
+    /// If `ptr` is null or not aligned, returns [`SyncError::InvalidInput`].
+    /// If initialization fails returns corresponding [`SyncError`].
+    pub unsafe fn new_in_place(ptr: *mut pthread_mutex_t) -> Result<Self, SyncError> {
+        if ptr.is_null() || !ptr.is_aligned() {
+            return Err(SyncError::InvalidInput(
+                "mutex pointer must be non-null and aligned",
+            ));
+        }
+        initialize_mutex(&mut *ptr, MutexConfig::Default)?;
+        // MutexState<()> is repr(C) and has the same layout as pthread_mutex_t
+        let state = SharedMemoryObject::from_raw_ptr(ptr.cast::<MutexState<()>>());
+
+        let owner_pid = getpid();
+        Ok(Self {
+            state,
+            tracker: None,
+            poison: None,
+            level: None,
+            config: MutexConfig::Default,
+            spin_count: DEFAULT_SPIN_COUNT,
+            owner_pid,
+        })
+    }
+
+    /// Creates new [`SharedMutex`] in memory allocated by `allocator`
+    ///
+    /// With [`SharedAllocator`](crate::SharedAllocator) this is equivalent to [`new`](#method.new). With
+    /// [`HeapAllocator`](crate::HeapAllocator) mutex lives in private memory of calling process, which allows to test
+    /// code built on top of it in single process, without forking. Such mutex doesn't synchronize different processes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # use process_sync::{HeapAllocator, SharedMutex};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// #
+    /// let mut mutex = SharedMutex::new_in(HeapAllocator)?;
+    /// mutex.lock()?;
+    /// assert!(!mutex.try_lock()?);
+    /// mutex.unlock()?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_in(allocator: impl RegionAllocator + 'static) -> Result<Self, SyncError> {
+        Self::init(
+            SharedMemoryObject::new_uninit_in(allocator)?,
+            (),
+            MutexConfig::Default,
+        )
+    }
+
+    /// Creates new [`SharedMutex`] which is not process-shared, for use by threads of single process
+    ///
+    /// This is a fallback for platforms which don't support process-shared mutexes (see
+    /// [`SyncError::ProcessSharedUnsupported`]): mutex lives in private memory of calling process, like with
+    /// [`HeapAllocator`], and is initialized without `PTHREAD_PROCESS_SHARED`. **Such mutex doesn't synchronize
+    /// different processes**, child process gets independent copy of it after `fork()`.
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_thread_local() -> Result<Self, SyncError> {
+        Self::init(
+            SharedMemoryObject::new_uninit_in(HeapAllocator)?,
+            (),
+            MutexConfig::ThreadLocal,
+        )
+    }
+
+    /// Creates new tracked [`SharedMutex`]
+    ///
+    /// Tracked mutex additionally records pid of process holding it in separate shared memory mapping, which can be
+    /// queried with [`owner_pid`](#method.owner_pid) to diagnose deadlocks. Tracking is best-effort and racy, so it
+    /// must only be used for diagnostics. Mutexes created with other constructors do not pay for it.
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_tracked() -> Result<Self, SyncError> {
+        let mut mutex = Self::new()?;
+        mutex.tracker = Some(SharedMemoryObject::new(AtomicI32::new(0))?);
+        Ok(mutex)
+    }
+
+    /// Creates new leveled [`SharedMutex`]
+    ///
+    /// Leveled mutexes detect lock ordering violations, which may lead to deadlocks: in debug builds locking mutex
+    /// panics if calling thread already holds leveled mutex of the same or higher `level`. Mutexes must therefore be
+    /// locked in order of increasing levels in all processes. Violation is detected deterministically, even if no
+    /// deadlock actually occurs. [`try_lock`](#method.try_lock) can't deadlock, so it is not checked.
+    ///
+    /// Held levels are tracked per thread, so in release builds checks are compiled out. Child process inherits levels
+    /// held by forking thread, so leveled mutexes should not be held while forking.
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_leveled(level: u32) -> Result<Self, SyncError> {
+        let mut mutex = Self::new()?;
+        mutex.level = Some(level);
+        Ok(mutex)
+    }
+
+    /// Creates new robust [`SharedMutex`]
+    ///
+    /// If process holding robust mutex terminates, next process to lock it acquires it in inconsistent state
+    /// (see [`LockStatus::AcquiredInconsistent`]) instead of blocking forever. Such mutex must be marked consistent
+    /// with [`mark_consistent`](#method.mark_consistent) before it is unlocked, otherwise it becomes permanently unusable.
+    ///
+    /// For more information see [`pthread_mutexattr_setrobust`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_setrobust.3.html).
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`]. On macOS, which has no robust
+    /// mutexes, returns [`SyncError::InvalidInput`].
+    pub fn new_robust() -> Result<Self, SyncError> {
+        Self::with_attr((), MutexConfig::Robust)
+    }
+
+    /// Creates new error-checking [`SharedMutex`]
+    ///
+    /// Relocking such mutex from the process already holding it fails instead of deadlocking:
+    /// [`lock`](#method.lock) returns [`SyncError::Deadlock`].
+    ///
+    /// For more information see [`pthread_mutexattr_settype`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_settype.3p.html).
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    ///
+    pub fn new_error_checking() -> Result<Self, SyncError> {
+        Self::with_type(MutexKind::ErrorCheck)
+    }
+
+    /// Creates new [`SharedMutex`] using given priority `protocol`
+    ///
+    /// Priority protocols prevent priority inversion, when low-priority process holding the mutex starves high-priority
+    /// process waiting for it. **They only have effect under real-time scheduling policies** (`SCHED_FIFO`,
+    /// `SCHED_RR`), for other policies mutex behaves as usual.
+    ///
+    /// For more information see [`pthread_mutexattr_setprotocol`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_setprotocol.3p.html)
+    /// and [`pthread_mutexattr_setprioceiling`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_setprioceiling.3p.html).
+    ///
+    /// # Errors
+    /// If priority ceiling is out of range of real-time priorities, returns [`SyncError::Os`] with `EINVAL`.
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_with_protocol(protocol: MutexProtocol) -> Result<Self, SyncError> {
+        Self::with_attr((), MutexConfig::Protocol(protocol))
+    }
+
+    /// Creates new recursive [`SharedMutex`]
+    ///
+    /// Such mutex can be locked multiple times by the process already holding it. Each successful
+    /// [`lock`](#method.lock) increments lock count and each [`unlock`](#method.unlock) decrements it,
+    /// mutex is released only when count drops to zero. **Number of unlocks must match number of locks**,
+    /// otherwise mutex stays locked forever.
+    ///
+    /// For more information see [`pthread_mutexattr_settype`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_settype.3p.html).
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new_recursive() -> Result<Self, SyncError> {
+        Self::with_type(MutexKind::Recursive)
+    }
+
+    /// Creates new [`SharedMutex`] of given `kind`
+    ///
+    /// Kind determines what happens when mutex is misused by the thread holding it, or by the one which doesn't hold it:
+    ///
+    /// | Kind              | Relock by holder                 | Unlock by non-holder    | Unlock of unlocked mutex |
+    /// |-------------------|----------------------------------|-------------------------|--------------------------|
+    /// | [`Normal`][n]     | deadlocks                        | undefined behaviour     | undefined behaviour      |
+    /// | [`ErrorCheck`][e] | [`SyncError::Deadlock`]          | [`SyncError::NotOwner`] | [`SyncError::NotOwner`]  |
+    /// | [`Recursive`][r]  | succeeds, increments lock count  | [`SyncError::NotOwner`] | [`SyncError::NotOwner`]  |
+    /// | [`Default`][d]    | undefined behaviour              | undefined behaviour     | undefined behaviour      |
+    ///
+    /// [`new`](#method.new) creates [`MutexKind::Default`] mutex, which is [`MutexKind::Normal`] on Linux and macOS.
+    /// [`new_error_checking`](#method.new_error_checking) and [`new_recursive`](#method.new_recursive) are shortcuts
+    /// for corresponding kinds.
+    ///
+    /// For more information see [`pthread_mutexattr_settype`](https://man7.org/linux/man-pages/man3/pthread_mutexattr_settype.3p.html).
+    ///
+    /// [n]: MutexKind::Normal
+    /// [e]: MutexKind::ErrorCheck
+    /// [r]: MutexKind::Recursive
+    /// [d]: MutexKind::Default
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn with_type(kind: MutexKind) -> Result<Self, SyncError> {
+        Self::with_attr((), MutexConfig::Type(kind))
+    }
+
+    /// Creates new [`SharedMutex`] with attributes built by caller
+    ///
+    /// This allows any combination of attributes, e.g. to match mutex of C code sharing the mapping exactly, instead
+    /// of constructors above which set one attribute each. Type, robustness, protocol and priority ceiling are read
+    /// from `attr` and set on new attributes object, which is also used to initialize mutex again in
+    /// [`reinitialize`](#method.reinitialize). Mutex is always process-shared, so caller doesn't need to set
+    /// `PTHREAD_PROCESS_SHARED`. `attr` itself is not modified or kept: caller still owns it and may destroy it right
+    /// after this call.
+    ///
+    /// # Safety
+    /// `attr` must be initialized with `pthread_mutexattr_init()` and not destroyed yet.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use std::error::Error;
+    /// #
+    /// # use libc::{
+    /// #     pthread_mutexattr_destroy, pthread_mutexattr_init, pthread_mutexattr_setrobust,
+    /// #     pthread_mutexattr_settype, pthread_mutexattr_t, PTHREAD_MUTEX_ERRORCHECK, PTHREAD_MUTEX_ROBUST,
+    /// # };
+    /// #
+    /// # use process_sync::{SharedMutex, SyncError};
+    /// #
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// #
+    /// let mutex = unsafe {
+    ///     let mut attr: pthread_mutexattr_t = std::mem::zeroed();
+    ///     assert_eq!(pthread_mutexattr_init(&mut attr), 0);
+    ///     assert_eq!(pthread_mutexattr_settype(&mut attr, PTHREAD_MUTEX_ERRORCHECK), 0);
+    ///     assert_eq!(pthread_mutexattr_setrobust(&mut attr, PTHREAD_MUTEX_ROBUST), 0);
+    ///     let mutex = SharedMutex::new_with_attr(&attr);
+    ///     pthread_mutexattr_destroy(&mut attr);
+    ///     mutex
+    /// };
+    ///
+    /// let mut mutex = mutex?;
+    /// mutex.lock()?;
+    /// assert!(matches!(mutex.lock(), Err(SyncError::Deadlock(_))));
+    /// mutex.unlock()?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// If reading attributes, allocation or initialization fails returns corresponding [`SyncError`].
+    /// For possible errors see [`pthread_mutex_init`](https://man7.org/linux/man-pages/man3/pthread_mutex_init.3p.html).
+    pub unsafe fn new_with_attr(attr: &pthread_mutexattr_t) -> Result<Self, SyncError> {
+        Self::with_attr((), MutexConfig::Attr(MutexAttrs::read(attr)?))
+    }
+}
+
+impl<T: Send> SharedMutex<T> {
+    /// Creates new [`SharedMutex`] protecting `data`
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error.
-    pub fn new() -> std::io::Result<Self> {
-        let mut mutex = SharedMemoryObject::new(PTHREAD_MUTEX_INITIALIZER)?;
-        initialize_mutex(mutex.get_mut())?;
+    /// Mutex and data are placed in the same shared memory mapping. Data can only be accessed through
+    /// [`SharedMutexGuard`] returned by [`guard`](#method.guard), so it is never accessed without holding the lock.
+    /// Data is dropped together with mutex, i.e. only in creating process.
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn with_data(data: T) -> Result<Self, SyncError> {
+        Self::with_attr(data, MutexConfig::Default)
+    }
+
+    /// Enables poisoning for this mutex, like in [`std::sync::Mutex`]
+    ///
+    /// Poisoning mutex records in separate shared memory mapping whether any process panicked while holding
+    /// [`SharedMutexGuard`], so that data may be left in inconsistent state. For robust mutexes (see
+    /// [`new_robust`](SharedMutex::new_robust)) death of process holding the mutex is recorded as well. Poisoning is
+    /// reported by [`guard_with_poison`](#method.guard_with_poison), other methods ignore it.
+    ///
+    /// Poisoning must be enabled right after construction, before mutex is shared with other processes:
+    /// ```rust
+    /// # use process_sync::SharedMutex;
+    /// let mut mutex = SharedMutex::new_robust()?.with_poisoning()?;
+    /// # Ok::<(), process_sync::SyncError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn with_poisoning(mut self) -> Result<Self, SyncError> {
+        self.poison = Some(SharedMemoryObject::new(AtomicBool::new(false))?);
+        Ok(self)
+    }
+
+    /// Sets number of attempts to lock without blocking made by [`lock_adaptive`](#method.lock_adaptive), `100` by
+    /// default
+    ///
+    /// Spin count is a setting of this handle, not of shared mutex, so processes may use different counts.
+    pub fn with_spin_count(mut self, spin_count: u32) -> Self {
+        self.spin_count = spin_count;
+        self
+    }
+
+    fn with_attr(data: T, config: MutexConfig) -> Result<Self, SyncError> {
+        Self::init(SharedMemoryObject::new_uninit()?, data, config)
+    }
+
+    /// Creates mutex protecting `data` together with uninitialized `U` in the same mapping, and returns pointer to it.
+    ///
+    /// Mutex owns the mapping, so `U` must be torn down before mutex is dropped.
+    pub(crate) fn with_data_packed<U: Sync + Send>(
+        data: T,
+    ) -> Result<(Self, *mut MaybeUninit<U>), SyncError> {
+        #[repr(C)]
+        struct Packed<T, U> {
+            // must be the first field, so that mutex state handle spans the whole mapping
+            state: MutexState<T>,
+            extra: MaybeUninit<U>,
+        }
+
+        let mut packed = SharedMemoryObject::<Packed<T, U>>::new_uninit()?;
+        let extra = unsafe { std::ptr::addr_of_mut!((*packed.get_mut().as_mut_ptr()).extra) };
+        let state = unsafe { packed.cast::<MaybeUninit<MutexState<T>>>() }?;
+        let mutex = Self::init(state, data, MutexConfig::Default)?;
+        Ok((mutex, extra))
+    }
+
+    fn init(
+        mut state: SharedMemoryObject<MaybeUninit<MutexState<T>>>,
+        data: T,
+        config: MutexConfig,
+    ) -> Result<Self, SyncError> {
+        // memory is zeroed, which is valid value to pass to pthread_mutex_init(), so no initializer is needed
+        let ptr = unsafe { state.get_mut() }.as_mut_ptr();
+        initialize_mutex(
+            unsafe { &mut *std::ptr::addr_of_mut!((*ptr).mutex) },
+            config,
+        )?;
+        unsafe { std::ptr::addr_of_mut!((*ptr).data).write(UnsafeCell::new(data)) };
+        let state = unsafe { state.assume_init() };
 
         let owner_pid = getpid();
-        Ok(Self { mutex, owner_pid })
+        Ok(Self {
+            state,
+            tracker: None,
+            poison: None,
+            level: None,
+            config,
+            spin_count: DEFAULT_SPIN_COUNT,
+            owner_pid,
+        })
     }
 
     /// Locks mutex.
     ///
-    /// This function will block until mutex is locked.
+    /// This function will block until mutex is locked. It is never interrupted by signals: after signal handler
+    /// returns, process continues waiting for the mutex.
+    ///
+    /// For robust mutexes returns [`LockStatus::AcquiredInconsistent`] if previous owner died while holding the lock.
+    ///
+    /// Mutex stays locked until [`unlock`](#method.unlock) is called, which is easy to forget on early return.
+    /// Prefer [`guard`](#method.guard) or [`with_locked`](#method.with_locked), which unlock mutex automatically.
     ///
     /// # Errors
-    /// If any pthread call fails, returns error from [`last_os_error`]. For possible errors see [`pthread_mutex_lock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html).
+    /// If pthread call fails, returns corresponding [`SyncError`]. For error-checking mutexes relock fails with
+    /// [`SyncError::Deadlock`]. For possible errors see [`pthread_mutex_lock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html).
+    pub fn lock(&mut self) -> Result<LockStatus, SyncError> {
+        self.lock_by_ref()
+    }
+
+    /// Same as [`lock`](#method.lock), for primitives which only have shared access to their mutex.
+    pub(crate) fn lock_by_ref(&self) -> Result<LockStatus, SyncError> {
+        self.check_level();
+        let status = match unsafe { pthread_mutex_lock(self.raw_mutex()) } {
+            0 => LockStatus::Acquired,
+            EOWNERDEAD => LockStatus::AcquiredInconsistent,
+            err => return Err(SyncError::from_errno("pthread_mutex_lock", err)),
+        };
+        self.track(getpid());
+        self.hold_level();
+        Ok(status)
+    }
+
+    /// Locks mutex, spinning for a while before blocking.
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn lock(&mut self) -> std::io::Result<()> {
-        check_libc_err(unsafe { pthread_mutex_lock(self.mutex.get_mut()) })?;
-        Ok(())
+    /// Makes up to [`with_spin_count`](#method.with_spin_count) attempts to lock mutex without blocking, hinting CPU
+    /// with [`std::hint::spin_loop`] between them, and only then blocks like [`lock`](#method.lock). When mutex is
+    /// held only briefly, this avoids cost of putting process to sleep and waking it up, at the cost of burning CPU
+    /// while spinning. For long critical sections prefer [`lock`](#method.lock).
+    ///
+    /// # Errors
+    /// Same as [`lock`](#method.lock).
+    pub fn lock_adaptive(&mut self) -> Result<LockStatus, SyncError> {
+        self.check_level();
+        for _ in 0..self.spin_count {
+            let status = match unsafe { pthread_mutex_trylock(self.get_mut()) } {
+                0 => LockStatus::Acquired,
+                EOWNERDEAD => LockStatus::AcquiredInconsistent,
+                EBUSY => {
+                    std::hint::spin_loop();
+                    continue;
+                }
+                err => return Err(SyncError::from_errno("pthread_mutex_trylock", err)),
+            };
+            self.track(getpid());
+            self.hold_level();
+            return Ok(status);
+        }
+        self.lock()
+    }
+
+    /// Tries to lock mutex without blocking.
+    ///
+    /// Returns `Ok(true)` if mutex was locked by this call and `Ok(false)` if it is already locked.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `EBUSY`, returns corresponding [`SyncError`].
+    /// For robust mutexes [`SyncError::OwnerDead`] is returned as error too, but in this case mutex **is** locked and must be
+    /// marked consistent with [`mark_consistent`](#method.mark_consistent). For possible errors see [`pthread_mutex_trylock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html).
+    pub fn try_lock(&mut self) -> Result<bool, SyncError> {
+        match unsafe { pthread_mutex_trylock(self.get_mut()) } {
+            0 => {
+                self.track(getpid());
+                self.hold_level();
+                Ok(true)
+            }
+            EBUSY => Ok(false),
+            err => Err(SyncError::from_errno("pthread_mutex_trylock", err)),
+        }
+    }
+
+    /// Returns `true` if mutex is currently locked by any process.
+    ///
+    /// **Result is only a snapshot and is racy**: mutex may be locked or unlocked by another process right after
+    /// this function returns, so it must not be used for synchronization, only for diagnostics.
+    ///
+    /// Implemented as [`try_lock`](#method.try_lock) immediately followed by unlock, so for a moment mutex is held by
+    /// calling process. Recursive mutex held by calling process is reported as unlocked.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `EBUSY`, returns corresponding [`SyncError`].
+    /// [`SyncError::OwnerDead`] is handled the same way as in [`try_lock`](#method.try_lock), mutex is left locked in
+    /// this case, since unlocking it without marking consistent would make it permanently unusable.
+    pub fn is_locked(&self) -> Result<bool, SyncError> {
+        let mutex = self.raw_mutex();
+        match unsafe { pthread_mutex_trylock(mutex) } {
+            0 => {}
+            EBUSY => return Ok(true),
+            err => return Err(SyncError::from_errno("pthread_mutex_trylock", err)),
+        }
+        match unsafe { pthread_mutex_unlock(mutex) } {
+            0 => Ok(false),
+            err => Err(SyncError::from_errno("pthread_mutex_unlock", err)),
+        }
+    }
+
+    /// Locks mutex, giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(true)` if mutex was locked and `Ok(false)` if timeout expired first.
+    /// Deadline is measured against `CLOCK_REALTIME`, so it is affected by system time changes. Very large timeouts are clamped to the latest representable time.
+    ///
+    /// macOS has no `pthread_mutex_timedlock`, so there mutex is polled with `pthread_mutex_trylock` until deadline
+    /// instead, sleeping up to a millisecond between attempts.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `ETIMEDOUT`, returns corresponding [`SyncError`].
+    /// [`SyncError::OwnerDead`] is handled the same way as in [`try_lock`](#method.try_lock). For possible errors see [`pthread_mutex_timedlock`](https://man7.org/linux/man-pages/man3/pthread_mutex_timedlock.3p.html).
+    pub fn lock_timeout(&mut self, timeout: Duration) -> Result<bool, SyncError> {
+        self.check_level();
+        let deadline = deadline_after(CLOCK_REALTIME, timeout)?;
+        match timed_lock(self.get_mut(), &deadline)? {
+            0 => {
+                self.track(getpid());
+                self.hold_level();
+                Ok(true)
+            }
+            ETIMEDOUT => Ok(false),
+            err => Err(SyncError::from_errno("pthread_mutex_timedlock", err)),
+        }
+    }
+
+    /// Locks mutex, giving up after `timeout` elapses, measured against `CLOCK_MONOTONIC`.
+    ///
+    /// Same as [`lock_timeout`](#method.lock_timeout), but timeout is not affected by system time changes. Uses
+    /// `pthread_mutex_clocklock`, which is available on Linux since glibc 2.30.
+    ///
+    /// # Errors
+    /// If `pthread_mutex_clocklock` is not available, returns [`SyncError::InvalidInput`].
+    /// Otherwise same as [`lock_timeout`](#method.lock_timeout). For possible errors see [`pthread_mutex_clocklock`](https://man7.org/linux/man-pages/man3/pthread_mutex_clocklock.3.html).
+    pub fn lock_timeout_monotonic(&mut self, timeout: Duration) -> Result<bool, SyncError> {
+        self.check_level();
+        let deadline = deadline_after(CLOCK_MONOTONIC, timeout)?;
+        match clock_lock(self.get_mut(), CLOCK_MONOTONIC, &deadline)? {
+            0 => {
+                self.track(getpid());
+                self.hold_level();
+                Ok(true)
+            }
+            ETIMEDOUT => Ok(false),
+            err => Err(SyncError::from_errno("pthread_mutex_clocklock", err)),
+        }
+    }
+
+    /// Locks mutex and returns guard that unlocks it when dropped.
+    ///
+    /// Guard gives access to data protected by mutex, see [`with_data`](#method.with_data).
+    /// Guard borrows mutex mutably, so mutex cannot be locked again or unlocked manually while guard is alive.
+    ///
+    /// # Errors
+    /// Same as [`lock`](#method.lock).
+    pub fn guard(&mut self) -> Result<SharedMutexGuard<'_, T>, SyncError> {
+        let status = self.lock()?;
+        Ok(SharedMutexGuard {
+            mutex: self,
+            status,
+            panicking: std::thread::panicking(),
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Locks mutex and returns guard, or [`PoisonError`] with guard if mutex is poisoned.
+    ///
+    /// Mutex is poisoned if some process panicked while holding guard, or died while holding robust mutex, see
+    /// [`with_poisoning`](#method.with_poisoning). In both cases mutex is locked and guard can be obtained with
+    /// [`PoisonError::into_inner`]. Poisoning stays until [`clear_poison`](#method.clear_poison) is called. Mutexes
+    /// without poisoning are never poisoned.
+    ///
+    /// For robust mutexes which were acquired in inconsistent state guard is still returned with
+    /// [`LockStatus::AcquiredInconsistent`], see [`guard`](#method.guard).
+    ///
+    /// # Errors
+    /// Same as [`lock`](#method.lock).
+    #[allow(clippy::type_complexity)]
+    pub fn guard_with_poison(
+        &mut self,
+    ) -> Result<Result<SharedMutexGuard<'_, T>, PoisonError<SharedMutexGuard<'_, T>>>, SyncError>
+    {
+        let guard = self.guard()?;
+        if guard.status == LockStatus::AcquiredInconsistent {
+            guard.mutex.set_poison(true);
+        }
+        if guard.mutex.is_poisoned() {
+            Ok(Err(PoisonError { guard }))
+        } else {
+            Ok(Ok(guard))
+        }
+    }
+
+    /// Returns `true` if mutex is poisoned, see [`guard_with_poison`](#method.guard_with_poison).
+    ///
+    /// **Result is only a snapshot**, another process may poison mutex right after this function returns.
+    pub fn is_poisoned(&self) -> bool {
+        match &self.poison {
+            // atomic is safe to access concurrently
+            Some(poison) => unsafe { poison.get() }.load(Ordering::Acquire),
+            None => false,
+        }
+    }
+
+    /// Clears poisoning, so that [`guard_with_poison`](#method.guard_with_poison) returns guard normally again.
+    ///
+    /// Should be called after data protected by mutex is restored to consistent state.
+    pub fn clear_poison(&self) {
+        self.set_poison(false);
+    }
+
+    fn set_poison(&self, poisoned: bool) {
+        if let Some(poison) = &self.poison {
+            // atomic is safe to access concurrently
+            unsafe { poison.get() }.store(poisoned, Ordering::Release);
+        }
+    }
+
+    /// Locks mutex, calls `f` with data protected by it and unlocks mutex again.
+    ///
+    /// This is the simplest safe way to access shared data, see [`with_data`](#method.with_data).
+    ///
+    /// # Errors
+    /// Same as [`lock`](#method.lock). If robust mutex is acquired in inconsistent state, `f` is not called and
+    /// [`SyncError::OwnerDead`] is returned, with mutex **still locked**, same as in [`try_lock`](#method.try_lock).
+    /// Use [`guard`](#method.guard) to recover data of such mutex.
+    pub fn with_locked<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> Result<R, SyncError> {
+        let mut guard = self.guard()?;
+        if guard.status() == LockStatus::AcquiredInconsistent {
+            // unlocking without marking consistent would make mutex unusable
+            std::mem::forget(guard);
+            return Err(SyncError::OwnerDead(OsError::new(
+                "pthread_mutex_lock",
+                EOWNERDEAD,
+            )));
+        }
+        Ok(f(&mut guard))
+    }
+
+    /// Locks mutex, calls `f` and unlocks mutex again.
+    ///
+    /// Same as [`with_locked`](#method.with_locked), for closures which don't need protected data, e.g. when mutex
+    /// guards some external resource. Mutex is unlocked even if `f` panics, since unlocking is done by
+    /// [`SharedMutexGuard`] while stack is unwound.
+    ///
+    /// # Errors
+    /// Same as [`with_locked`](#method.with_locked).
+    pub fn lock_and<R>(&mut self, f: impl FnOnce() -> R) -> Result<R, SyncError> {
+        self.with_locked(|_| f())
+    }
+
+    /// Marks robust mutex, acquired in inconsistent state, as consistent.
+    ///
+    /// Must be called by process holding the lock, after it restored invariants of data protected by mutex.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_mutex_consistent`](https://man7.org/linux/man-pages/man3/pthread_mutex_consistent.3.html).
+    /// On macOS, which has no robust mutexes, returns [`SyncError::InvalidInput`].
+    pub fn mark_consistent(&mut self) -> Result<(), SyncError> {
+        self.mark_consistent_by_ref()
+    }
+
+    /// Same as [`mark_consistent`](#method.mark_consistent), for primitives which only have shared access to their mutex.
+    pub(crate) fn mark_consistent_by_ref(&self) -> Result<(), SyncError> {
+        make_consistent(self.raw_mutex())
     }
 
     /// Unlocks mutex.
     ///
     /// This function must be called from the same process that called [`lock`](#method.lock) previously.
+    /// Violation is only detected for error-checking and robust mutexes, for other mutexes behaviour is undefined.
+    ///
+    /// # Errors
+    /// If mutex is not held by calling process, returns [`SyncError::NotOwner`]. If pthread call fails otherwise,
+    /// returns corresponding [`SyncError`]. For possible errors see [`pthread_mutex_unlock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html).
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        self.unlock_by_ref()
+    }
+
+    /// Same as [`unlock`](#method.unlock), for primitives which only have shared access to their mutex.
+    pub(crate) fn unlock_by_ref(&self) -> Result<(), SyncError> {
+        // cleared before unlocking, so that it does not overwrite pid of the next owner
+        let previous_owner = self.owner_pid();
+        self.track(0);
+        let err = match unsafe { pthread_mutex_unlock(self.raw_mutex()) } {
+            0 => {
+                self.release_level();
+                return Ok(());
+            }
+            EPERM => SyncError::NotOwner(OsError::new("pthread_mutex_unlock", EPERM)),
+            err => SyncError::from_errno("pthread_mutex_unlock", err),
+        };
+        // mutex is still locked, so its owner didn't change
+        self.track(previous_owner.unwrap_or(0));
+        Err(err)
+    }
+
+    /// Destroys mutex explicitly, returning error instead of panicking like [`Drop`] does.
+    ///
+    /// Unlike [`Drop`], mutex is destroyed regardless of which process created it, so ownership can be transferred
+    /// to any process. Only one process must destroy the mutex, and other processes must not use it afterwards.
+    /// Memory is unmapped in calling process in any case, and mutex is not destroyed again on drop. Protected data is
+    /// dropped by calling process too, see [`with_data`](#method.with_data).
     ///
     /// # Errors
-    /// If any pthread call fails, returns error from [`last_os_error`]. For possible errors see [`pthread_mutex_unlock`](https://man7.org/linux/man-pages/man3/pthread_mutex_lock.3p.html).
+    /// If pthread call fails, returns corresponding [`SyncError`], e.g. [`SyncError::WouldBlock`] if mutex is locked.
+    /// For possible errors see [`pthread_mutex_destroy`](https://man7.org/linux/man-pages/man3/pthread_mutex_destroy.3p.html).
+    pub fn into_destroyed(mut self) -> Result<(), SyncError> {
+        // no process has pid 0, so mutex is not destroyed again when self is dropped
+        self.owner_pid = 0;
+        self.state.set_owner_pid(getpid());
+        match unsafe { pthread_mutex_destroy(self.get_mut()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_mutex_destroy", err)),
+        }
+    }
+
+    /// Destroys mutex and initializes it again in the same shared memory, with the same attributes.
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn unlock(&mut self) -> std::io::Result<()> {
-        check_libc_err(unsafe { pthread_mutex_unlock(self.mutex.get_mut()) })?;
+    /// This gives clean slate when mutex state can't be trusted anymore, e.g. robust mutex which was unlocked without
+    /// being marked consistent and became unrecoverable. Protected data is left as is.
+    ///
+    /// # Safety
+    /// **No other process may use the mutex during and before this call**: it must not be locked (unlocked robust
+    /// mutex in unrecoverable state is fine), waited on with [`SharedCondvar`](crate::SharedCondvar), or locked
+    /// concurrently with reinitialization. Otherwise behaviour is undefined. Calling process must coordinate this by
+    /// other means, e.g. by stopping all other processes.
+    ///
+    /// # Errors
+    /// If destroying or initialization fails returns corresponding [`SyncError`]. For possible errors see [`pthread_mutex_destroy`](https://man7.org/linux/man-pages/man3/pthread_mutex_destroy.3p.html).
+    pub unsafe fn reinitialize(&mut self) -> Result<(), SyncError> {
+        match pthread_mutex_destroy(self.get_mut()) {
+            0 => {}
+            err => return Err(SyncError::from_errno("pthread_mutex_destroy", err)),
+        }
+        // destroyed mutex has unspecified state, so zero it like freshly mapped memory
+        self.get_mut().write_bytes(0, 1);
+        initialize_mutex(&mut *self.get_mut(), self.config)?;
+        self.track(0);
         Ok(())
     }
 
+    /// Returns pid of process holding tracked mutex, or `None` if it is not locked.
+    ///
+    /// **Result is only a snapshot and is racy**, see [`new_tracked`](SharedMutex::new_tracked). Process waiting on
+    /// [`SharedCondvar`](crate::SharedCondvar) is still reported as owner, and so is process which died holding robust
+    /// mutex. Always returns `None` for mutexes which are not tracked.
+    pub fn owner_pid(&self) -> Option<pid_t> {
+        let tracker = self.tracker.as_ref()?;
+        // atomic is safe to access concurrently
+        match unsafe { tracker.get() }.load(Ordering::Relaxed) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    pub(crate) fn track(&self, pid: pid_t) {
+        if let Some(tracker) = &self.tracker {
+            // atomic is safe to access concurrently
+            unsafe { tracker.get() }.store(pid, Ordering::Relaxed);
+        }
+    }
+
+    /// Fails if mutex is tracked and not held by calling process, for other mutexes it can't be checked cheaply.
+    ///
+    /// Error is reported as if `operation` failed with `EPERM`.
+    pub(crate) fn check_held(&self, operation: &'static str) -> Result<(), SyncError> {
+        if self.tracker.is_some() && self.owner_pid() != Some(getpid()) {
+            return Err(SyncError::NotOwner(OsError::new(operation, EPERM)));
+        }
+        Ok(())
+    }
+
+    /// Returns level of leveled mutex, see [`new_leveled`](SharedMutex::new_leveled).
+    pub fn level(&self) -> Option<u32> {
+        self.level
+    }
+
+    fn check_level(&self) {
+        #[cfg(debug_assertions)]
+        if let Some(level) = self.level {
+            HELD_LEVELS.with(|held| {
+                if let Some(&highest) = held.borrow().iter().max() {
+                    assert!(
+                        highest < level,
+                        "lock ordering violation: locking mutex of level {} while holding mutex of level {}",
+                        level,
+                        highest
+                    );
+                }
+            });
+        }
+    }
+
+    fn hold_level(&self) {
+        #[cfg(debug_assertions)]
+        if let Some(level) = self.level {
+            HELD_LEVELS.with(|held| held.borrow_mut().push(level));
+        }
+    }
+
+    fn release_level(&self) {
+        #[cfg(debug_assertions)]
+        if let Some(level) = self.level {
+            HELD_LEVELS.with(|held| {
+                let mut held = held.borrow_mut();
+                // mutexes may be unlocked in any order
+                if let Some(index) = held.iter().rposition(|&held| held == level) {
+                    held.remove(index);
+                }
+            });
+        }
+    }
+
+    /// Returns pointer to wrapped `pthread_mutex_t`.
+    ///
+    /// Mutex is placed at the start of shared memory mapping and is followed by protected data, like in `#[repr(C)]`
+    /// struct. It is initialized with `PTHREAD_PROCESS_SHARED`, so it can be passed to C code in another process which
+    /// has the same mapping. Mutex must not be destroyed through this pointer.
+    pub fn as_raw(&self) -> *mut pthread_mutex_t {
+        self.raw_mutex()
+    }
+
     pub(crate) fn get_mut(&mut self) -> *mut pthread_mutex_t {
-        self.mutex.get_mut()
+        self.raw_mutex()
+    }
+
+    fn raw_mutex(&self) -> *mut pthread_mutex_t {
+        unsafe { std::ptr::addr_of_mut!((*self.state.raw_ptr()).mutex) }
+    }
+}
+
+/// Priority protocol of [`SharedMutex`], see [`SharedMutex::new_with_protocol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutexProtocol {
+    /// Priority of holding process is not affected (`PTHREAD_PRIO_NONE`), same as [`SharedMutex::new`].
+    None,
+    /// Holding process inherits the highest priority of processes waiting for the mutex (`PTHREAD_PRIO_INHERIT`).
+    PriorityInheritance,
+    /// Holding process runs at least with given priority ceiling (`PTHREAD_PRIO_PROTECT`). Ceiling must be valid
+    /// priority for `SCHED_FIFO` and not lower than priority of any process locking the mutex.
+    PriorityCeiling(c_int),
+}
+
+/// Type of [`SharedMutex`], see [`SharedMutex::with_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutexKind {
+    /// Mutex without error checking (`PTHREAD_MUTEX_NORMAL`).
+    Normal,
+    /// Mutex which can be relocked by the thread holding it (`PTHREAD_MUTEX_RECURSIVE`).
+    Recursive,
+    /// Mutex which reports relocking and unlocking by wrong thread as errors (`PTHREAD_MUTEX_ERRORCHECK`).
+    ErrorCheck,
+    /// Implementation-defined mutex type (`PTHREAD_MUTEX_DEFAULT`), same as [`SharedMutex::new`].
+    Default,
+}
+
+/// Attributes mutex was created with, kept to initialize it again in [`SharedMutex::reinitialize`].
+#[derive(Debug, Clone, Copy)]
+enum MutexConfig {
+    Default,
+    Robust,
+    Type(MutexKind),
+    Protocol(MutexProtocol),
+    /// Attributes read from caller's attributes object, see [`SharedMutex::new_with_attr`].
+    Attr(MutexAttrs),
+    /// Not process-shared, see [`SharedMutex::new_thread_local`].
+    ThreadLocal,
+}
+
+/// Attributes of `pthread_mutexattr_t` other than `pshared`, read with `pthread_mutexattr_get*()`.
+///
+/// Attributes object itself is not kept: copying it is undefined behaviour, and caller may destroy it at any time.
+#[derive(Debug, Clone, Copy)]
+struct MutexAttrs {
+    kind: c_int,
+    robust: bool,
+    protocol: c_int,
+    /// Only set for `PTHREAD_PRIO_PROTECT` protocol.
+    prioceiling: Option<c_int>,
+}
+
+impl MutexAttrs {
+    /// # Safety
+    /// `attr` must be initialized and not destroyed yet.
+    unsafe fn read(attr: &pthread_mutexattr_t) -> Result<Self, SyncError> {
+        let mut kind = 0;
+        check_pthread_err(
+            "pthread_mutexattr_gettype",
+            pthread_mutexattr_gettype(attr, &mut kind),
+        )?;
+        let robust = get_robust(attr)?;
+        let mut protocol = 0;
+        check_pthread_err(
+            "pthread_mutexattr_getprotocol",
+            pthread_mutexattr_getprotocol(attr, &mut protocol),
+        )?;
+        let prioceiling = if protocol == PTHREAD_PRIO_PROTECT {
+            let mut prioceiling = 0;
+            check_pthread_err(
+                "pthread_mutexattr_getprioceiling",
+                pthread_mutexattr_getprioceiling(attr, &mut prioceiling),
+            )?;
+            Some(prioceiling)
+        } else {
+            None
+        };
+        Ok(Self {
+            kind,
+            robust,
+            protocol,
+            prioceiling,
+        })
     }
 }
 
-// TODO: document drop behaviour
-impl Drop for SharedMutex {
+impl MutexConfig {
+    fn configure(self, attr: &mut pthread_mutexattr_t) -> Result<(), SyncError> {
+        match self {
+            MutexConfig::Default | MutexConfig::ThreadLocal => Ok(()),
+            MutexConfig::Attr(attrs) => {
+                check_pthread_err("pthread_mutexattr_settype", unsafe {
+                    pthread_mutexattr_settype(attr, attrs.kind)
+                })?;
+                set_robust(attr, attrs.robust)?;
+                set_protocol(attr, attrs.protocol, attrs.prioceiling)
+            }
+            MutexConfig::Robust => set_robust(attr, true),
+            MutexConfig::Type(kind) => {
+                let kind = match kind {
+                    MutexKind::Normal => PTHREAD_MUTEX_NORMAL,
+                    MutexKind::Recursive => PTHREAD_MUTEX_RECURSIVE,
+                    MutexKind::ErrorCheck => PTHREAD_MUTEX_ERRORCHECK,
+                    MutexKind::Default => PTHREAD_MUTEX_DEFAULT,
+                };
+                match unsafe { pthread_mutexattr_settype(attr, kind) } {
+                    0 => Ok(()),
+                    err => Err(SyncError::from_errno("pthread_mutexattr_settype", err)),
+                }
+            }
+            MutexConfig::Protocol(protocol) => {
+                let (protocol, ceiling) = match protocol {
+                    MutexProtocol::None => (PTHREAD_PRIO_NONE, None),
+                    MutexProtocol::PriorityInheritance => (PTHREAD_PRIO_INHERIT, None),
+                    MutexProtocol::PriorityCeiling(ceiling) => {
+                        (PTHREAD_PRIO_PROTECT, Some(ceiling))
+                    }
+                };
+                set_protocol(attr, protocol, ceiling)
+            }
+        }
+    }
+}
+
+fn set_protocol(
+    attr: &mut pthread_mutexattr_t,
+    protocol: c_int,
+    ceiling: Option<c_int>,
+) -> Result<(), SyncError> {
+    match unsafe { pthread_mutexattr_setprotocol(attr, protocol) } {
+        0 => {}
+        err => return Err(SyncError::from_errno("pthread_mutexattr_setprotocol", err)),
+    }
+    if let Some(ceiling) = ceiling {
+        match unsafe { pthread_mutexattr_setprioceiling(attr, ceiling) } {
+            0 => {}
+            err => {
+                return Err(SyncError::from_errno(
+                    "pthread_mutexattr_setprioceiling",
+                    err,
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Result of successfully locking [`SharedMutex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockStatus {
+    /// Mutex was locked normally.
+    Acquired,
+    /// Mutex was locked, but its previous owner died while holding it. Only possible for robust mutexes.
+    ///
+    /// See [`SharedMutex::mark_consistent`].
+    AcquiredInconsistent,
+}
+
+/// Scope guard returned by [`SharedMutex::guard`].
+///
+/// Dereferences to data protected by mutex. Mutex is unlocked when guard is dropped.
+///
+/// Guard is not [`Send`]: `pthread` mutexes are owned by threads, so mutex must be unlocked by the same thread which
+/// locked it.
+///
+/// ```compile_fail
+/// # use process_sync::SharedMutex;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut mutex = SharedMutex::with_data(0)?;
+/// std::thread::scope(|scope| {
+///     let guard = mutex.guard().unwrap();
+///     scope.spawn(move || drop(guard));
+/// });
+/// #     Ok(())
+/// # }
+/// ```
+#[must_use = "if unused the mutex will immediately unlock"]
+pub struct SharedMutexGuard<'a, T: Send = ()> {
+    mutex: &'a mut SharedMutex<T>,
+    status: LockStatus,
+    /// Whether thread was already panicking when guard was created, so dropping guard doesn't poison mutex.
+    panicking: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+// sharing guard only gives access to `&T`, just like `std::sync::MutexGuard`
+unsafe impl<T: Send + Sync> Sync for SharedMutexGuard<'_, T> {}
+
+impl<T: Send> SharedMutexGuard<'_, T> {
+    /// Returns status with which mutex was locked.
+    pub fn status(&self) -> LockStatus {
+        self.status
+    }
+
+    /// Marks robust mutex as consistent. See [`SharedMutex::mark_consistent`].
+    ///
+    /// # Errors
+    /// Same as [`SharedMutex::mark_consistent`].
+    pub fn mark_consistent(&mut self) -> Result<(), SyncError> {
+        self.mutex.mark_consistent()?;
+        self.status = LockStatus::Acquired;
+        Ok(())
+    }
+
+    pub(crate) fn mutex(&mut self) -> &mut SharedMutex<T> {
+        self.mutex
+    }
+
+    pub(crate) fn set_status(&mut self, status: LockStatus) {
+        self.status = status;
+    }
+}
+
+impl<T: Send> Deref for SharedMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*self.mutex.state.raw_ptr()).data.get() }
+    }
+}
+
+impl<T: Send> DerefMut for SharedMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(*self.mutex.state.raw_ptr()).data.get() }
+    }
+}
+
+/// Serializes data protected by mutex, which can't be modified by other processes while guard is held.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + Send> serde::Serialize for SharedMutexGuard<'_, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<T: Send> Drop for SharedMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        if !self.panicking && std::thread::panicking() {
+            self.mutex.set_poison(true);
+        }
+        if let Err(err) = self.mutex.unlock() {
+            report_primitive_drop_error(
+                "unlock",
+                "mutex",
+                self.mutex.raw_mutex(),
+                self.mutex.owner_pid,
+                err,
+            );
+        }
+    }
+}
+
+/// Error returned by [`SharedMutex::guard_with_poison`] when mutex is poisoned.
+///
+/// Mutex is locked anyway, guard can be accessed with [`into_inner`](#method.into_inner).
+pub struct PoisonError<G> {
+    guard: G,
+}
+
+impl<G> PoisonError<G> {
+    /// Consumes error, returning guard which holds the lock.
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    /// Returns reference to guard which holds the lock.
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    /// Returns mutable reference to guard which holds the lock.
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+
+impl<G> std::fmt::Debug for PoisonError<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<G> std::fmt::Display for PoisonError<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "poisoned lock: another process failed while holding it")
+    }
+}
+
+impl<G> std::error::Error for PoisonError<G> {}
+
+impl<T: Send> Drop for SharedMutex<T> {
     fn drop(&mut self) {
         if getpid() == self.owner_pid {
-            check_libc_err(unsafe { pthread_mutex_destroy(self.mutex.get_mut()) })
-                .expect("cannot destroy mutex");
+            match unsafe { pthread_mutex_destroy(self.get_mut()) } {
+                0 => {}
+                err => report_primitive_drop_error(
+                    "destroy",
+                    "mutex",
+                    self.raw_mutex(),
+                    self.owner_pid,
+                    SyncError::from_errno("pthread_mutex_destroy", err),
+                ),
+            }
         }
     }
 }
 
-fn initialize_mutex(mutex: &mut pthread_mutex_t) -> std::io::Result<()> {
-    let mut attr: pthread_mutexattr_t = unsafe { std::mem::zeroed() };
-    check_libc_err(unsafe { pthread_mutexattr_init(&mut attr) })?;
+impl<T: Send> sealed::Sealed for SharedMutex<T> {}
+
+impl<T: Send> SharedPrimitive for SharedMutex<T> {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
+        self.state.set_owner_pid(pid);
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    // levels of leveled mutexes held by current thread
+    static HELD_LEVELS: std::cell::RefCell<Vec<u32>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+extern "C" {
+    // not exposed by libc crate for all targets
+    fn pthread_mutexattr_setprotocol(attr: *mut pthread_mutexattr_t, protocol: c_int) -> c_int;
+    fn pthread_mutexattr_setprioceiling(
+        attr: *mut pthread_mutexattr_t,
+        prioceiling: c_int,
+    ) -> c_int;
+    fn pthread_mutexattr_gettype(attr: *const pthread_mutexattr_t, kind: *mut c_int) -> c_int;
+    #[cfg(not(target_os = "macos"))]
+    fn pthread_mutexattr_getrobust(attr: *const pthread_mutexattr_t, robust: *mut c_int) -> c_int;
+    fn pthread_mutexattr_getprotocol(
+        attr: *const pthread_mutexattr_t,
+        protocol: *mut c_int,
+    ) -> c_int;
+    fn pthread_mutexattr_getprioceiling(
+        attr: *const pthread_mutexattr_t,
+        prioceiling: *mut c_int,
+    ) -> c_int;
+}
+
+#[cfg(not(target_os = "macos"))]
+fn set_robust(attr: &mut pthread_mutexattr_t, robust: bool) -> Result<(), SyncError> {
+    let robust = if robust {
+        PTHREAD_MUTEX_ROBUST
+    } else {
+        PTHREAD_MUTEX_STALLED
+    };
+    check_pthread_err("pthread_mutexattr_setrobust", unsafe {
+        pthread_mutexattr_setrobust(attr, robust)
+    })
+}
+
+/// macOS has no robust mutexes, so only default (stalled) robustness can be set.
+#[cfg(target_os = "macos")]
+fn set_robust(_attr: &mut pthread_mutexattr_t, robust: bool) -> Result<(), SyncError> {
+    if robust {
+        return Err(SyncError::InvalidInput(
+            "robust mutexes are not supported on this platform",
+        ));
+    }
+    Ok(())
+}
 
-    check_libc_err(unsafe { pthread_mutexattr_setpshared(&mut attr, PTHREAD_PROCESS_SHARED) })
-        .expect("cannot set PTHREAD_PROCESS_SHARED");
+/// # Safety
+/// `attr` must be initialized and not destroyed yet.
+#[cfg(not(target_os = "macos"))]
+unsafe fn get_robust(attr: &pthread_mutexattr_t) -> Result<bool, SyncError> {
+    let mut robust = 0;
+    check_pthread_err(
+        "pthread_mutexattr_getrobust",
+        pthread_mutexattr_getrobust(attr, &mut robust),
+    )?;
+    Ok(robust == PTHREAD_MUTEX_ROBUST)
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn get_robust(_attr: &pthread_mutexattr_t) -> Result<bool, SyncError> {
+    Ok(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn make_consistent(mutex: *mut pthread_mutex_t) -> Result<(), SyncError> {
+    check_pthread_err("pthread_mutex_consistent", unsafe {
+        pthread_mutex_consistent(mutex)
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn make_consistent(_mutex: *mut pthread_mutex_t) -> Result<(), SyncError> {
+    Err(SyncError::InvalidInput(
+        "robust mutexes are not supported on this platform",
+    ))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn timed_lock(mutex: *mut pthread_mutex_t, deadline: &timespec) -> Result<c_int, SyncError> {
+    Ok(unsafe { pthread_mutex_timedlock(mutex, deadline) })
+}
+
+/// Emulates `pthread_mutex_timedlock`, which macOS lacks, by polling with `pthread_mutex_trylock`.
+#[cfg(target_os = "macos")]
+fn timed_lock(mutex: *mut pthread_mutex_t, deadline: &timespec) -> Result<c_int, SyncError> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    loop {
+        match unsafe { pthread_mutex_trylock(mutex) } {
+            EBUSY => {}
+            ret => return Ok(ret),
+        }
+        let now = deadline_after(CLOCK_REALTIME, Duration::ZERO)?;
+        let remaining = Duration::new(deadline.tv_sec as u64, deadline.tv_nsec as u32)
+            .checked_sub(Duration::new(now.tv_sec as u64, now.tv_nsec as u32));
+        match remaining {
+            Some(remaining) if !remaining.is_zero() => {
+                std::thread::sleep(remaining.min(POLL_INTERVAL))
+            }
+            _ => return Ok(ETIMEDOUT),
+        }
+    }
+}
+
+/// Calls `pthread_mutex_clocklock`, looked up at runtime since older glibc versions and other C libraries lack it.
+#[cfg(target_os = "linux")]
+fn clock_lock(
+    mutex: *mut pthread_mutex_t,
+    clock: clockid_t,
+    deadline: &timespec,
+) -> Result<c_int, SyncError> {
+    type ClockLockFn =
+        unsafe extern "C" fn(*mut pthread_mutex_t, clockid_t, *const timespec) -> c_int;
+    static CLOCK_LOCK: std::sync::OnceLock<Option<ClockLockFn>> = std::sync::OnceLock::new();
+
+    let clock_lock = CLOCK_LOCK.get_or_init(|| {
+        let symbol =
+            unsafe { libc::dlsym(libc::RTLD_DEFAULT, c"pthread_mutex_clocklock".as_ptr()) };
+        // symbol has signature of pthread_mutex_clocklock(3)
+        (!symbol.is_null())
+            .then(|| unsafe { std::mem::transmute::<*mut libc::c_void, ClockLockFn>(symbol) })
+    });
+    match clock_lock {
+        Some(clock_lock) => Ok(unsafe { clock_lock(mutex, clock, deadline) }),
+        None => Err(SyncError::InvalidInput(
+            "pthread_mutex_clocklock is not available",
+        )),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_lock(
+    _mutex: *mut pthread_mutex_t,
+    _clock: clockid_t,
+    _deadline: &timespec,
+) -> Result<c_int, SyncError> {
+    Err(SyncError::InvalidInput(
+        "pthread_mutex_clocklock is not available",
+    ))
+}
+
+fn initialize_mutex(mutex: &mut pthread_mutex_t, config: MutexConfig) -> Result<(), SyncError> {
+    let mut attr: pthread_mutexattr_t = unsafe { std::mem::zeroed() };
+    check_pthread_err("pthread_mutexattr_init", unsafe {
+        pthread_mutexattr_init(&mut attr)
+    })?;
 
-    let ret = check_libc_err(unsafe { pthread_mutex_init(mutex, &mut attr) });
+    let pshared = match config {
+        MutexConfig::ThreadLocal => Ok(()),
+        _ => check_pshared_err("pthread_mutexattr_setpshared", unsafe {
+            pthread_mutexattr_setpshared(&mut attr, PTHREAD_PROCESS_SHARED)
+        }),
+    };
+    let ret = pshared
+        .and_then(|()| config.configure(&mut attr))
+        .and_then(|()| {
+            check_pthread_err("pthread_mutex_init", unsafe {
+                pthread_mutex_init(mutex, &attr)
+            })
+        });
 
     destroy_mutexattr(attr).expect("cannot destroy mutexattr");
 
-    ret.map(|_| ())
+    ret
 }
 
-fn destroy_mutexattr(mut attr: pthread_mutexattr_t) -> std::io::Result<()> {
-    check_libc_err(unsafe { pthread_mutexattr_destroy(&mut attr) })?;
+fn destroy_mutexattr(mut attr: pthread_mutexattr_t) -> Result<(), SyncError> {
+    check_pthread_err("pthread_mutexattr_destroy", unsafe {
+        pthread_mutexattr_destroy(&mut attr)
+    })?;
     Ok(())
 }
@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    shared_memory::SharedMemoryObject,
+    SharedCondvar, SharedMutex,
+};
+
+/// Notification helper that can be shared between processes, which doesn't lose wakeups.
+///
+/// Plain [`SharedCondvar`] is easy to misuse: notification sent before waiter starts waiting is lost. [`SharedNotify`]
+/// pairs condvar with mutex and generation counter, which is incremented by every [`notify`](#method.notify).
+/// [`wait_for`](#method.wait_for) records generation before checking predicate and only sleeps if generation didn't
+/// change since then, so notification sent at any moment after predicate was checked wakes it up.
+///
+/// State checked by predicate must be updated before calling [`notify`](#method.notify), e.g. stored in atomic or
+/// protected by another lock.
+///
+/// Dropping notify in creating process while it being waited will cause undefined behaviour.
+/// It is recommended to drop it in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::sync::atomic::Ordering;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{SharedAtomicU32, SharedNotify};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut notify = SharedNotify::new()?;
+/// let ready = SharedAtomicU32::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     // returns even if parent notified before child started waiting
+///     notify.wait_for(|| ready.load(Ordering::Acquire) == 1)?;
+/// } else {
+///     ready.store(1, Ordering::Release);
+///     notify.notify()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedNotify {
+    mutex: SharedMutex,
+    condvar: SharedCondvar,
+    generation: SharedMemoryObject<AtomicU64>,
+}
+
+impl SharedNotify {
+    /// Creates new [`SharedNotify`]
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Ok(Self {
+            mutex: SharedMutex::new()?,
+            condvar: SharedCondvar::new()?,
+            generation: SharedMemoryObject::new(AtomicU64::new(0))?,
+        })
+    }
+
+    /// Wakes up all processes waiting in [`wait_for`](#method.wait_for), so that they check their predicates again.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn notify(&mut self) -> Result<(), SyncError> {
+        self.mutex.lock()?;
+        self.counter().fetch_add(1, Ordering::AcqRel);
+        let ret = self.condvar.notify_all();
+        self.mutex.unlock()?;
+        ret
+    }
+
+    /// Waits until `predicate` returns `true`.
+    ///
+    /// `predicate` is checked without holding any lock, first immediately and then after each notification.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn wait_for(&mut self, mut predicate: impl FnMut() -> bool) -> Result<(), SyncError> {
+        loop {
+            let generation = self.generation();
+            if predicate() {
+                return Ok(());
+            }
+
+            self.mutex.lock()?;
+            // atomic is safe to access concurrently
+            let current = unsafe { self.generation.get() };
+            // generation is changed with mutex locked, so notification can't be missed between check and wait
+            let ret = self.condvar.wait_while(&mut self.mutex, || {
+                current.load(Ordering::Acquire) == generation
+            });
+            self.mutex.unlock()?;
+            ret?;
+        }
+    }
+
+    /// Returns number of notifications sent so far.
+    ///
+    /// **Result is only a snapshot**, it may be incremented by another process right after this function returns.
+    pub fn generation(&self) -> u64 {
+        self.counter().load(Ordering::Acquire)
+    }
+
+    fn counter(&self) -> &AtomicU64 {
+        // atomic is safe to access concurrently
+        unsafe { self.generation.get() }
+    }
+}
+
+impl sealed::Sealed for SharedNotify {}
+
+impl SharedPrimitive for SharedNotify {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.mutex.set_owner_pid(pid);
+        self.condvar.set_owner_pid(pid);
+    }
+}
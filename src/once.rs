@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::{report_drop_error, SyncError},
+    shared_memory::SharedMemoryObject,
+    LockStatus, SharedMutex,
+};
+
+/// One-time initialization that can be shared between processes, similar to [`std::sync::Once`].
+///
+/// Exactly one process calling [`call_once`](#method.call_once) runs initialization, processes arriving while it runs
+/// block until it is done, and processes arriving after it is done return immediately.
+///
+/// Initialization runs under robust [`SharedMutex`], so if initializing process dies (or initialization panics),
+/// next process calling [`call_once`](#method.call_once) runs initialization again.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedOnce;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let once = SharedOnce::new()?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// // printed only once
+/// once.call_once(|| println!("initialized"))?;
+/// assert!(once.is_completed());
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedOnce {
+    mutex: SharedMutex,
+    done: SharedMemoryObject<AtomicBool>,
+}
+
+impl SharedOnce {
+    /// Creates new [`SharedOnce`]
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Ok(Self {
+            mutex: SharedMutex::new_robust()?,
+            done: SharedMemoryObject::new(AtomicBool::new(false))?,
+        })
+    }
+
+    /// Runs `f` if no process has completed initialization yet.
+    ///
+    /// If another process is running initialization, blocks until it is done. If it dies before completing it, `f` is
+    /// run by calling process instead.
+    ///
+    /// # Errors
+    /// If locking or unlocking mutex fails returns corresponding [`SyncError`].
+    pub fn call_once(&self, f: impl FnOnce()) -> Result<(), SyncError> {
+        if self.is_completed() {
+            return Ok(());
+        }
+
+        let status = self.mutex.lock_by_ref()?;
+        // unlocks mutex even if `f` panics
+        let _unlock = Unlock(&self.mutex);
+        if status == LockStatus::AcquiredInconsistent {
+            // initializing process died, state is recovered by running initialization again
+            self.mutex.mark_consistent_by_ref()?;
+        }
+
+        let done = unsafe { self.done.get() };
+        if !done.load(Ordering::Acquire) {
+            f();
+            done.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if some process has completed initialization
+    pub fn is_completed(&self) -> bool {
+        // atomic is safe to access concurrently
+        unsafe { self.done.get() }.load(Ordering::Acquire)
+    }
+}
+
+/// Unlocks mutex of [`SharedOnce`] on drop, like [`SharedMutexGuard`](crate::SharedMutexGuard) which needs exclusive
+/// access to mutex.
+struct Unlock<'a>(&'a SharedMutex);
+
+impl Drop for Unlock<'_> {
+    fn drop(&mut self) {
+        if let Err(err) = self.0.unlock_by_ref() {
+            report_drop_error("cannot unlock SharedOnce", err);
+        }
+    }
+}
+
+impl sealed::Sealed for SharedOnce {}
+
+impl SharedPrimitive for SharedOnce {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.mutex.set_owner_pid(pid);
+    }
+}
@@ -0,0 +1,253 @@
+use libc::{
+    c_int, pid_t, pthread_rwlock_destroy, pthread_rwlock_init, pthread_rwlock_rdlock,
+    pthread_rwlock_t, pthread_rwlock_tryrdlock, pthread_rwlock_trywrlock, pthread_rwlock_unlock,
+    pthread_rwlock_wrlock, pthread_rwlockattr_destroy, pthread_rwlockattr_init,
+    pthread_rwlockattr_setpshared, pthread_rwlockattr_t, EBUSY, PTHREAD_PROCESS_SHARED,
+};
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::{report_primitive_drop_error, SyncError},
+    shared_memory::SharedMemoryObject,
+    util::{check_pshared_err, check_pthread_err, getpid},
+};
+
+/// Reader-writer lock that can be shared between processes.
+///
+/// Any number of processes may hold read lock at the same time, while write lock is exclusive.
+///
+/// Dropping lock in creating process while it being locked or waited will cause undefined behaviour.
+/// It is recommended to drop this lock in creating process only after no other process has access to it.
+///
+/// For more information see [`pthread_rwlock_init`](https://man7.org/linux/man-pages/man3/pthread_rwlock_init.3p.html), [`pthread_rwlock_rdlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_rdlock.3p.html), [`pthread_rwlock_wrlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_wrlock.3p.html) and [`SharedMemoryObject`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedRwLock;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut rwlock = SharedRwLock::new()?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     println!("child read()");
+///     rwlock.read()?;
+///     println!("child locked");
+///     sleep(Duration::from_millis(40));
+///     println!("child unlock()");
+///     rwlock.unlock()?;
+/// } else {
+///     sleep(Duration::from_millis(20));
+///     println!("parent read()");
+///     rwlock.read()?;
+///     println!("parent locked");
+///     println!("parent unlock()");
+///     rwlock.unlock()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// Output:
+/// ```txt
+/// child read()
+/// child locked
+/// parent read()
+/// parent locked
+/// parent unlock()
+/// child unlock()
+/// ```
+pub struct SharedRwLock {
+    rwlock: SharedMemoryObject<pthread_rwlock_t>,
+    owner_pid: pid_t,
+}
+
+impl SharedRwLock {
+    /// Creates new [`SharedRwLock`]
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Self::init(None)
+    }
+
+    /// Creates new [`SharedRwLock`] which prefers writers
+    ///
+    /// By default readers may acquire lock while writer is waiting, so frequent readers can starve writer forever.
+    /// Lock created with this function blocks new readers while writer is waiting. This is glibc extension
+    /// `PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP`: process holding read lock must not lock for reading again,
+    /// otherwise it will deadlock if writer is waiting.
+    ///
+    /// # Errors
+    /// If C library is not glibc, returns [`SyncError::InvalidInput`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlockattr_setkind_np`](https://man7.org/linux/man-pages/man3/pthread_rwlockattr_setkind_np.3.html).
+    #[cfg(target_os = "linux")]
+    pub fn new_writer_preferring() -> Result<Self, SyncError> {
+        Self::init(Some(PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP))
+    }
+
+    /// Creates rwlock of given `kind` (see `pthread_rwlockattr_setkind_np()`), or of default kind if it is `None`.
+    fn init(kind: Option<c_int>) -> Result<Self, SyncError> {
+        // mapping is zeroed, which is valid value to pass to pthread_rwlock_init(), so no initializer is needed
+        let mut rwlock = SharedMemoryObject::<pthread_rwlock_t>::new_uninit()?;
+        initialize_rwlock(unsafe { rwlock.get_mut().assume_init_mut() }, kind)?;
+        let rwlock = unsafe { rwlock.assume_init() };
+
+        let owner_pid = getpid();
+        Ok(Self { rwlock, owner_pid })
+    }
+
+    /// Locks for reading.
+    ///
+    /// This function will block until no process holds write lock.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlock_rdlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_rdlock.3p.html).
+    pub fn read(&mut self) -> Result<(), SyncError> {
+        match unsafe { pthread_rwlock_rdlock(self.rwlock.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_rwlock_rdlock", err)),
+        }
+    }
+
+    /// Tries to lock for reading without blocking.
+    ///
+    /// Returns `Ok(true)` if read lock was acquired and `Ok(false)` if write lock is held by another process.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `EBUSY`, returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlock_tryrdlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_rdlock.3p.html).
+    pub fn try_read(&mut self) -> Result<bool, SyncError> {
+        match unsafe { pthread_rwlock_tryrdlock(self.rwlock.raw_ptr()) } {
+            0 => Ok(true),
+            EBUSY => Ok(false),
+            err => Err(SyncError::from_errno("pthread_rwlock_tryrdlock", err)),
+        }
+    }
+
+    /// Locks for writing.
+    ///
+    /// This function will block until no other process holds read or write lock.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlock_wrlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_wrlock.3p.html).
+    pub fn write(&mut self) -> Result<(), SyncError> {
+        match unsafe { pthread_rwlock_wrlock(self.rwlock.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_rwlock_wrlock", err)),
+        }
+    }
+
+    /// Tries to lock for writing without blocking.
+    ///
+    /// Returns `Ok(true)` if write lock was acquired and `Ok(false)` if lock is held by another process.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `EBUSY`, returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlock_trywrlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_wrlock.3p.html).
+    pub fn try_write(&mut self) -> Result<bool, SyncError> {
+        match unsafe { pthread_rwlock_trywrlock(self.rwlock.raw_ptr()) } {
+            0 => Ok(true),
+            EBUSY => Ok(false),
+            err => Err(SyncError::from_errno("pthread_rwlock_trywrlock", err)),
+        }
+    }
+
+    /// Releases read or write lock held by this process.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_rwlock_unlock`](https://man7.org/linux/man-pages/man3/pthread_rwlock_unlock.3p.html).
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        match unsafe { pthread_rwlock_unlock(self.rwlock.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_rwlock_unlock", err)),
+        }
+    }
+}
+
+impl Drop for SharedRwLock {
+    fn drop(&mut self) {
+        if getpid() == self.owner_pid {
+            match unsafe { pthread_rwlock_destroy(self.rwlock.raw_ptr()) } {
+                0 => {}
+                err => report_primitive_drop_error(
+                    "destroy",
+                    "rwlock",
+                    self.rwlock.as_ptr(),
+                    self.owner_pid,
+                    SyncError::from_errno("pthread_rwlock_destroy", err),
+                ),
+            }
+        }
+    }
+}
+
+impl sealed::Sealed for SharedRwLock {}
+
+impl SharedPrimitive for SharedRwLock {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
+    }
+}
+
+fn initialize_rwlock(rwlock: &mut pthread_rwlock_t, kind: Option<c_int>) -> Result<(), SyncError> {
+    let mut attr: pthread_rwlockattr_t = unsafe { std::mem::zeroed() };
+    check_pthread_err("pthread_rwlockattr_init", unsafe {
+        pthread_rwlockattr_init(&mut attr)
+    })?;
+
+    if let Err(err) = check_pshared_err("pthread_rwlockattr_setpshared", unsafe {
+        pthread_rwlockattr_setpshared(&mut attr, PTHREAD_PROCESS_SHARED)
+    }) {
+        destroy_rwlockattr(attr).expect("cannot destroy rwlockattr");
+        return Err(err);
+    }
+
+    if let Some(kind) = kind {
+        if let Err(err) = set_kind(&mut attr, kind) {
+            destroy_rwlockattr(attr).expect("cannot destroy rwlockattr");
+            return Err(err);
+        }
+    }
+
+    let ret = check_pthread_err("pthread_rwlock_init", unsafe {
+        pthread_rwlock_init(rwlock, &attr)
+    });
+
+    destroy_rwlockattr(attr).expect("cannot destroy rwlockattr");
+
+    ret
+}
+
+/// Not exported by libc, value is taken from glibc `<pthread.h>`.
+#[cfg(target_os = "linux")]
+const PTHREAD_RWLOCK_PREFER_WRITER_NONRECURSIVE_NP: c_int = 2;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn set_kind(attr: &mut pthread_rwlockattr_t, kind: c_int) -> Result<(), SyncError> {
+    check_pthread_err("pthread_rwlockattr_setkind_np", unsafe {
+        libc::pthread_rwlockattr_setkind_np(attr, kind)
+    })
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn set_kind(_attr: &mut pthread_rwlockattr_t, _kind: c_int) -> Result<(), SyncError> {
+    Err(SyncError::InvalidInput(
+        "writer-preferring rwlock is only supported with glibc",
+    ))
+}
+
+fn destroy_rwlockattr(mut attr: pthread_rwlockattr_t) -> Result<(), SyncError> {
+    check_pthread_err("pthread_rwlockattr_destroy", unsafe {
+        pthread_rwlockattr_destroy(&mut attr)
+    })?;
+    Ok(())
+}
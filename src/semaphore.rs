@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use libc::{c_int, c_uint, pid_t, sem_post, sem_t, sem_trywait, sem_wait, ENOSYS};
+#[cfg(not(target_os = "macos"))]
+use libc::{sem_destroy, sem_getvalue, sem_init, sem_timedwait};
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    clock::ClockId,
+    error::{report_primitive_drop_error, OsError, SyncError},
+    shared_memory::SharedMemoryObject,
+    util::{check_libc_err, deadline_after, getpid},
+};
+
+/// Counting semaphore that can be shared between processes.
+///
+/// Waits can be interrupted by signal handlers, see [`set_retry_interrupted`](#method.set_retry_interrupted).
+///
+/// Dropping semaphore in creating process while it being waited will cause undefined behaviour.
+/// It is recommended to drop this semaphore in creating process only after no other process has access to it.
+///
+/// For more information see [`sem_init`](https://man7.org/linux/man-pages/man3/sem_init.3.html), [`sem_wait`](https://man7.org/linux/man-pages/man3/sem_wait.3.html), [`sem_post`](https://man7.org/linux/man-pages/man3/sem_post.3.html) and [`SharedMemoryObject`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// # use std::thread::sleep;
+/// # use std::time::Duration;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedSemaphore;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let semaphore = SharedSemaphore::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// if pid == 0 {
+///     println!("child wait()");
+///     semaphore.wait()?;
+///     println!("child acquired");
+/// } else {
+///     sleep(Duration::from_millis(40));
+///     println!("parent post()");
+///     semaphore.post()?;
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// Output:
+/// ```txt
+/// child wait()
+/// parent post()
+/// child acquired
+/// ```
+pub struct SharedSemaphore {
+    semaphore: SharedMemoryObject<sem_t>,
+    owner_pid: pid_t,
+    retry_interrupted: bool,
+}
+
+impl SharedSemaphore {
+    /// Creates new [`SharedSemaphore`] with given initial value
+    ///
+    /// # Errors
+    /// If platform doesn't support process-shared semaphores (e.g. macOS, which has no unnamed semaphores at all),
+    /// returns [`SyncError::ProcessSharedUnsupported`]. If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new(value: c_uint) -> Result<Self, SyncError> {
+        // mapping is zeroed, which is valid value to pass to sem_init()
+        let mut semaphore = SharedMemoryObject::<sem_t>::new_uninit()?;
+        check_libc_err("sem_init", unsafe {
+            sem_init(semaphore.get_mut().as_mut_ptr(), 1, value)
+        })
+        .map_err(|err| match err.raw_os_error() {
+            // process-shared semaphores are not supported
+            Some(ENOSYS) => SyncError::ProcessSharedUnsupported(OsError::new("sem_init", ENOSYS)),
+            _ => err,
+        })?;
+        let semaphore = unsafe { semaphore.assume_init() };
+
+        let owner_pid = getpid();
+        Ok(Self {
+            semaphore,
+            owner_pid,
+            retry_interrupted: false,
+        })
+    }
+
+    /// Sets whether waits interrupted by signal handler are retried.
+    ///
+    /// By default `sem_wait()` interrupted by signal handler fails with `EINTR` (regardless of `SA_RESTART`), which
+    /// is returned as [`SyncError::Interrupted`], so caller can run its own logic, e.g. check flag set by handler,
+    /// before waiting again. When `retry` is `true`, interrupted waits are restarted instead. Timed waits are restarted
+    /// with the same deadline, so total timeout is not extended. Setting is local to calling process.
+    pub fn set_retry_interrupted(&mut self, retry: bool) {
+        self.retry_interrupted = retry;
+    }
+
+    /// Decrements semaphore.
+    ///
+    /// This function will block until semaphore value is greater than zero.
+    ///
+    /// # Errors
+    /// If wait is interrupted by signal handler, returns [`SyncError::Interrupted`], unless retrying is enabled with
+    /// [`set_retry_interrupted`](#method.set_retry_interrupted). If call fails otherwise, returns corresponding
+    /// [`SyncError`]. For possible errors see [`sem_wait`](https://man7.org/linux/man-pages/man3/sem_wait.3.html).
+    pub fn wait(&self) -> Result<(), SyncError> {
+        loop {
+            match check_libc_err("sem_wait", unsafe { sem_wait(self.semaphore.raw_ptr()) }) {
+                Ok(_) => return Ok(()),
+                Err(SyncError::Interrupted(_)) if self.retry_interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Tries to decrement semaphore without blocking.
+    ///
+    /// Returns `Ok(true)` if semaphore was decremented and `Ok(false)` if its value is zero.
+    ///
+    /// # Errors
+    /// If call fails with anything other than `EAGAIN`, returns corresponding [`SyncError`]. For possible errors see [`sem_trywait`](https://man7.org/linux/man-pages/man3/sem_wait.3.html).
+    pub fn try_wait(&self) -> Result<bool, SyncError> {
+        match check_libc_err("sem_trywait", unsafe {
+            sem_trywait(self.semaphore.raw_ptr())
+        }) {
+            Ok(_) => Ok(true),
+            Err(SyncError::WouldBlock(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Decrements semaphore, giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(true)` if semaphore was decremented and `Ok(false)` if timeout expired first.
+    /// Deadline is measured against [`ClockId::Realtime`], since it is the only clock supported by `sem_timedwait`,
+    /// so it is affected by system time changes.
+    ///
+    /// # Errors
+    /// Interrupted wait is handled the same way as in [`wait`](#method.wait). If call fails with anything other than
+    /// `ETIMEDOUT`, returns corresponding [`SyncError`]. For possible errors see [`sem_timedwait`](https://man7.org/linux/man-pages/man3/sem_wait.3.html).
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<bool, SyncError> {
+        let deadline = deadline_after(ClockId::Realtime.as_raw(), timeout)?;
+        loop {
+            match check_libc_err("sem_timedwait", unsafe {
+                sem_timedwait(self.semaphore.raw_ptr(), &deadline)
+            }) {
+                Ok(_) => return Ok(true),
+                Err(SyncError::Timeout(_)) => return Ok(false),
+                Err(SyncError::Interrupted(_)) if self.retry_interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Returns current value of semaphore.
+    ///
+    /// **Value is only a snapshot**, it may be changed by another process right after this function returns.
+    /// When processes are blocked waiting on semaphore, POSIX allows value to be either zero or negative number,
+    /// absolute value of which is number of waiters. Linux always reports zero.
+    ///
+    /// # Errors
+    /// If call fails, returns corresponding [`SyncError`]. For possible errors see [`sem_getvalue`](https://man7.org/linux/man-pages/man3/sem_getvalue.3.html).
+    pub fn value(&self) -> Result<c_int, SyncError> {
+        let mut value: c_int = 0;
+        check_libc_err("sem_getvalue", unsafe {
+            sem_getvalue(self.semaphore.raw_ptr(), &mut value)
+        })?;
+        Ok(value)
+    }
+
+    /// Increments semaphore, waking up one of processes waiting on it.
+    ///
+    /// # Errors
+    /// If call fails, returns corresponding [`SyncError`]. For possible errors see [`sem_post`](https://man7.org/linux/man-pages/man3/sem_post.3.html).
+    pub fn post(&self) -> Result<(), SyncError> {
+        check_libc_err("sem_post", unsafe { sem_post(self.semaphore.raw_ptr()) })?;
+        Ok(())
+    }
+}
+
+impl Drop for SharedSemaphore {
+    fn drop(&mut self) {
+        if getpid() == self.owner_pid {
+            if let Err(err) = check_libc_err("sem_destroy", unsafe {
+                sem_destroy(self.semaphore.raw_ptr())
+            }) {
+                report_primitive_drop_error(
+                    "destroy",
+                    "semaphore",
+                    self.semaphore.as_ptr(),
+                    self.owner_pid,
+                    err,
+                );
+            }
+        }
+    }
+}
+
+impl sealed::Sealed for SharedSemaphore {}
+
+impl SharedPrimitive for SharedSemaphore {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
+    }
+}
+
+// macOS declares unnamed semaphores, but doesn't implement them: sem_init() always fails with ENOSYS, so that other
+// functions are never reached. libc crate doesn't expose them for macOS, and sem_timedwait() is missing altogether.
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn sem_init(sem: *mut sem_t, pshared: c_int, value: c_uint) -> c_int;
+    fn sem_getvalue(sem: *mut sem_t, value: *mut c_int) -> c_int;
+    fn sem_destroy(sem: *mut sem_t) -> c_int;
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn sem_timedwait(_sem: *mut sem_t, _deadline: *const libc::timespec) -> c_int {
+    *libc::__error() = ENOSYS;
+    -1
+}
@@ -1,5 +1,24 @@
-use libc::{c_void, mmap, munmap, MAP_ANONYMOUS, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE};
-use std::{mem::size_of, ptr::null_mut};
+use libc::{
+    c_int, c_void, close, fstat, ftruncate, getrlimit, mmap, munmap, off_t, pid_t, rlimit,
+    shm_open, shm_unlink, stat, sysconf, _SC_PAGESIZE, EINVAL, ENOENT, ENOMEM, MAP_ANONYMOUS,
+    MAP_FAILED, MAP_PRIVATE, MAP_SHARED, O_CREAT, O_EXCL, O_RDWR, PROT_READ, PROT_WRITE, RLIMIT_AS,
+    RLIM_INFINITY,
+};
+use std::{
+    alloc::Layout,
+    ffi::CString,
+    fs::File,
+    mem::{size_of, ManuallyDrop, MaybeUninit},
+    os::fd::{AsRawFd, OwnedFd, RawFd},
+    ptr::{null_mut, slice_from_raw_parts_mut, NonNull},
+    slice::{Iter, IterMut, SliceIndex},
+};
+
+use crate::{
+    alloc::RegionAllocator,
+    error::{report_drop_error, OsError, SyncError},
+    util::{check_libc_err, getpid, last_errno},
+};
 
 /// An object that can be shared between processes.
 ///
@@ -8,6 +27,35 @@ use std::{mem::size_of, ptr::null_mut};
 ///
 /// For more details see [man page](https://man7.org/linux/man-pages/man2/mmap.2.html).
 ///
+/// # Drop
+/// Every process unmaps memory when it drops its handle, but destructor of underlying object runs only once, when it
+/// is dropped in creating process. Objects attached with [`open_named`](#method.open_named) or wrapped with
+/// [`from_raw_ptr`](#method.from_raw_ptr) are never dropped. **Dropping object in creating process while other
+/// processes still use it is undefined behaviour**, just like for primitives of this crate. Moreover, destructor
+/// runs in creating process only, so resources which are only valid in some other process (e.g. file descriptors
+/// opened after `fork()`) must not be owned by shared object.
+///
+/// # Zero-sized types
+/// Zero-sized objects (e.g. `()`) and empty slices are not mapped at all, just like `Box` doesn't allocate them:
+/// object points to dangling, but properly aligned address, and nothing is unmapped on drop. Such objects can be
+/// created and accessed as usual, and their destructors still run in creating process only.
+///
+/// # Memory ordering
+/// Shared mapping is the same physical memory in every process, so Rust memory model applies to processes exactly
+/// as it applies to threads. Plain reads and writes through [`get`](#method.get) and [`get_mut`](#method.get_mut) are
+/// not atomic: reading object while another process writes it is a data race, and may observe torn value (e.g. half of
+/// `u64` on 32-bit platform, or partially written struct).
+///
+/// Locking primitives of this crate synchronize memory as required by POSIX: unlocking (`pthread_mutex_unlock()`,
+/// `sem_post()`, etc.) has release semantics and locking has acquire semantics, so everything written before unlock
+/// is visible to process which locks next. Accessing object only while holding the same lock is therefore enough.
+/// For lock-free publication use atomics, e.g. [`SharedAtomicU32`](crate::SharedAtomicU32), together with
+/// [`fence`](crate::fence) or `Release`/`Acquire` orderings.
+///
+/// # Threads
+/// Handle is [`Send`] and [`Sync`] whenever `T` is both, so it can be moved to or shared with other threads of the
+/// same process. Threads see the same mapping just like processes do, so this doesn't add any new requirements.
+///
 /// # Example
 /// ```rust
 /// # use std::error::Error;
@@ -26,87 +74,1102 @@ use std::{mem::size_of, ptr::null_mut};
 /// let pid = unsafe { fork() };
 /// assert!(pid >= 0);
 ///
+/// // access is synchronized with sleeps, which is only good enough for example
 /// if pid == 0 {
-///     assert_eq!(*shared.get(), 123);
-///     *shared.get_mut() = 456;
+///     assert_eq!(unsafe { *shared.get() }, 123);
+///     unsafe { *shared.get_mut() = 456 };
 ///     sleep(Duration::from_millis(40));
-///     assert_eq!(*shared.get(), 789);
+///     assert_eq!(unsafe { *shared.get() }, 789);
 /// } else {
 ///     sleep(Duration::from_millis(20));
-///     assert_eq!(*shared.get(), 456);
-///     *shared.get_mut() = 789;
+///     assert_eq!(unsafe { *shared.get() }, 456);
+///     unsafe { *shared.get_mut() = 789 };
 /// }
 /// #
 /// #     Ok(())
 /// # }
 /// ```
-pub struct SharedMemoryObject<T> {
+pub struct SharedMemoryObject<T: ?Sized> {
     ptr: *mut T,
+    len: usize,
+    unmap_on_drop: bool,
+    owner_pid: pid_t,
+    /// Descriptor of memfd backing mapping, see [`new_memfd`](#method.new_memfd) and [`from_fd`](#method.from_fd).
+    fd: Option<OwnedFd>,
+    /// Allocator which frees memory instead of `munmap()`, see [`new_in`](#method.new_in).
+    allocator: Option<(Box<dyn RegionAllocator>, Layout)>,
 }
 
 impl<T: Sync + Send> SharedMemoryObject<T> {
     /// Allocates shared memory and moves `obj` there.
     ///
+    /// Only bytes of `obj` itself are placed in shared memory, so **`T` must be self-contained**. Pointers into private
+    /// memory of process, such as `Box`, `String`, `Vec` or references, are copied as is: after `fork()` they point to
+    /// separate copies of heap in each process, and changes made through them are not seen by other processes (or
+    /// memory is freed in one process while used in another). With `bytemuck` feature
+    /// [`new_checked`](#method.new_checked) rejects such types at compile time.
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new(obj: T) -> Result<Self, SyncError> {
+        let addr = allocate_shared_memory(size_of::<T>())?;
+
+        let addr = addr as *mut T;
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates at least `bytes` of shared memory and moves `obj` to its beginning.
+    ///
+    /// Mapping is `max(size_of::<T>(), bytes)` long, whole length is shared and unmapped on drop. Space after `obj`
+    /// can be used for trailing data, e.g. flexible array following a header, see [`capacity`](#method.capacity).
+    ///
     /// # Errors
-    /// If allocation fails returns error from [`last_os_error`].
+    /// If `bytes` exceeds `isize::MAX`, returns [`SyncError::InvalidInput`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn with_capacity(obj: T, bytes: usize) -> Result<Self, SyncError> {
+        let len = bytes.max(size_of::<T>());
+        let addr = allocate_shared_memory(len)? as *mut T;
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len,
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates shared memory aligned to at least `align` bytes and moves `obj` there.
+    ///
+    /// Mappings are always page-aligned, so this is only useful for alignments greater than page size, e.g. to
+    /// place object at the beginning of huge page or NUMA-friendly boundary. Larger region is mapped and trimmed down to
+    /// the aligned part, so no extra memory stays mapped.
+    ///
+    /// # Errors
+    /// If `align` is not a power of two, returns [`SyncError::InvalidInput`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_aligned(obj: T, align: usize) -> Result<Self, SyncError> {
+        if !align.is_power_of_two() {
+            return Err(SyncError::InvalidInput("alignment must be power of two"));
+        }
+
+        let addr = allocate_aligned_shared_memory(size_of::<T>(), align)? as *mut T;
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates shared memory using extra `mmap()` flags and moves `obj` there.
+    ///
+    /// `flags` are OR'd into `MAP_SHARED | MAP_ANONYMOUS`, e.g. `MAP_POPULATE` to prefault pages, or `MAP_HUGETLB`
+    /// to back object with huge pages. Mappings with `MAP_HUGETLB` are rounded up to huge page size.
+    ///
+    /// For more details see [man page](https://man7.org/linux/man-pages/man2/mmap.2.html).
+    ///
+    /// # Errors
+    /// If `flags` contain `MAP_PRIVATE`, returns [`SyncError::InvalidInput`], since private mapping is not shared.
+    /// If `MAP_HUGETLB` is requested but huge pages cannot be allocated, returns [`SyncError::HugePagesUnavailable`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn map_anonymous_with_flags(obj: T, flags: c_int) -> Result<Self, SyncError> {
+        if flags & MAP_PRIVATE != 0 {
+            return Err(SyncError::InvalidInput(
+                "MAP_PRIVATE cannot be used for shared memory",
+            ));
+        }
+
+        let len = mapping_len(size_of::<T>(), flags)?;
+        let addr =
+            map_shared_memory(len, MAP_ANONYMOUS | flags, -1, 0).map_err(|err| match err {
+                SyncError::MmapFailed(err)
+                    if matches!(err.errno(), ENOMEM | EINVAL) && is_hugetlb(flags) =>
+                {
+                    SyncError::HugePagesUnavailable(err)
+                }
+                err => err,
+            })?;
+
+        let addr = addr as *mut T;
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len,
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates shared memory for `T` without initializing it.
+    ///
+    /// Unlike [`new`](#method.new), no value of `T` is constructed on the stack, which is useful for large objects.
+    /// Memory is guaranteed to be zeroed, since it is backed by fresh anonymous mapping.
     ///
-    /// [`last_os_error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html#method.last_os_error
-    pub fn new(obj: T) -> std::io::Result<Self> {
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_uninit() -> Result<SharedMemoryObject<MaybeUninit<T>>, SyncError> {
         let addr = allocate_shared_memory(size_of::<T>())?;
 
+        Ok(SharedMemoryObject {
+            ptr: addr as *mut MaybeUninit<T>,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates memory with `allocator` and moves `obj` there
+    ///
+    /// Memory is freed with the same allocator when object is dropped. With [`SharedAllocator`](crate::SharedAllocator)
+    /// this is equivalent to [`new`](#method.new), see [`RegionAllocator`] for other allocators.
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_in(obj: T, allocator: impl RegionAllocator + 'static) -> Result<Self, SyncError> {
+        let mut uninit = Self::new_uninit_in(allocator)?;
+        unsafe { uninit.get_mut() }.write(obj);
+        Ok(unsafe { uninit.assume_init() })
+    }
+
+    /// Allocates zeroed memory for `T` with `allocator`, without initializing it
+    ///
+    /// Memory is freed with the same allocator when object is dropped, see [`new_in`](#method.new_in).
+    ///
+    /// # Errors
+    /// If `T` is zero-sized, returns [`SyncError::InvalidInput`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_uninit_in(
+        allocator: impl RegionAllocator + 'static,
+    ) -> Result<SharedMemoryObject<MaybeUninit<T>>, SyncError> {
+        let layout = Layout::new::<T>();
+        if layout.size() == 0 {
+            return Err(SyncError::InvalidInput("cannot allocate zero-sized object"));
+        }
+        let ptr = allocator.allocate(layout)?;
+
+        Ok(SharedMemoryObject {
+            ptr: ptr.as_ptr().cast(),
+            len: layout.size(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: Some((Box::new(allocator), layout)),
+        })
+    }
+
+    /// Allocates shared memory and writes `T::default()` there.
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_default() -> Result<Self, SyncError>
+    where
+        T: Default,
+    {
+        let mut obj = Self::new_uninit()?;
+        unsafe { obj.get_mut().write(T::default()) };
+        Ok(unsafe { obj.assume_init() })
+    }
+
+    /// Creates named shared memory object `name` and moves `obj` there.
+    ///
+    /// Unrelated processes can attach to created object using [`open_named`](#method.open_named). Name remains
+    /// registered in system until removed with [`unlink_named`].
+    ///
+    /// For more information see [`shm_open`](https://man7.org/linux/man-pages/man3/shm_open.3.html).
+    ///
+    /// # Errors
+    /// If object with such name already exists, or any call fails, returns corresponding [`SyncError`].
+    pub fn create_named(name: &str, obj: T) -> Result<Self, SyncError> {
+        let addr = open_named_shared_memory(name, O_CREAT | O_EXCL, size_of::<T>())?;
+
         let addr = addr as *mut T;
-        unsafe { *addr = obj };
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Attaches to named shared memory object `name`.
+    ///
+    /// If `create` is `true` and object doesn't exist, it is created and filled with zeroes.
+    ///
+    /// For more information see [`shm_open`](https://man7.org/linux/man-pages/man3/shm_open.3.html).
+    ///
+    /// # Errors
+    /// If object is smaller than `T`, returns [`SyncError::InvalidInput`].
+    /// If any call fails returns corresponding [`SyncError`].
+    ///
+    /// # Safety
+    /// Object must contain valid `T` (which includes all zeroes if object is created by this call).
+    pub unsafe fn open_named(name: &str, create: bool) -> Result<Self, SyncError> {
+        let oflag = if create { O_CREAT } else { 0 };
+        let addr = open_named_shared_memory(name, oflag, size_of::<T>())?;
+
+        Ok(Self {
+            ptr: addr as *mut T,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: 0,
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Allocates shared memory backed by anonymous file created with `memfd_create()` and moves `obj` there.
+    ///
+    /// Unlike anonymous mapping, memory can be shared with unrelated processes: pass descriptor returned by
+    /// [`as_raw_fd`](#method.as_raw_fd) over Unix domain socket (`SCM_RIGHTS`), and map it in receiving process with
+    /// [`from_fd`](#method.from_fd). Descriptor is closed when returned object is dropped.
+    ///
+    /// For more information see [`memfd_create`](https://man7.org/linux/man-pages/man2/memfd_create.2.html).
+    ///
+    /// # Errors
+    /// If any call fails returns corresponding [`SyncError`].
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn new_memfd(obj: T) -> Result<Self, SyncError> {
+        let fd = check_libc_err("memfd_create", unsafe {
+            libc::memfd_create(c"process-sync".as_ptr(), libc::MFD_CLOEXEC)
+        })?;
+        // descriptor was just created and is owned by nobody else
+        let fd = unsafe { <OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd) };
+        let len = size_of::<T>();
+        check_libc_err("ftruncate", unsafe {
+            ftruncate(fd.as_raw_fd(), len as off_t)
+        })?;
+        let addr = map_shared_memory(len, 0, fd.as_raw_fd(), 0)? as *mut T;
+        unsafe { std::ptr::write(addr, obj) };
+
+        Ok(Self {
+            ptr: addr,
+            len,
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: Some(fd),
+            allocator: None,
+        })
+    }
+
+    /// Maps shared memory file `fd`, e.g. received from process which created object with
+    /// [`new_memfd`](#method.new_memfd).
+    ///
+    /// Returned object takes ownership of `fd` and closes it when dropped. Like for
+    /// [`open_named`](#method.open_named), underlying object is never dropped by returned object.
+    ///
+    /// # Errors
+    /// If file is smaller than `T`, returns [`SyncError::InvalidInput`].
+    /// If any call fails returns corresponding [`SyncError`].
+    ///
+    /// # Safety
+    /// File must contain valid `T`, which must not be accessed through other mappings of `fd` in ways violating
+    /// [`get_mut`](#method.get_mut).
+    pub unsafe fn from_fd(fd: OwnedFd) -> Result<Self, SyncError> {
+        let len = size_of::<T>();
+        resize_shared_memory(fd.as_raw_fd(), false, len)?;
+        let addr = map_shared_memory(len, 0, fd.as_raw_fd(), 0)?;
+
+        Ok(Self {
+            ptr: addr as *mut T,
+            len,
+            unmap_on_drop: true,
+            owner_pid: 0,
+            fd: Some(fd),
+            allocator: None,
+        })
+    }
+
+    /// Maps `size_of::<T>()` bytes of `file` starting at `offset`.
+    ///
+    /// Unlike anonymous and named shared memory, contents are persisted in the file, so they survive exit of all
+    /// processes and reboots, and can be attached again later. If file is shorter than `offset` plus size of `T`, it is
+    /// extended with zeroes. `file` must be opened for reading and writing, and can be closed after this call, since
+    /// mapping stays valid. Like for [`open_named`](#method.open_named), underlying object is never dropped by returned
+    /// object.
+    ///
+    /// For more information see [`mmap`](https://man7.org/linux/man-pages/man2/mmap.2.html).
+    ///
+    /// # Errors
+    /// If `offset` is negative or not a multiple of page size, returns [`SyncError::InvalidInput`].
+    /// If any call fails returns corresponding [`SyncError`].
+    ///
+    /// # Safety
+    /// File must contain valid `T` at `offset` (which includes all zeroes if file is extended by this call). File must
+    /// not be truncated while it is mapped, since accessing memory past the end of file raises `SIGBUS`.
+    pub unsafe fn from_file(file: &File, offset: i64) -> Result<Self, SyncError> {
+        let offset = off_t::try_from(offset)
+            .ok()
+            .filter(|&offset| offset >= 0 && (offset as usize).is_multiple_of(page_size()))
+            .ok_or(SyncError::InvalidInput(
+                "file offset must be non-negative multiple of page size",
+            ))?;
+        let len = size_of::<T>();
+        let end = (offset as u64)
+            .checked_add(len as u64)
+            .ok_or(SyncError::InvalidInput("file offset overflows"))?;
+        let file_len = file
+            .metadata()
+            .map_err(|err| SyncError::from_errno("fstat", err.raw_os_error().unwrap_or(EINVAL)))?
+            .len();
+        if file_len < end {
+            file.set_len(end).map_err(|err| {
+                SyncError::from_errno("ftruncate", err.raw_os_error().unwrap_or(EINVAL))
+            })?;
+        }
+        let addr = map_shared_memory(len, 0, file.as_raw_fd(), offset)?;
+
+        Ok(Self {
+            ptr: addr as *mut T,
+            len,
+            unmap_on_drop: true,
+            owner_pid: 0,
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Wraps existing memory, e.g. mapped with `shm_open()` and `mmap()`, without allocating.
+    ///
+    /// Memory is **not** unmapped when returned object is dropped, caller remains responsible for it.
+    ///
+    /// # Safety
+    /// `ptr` must be non-null, properly aligned for `T` and point to valid initialized `T`. Memory must stay mapped for
+    /// the whole lifetime of returned object, and must be mapped with `MAP_SHARED` for updates to be seen by other processes.
+    pub unsafe fn from_raw_ptr(ptr: *mut T) -> Self {
+        Self {
+            ptr,
+            len: size_of::<T>(),
+            unmap_on_drop: false,
+            owner_pid: 0,
+            fd: None,
+            allocator: None,
+        }
+    }
+
+    /// Consumes object without unmapping memory and returns pointer to underlying object.
+    ///
+    /// Mapping stays valid until it is unmapped manually, or by object reconstructed with [`from_raw`](#method.from_raw).
+    /// This parallels [`Box::into_raw`].
+    pub fn into_raw(self) -> *mut T {
+        let this = ManuallyDrop::new(self);
+        // mapping stays valid after descriptor is closed
+        drop(unsafe { std::ptr::read(&this.fd) });
+        drop(unsafe { std::ptr::read(&this.allocator) });
+        this.ptr
+    }
+
+    /// Reconstructs object from pointer returned by [`into_raw`](#method.into_raw).
+    ///
+    /// Memory is unmapped when returned object is dropped, and calling process becomes responsible for dropping
+    /// underlying object, see [Drop](#drop).
+    ///
+    /// # Safety
+    /// `ptr` must be returned by [`into_raw`](#method.into_raw) of object which owned its mapping, i.e. was not created
+    /// with [`from_raw_ptr`](#method.from_raw_ptr) or [`new_in`](#method.new_in), and its [`capacity`](#method.capacity) must be equal to size of
+    /// `T`, i.e. it was not created with [`with_capacity`](#method.with_capacity) or with `MAP_HUGETLB`. Mapping must
+    /// not be reconstructed or unmapped more than once.
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        Self {
+            ptr,
+            len: size_of::<T>(),
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        }
+    }
+
+    /// Consumes object without unmapping memory and returns reference to underlying object, which stays valid for
+    /// the rest of process lifetime.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn leak(self) -> &'static mut T {
+        &mut *self.into_raw()
+    }
+
+    /// Reinterprets the same memory as `U`, e.g. after migrating shared layout to new version in place.
+    ///
+    /// Mapping is neither copied nor remapped: returned object owns it, and unmaps it with the original length when
+    /// dropped. Destructor of `T` is never run, and destructor of `U` runs in creating process as usual.
+    ///
+    /// # Errors
+    /// If `U` is larger than mapping (see [`capacity`](#method.capacity)) or mapping is not aligned for `U`, returns
+    /// [`SyncError::InvalidInput`] and drops `self`.
+    ///
+    /// # Safety
+    /// Memory must contain valid `U`. All processes must stop accessing object as `T` before it is accessed as `U`,
+    /// e.g. migration must be done while holding lock which protects object.
+    pub unsafe fn cast<U: Sync + Send>(self) -> Result<SharedMemoryObject<U>, SyncError> {
+        if size_of::<U>() > self.len {
+            return Err(SyncError::InvalidInput(
+                "type is larger than shared memory object",
+            ));
+        }
+        if !self.ptr.cast::<U>().is_aligned() {
+            return Err(SyncError::InvalidInput(
+                "shared memory object is not aligned for type",
+            ));
+        }
+
+        let this = ManuallyDrop::new(self);
+        Ok(SharedMemoryObject {
+            ptr: this.ptr.cast::<U>(),
+            len: this.len,
+            unmap_on_drop: this.unmap_on_drop,
+            owner_pid: this.owner_pid,
+            fd: unsafe { std::ptr::read(&this.fd) },
+            allocator: unsafe { std::ptr::read(&this.allocator) },
+        })
+    }
+
+    /// Replaces underlying object with `value`, returning old object.
+    ///
+    /// # Safety
+    /// Taking `&mut self` only guarantees exclusive access within calling process, access is still unsynchronized
+    /// with other processes. See [`get_mut`](#method.get_mut).
+    pub unsafe fn replace(&mut self, value: T) -> T {
+        std::mem::replace(&mut *self.ptr, value)
+    }
+
+    /// Takes underlying object, leaving [`Default`] value in its place.
+    ///
+    /// # Safety
+    /// See [`replace`](#method.replace).
+    pub unsafe fn take(&mut self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Zeroable + Sync + Send> SharedMemoryObject<T> {
+    /// Allocates zeroed shared memory for `T`.
+    ///
+    /// Fresh anonymous mapping is already zeroed, so nothing is written and no value of `T` is constructed on the
+    /// stack, unlike [`new`](#method.new).
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn zeroed() -> Result<Self, SyncError> {
+        // all-zero bytes are valid T
+        Ok(unsafe { Self::new_uninit()?.assume_init() })
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::NoUninit + Sync + Send> SharedMemoryObject<T> {
+    /// Allocates shared memory and moves `obj` there, like [`new`](#method.new).
+    ///
+    /// `T` must be [`NoUninit`](bytemuck::NoUninit), i.e. plain data without pointers and padding, so it can't
+    /// accidentally share pointers into private memory of process:
+    /// ```compile_fail
+    /// # use process_sync::SharedMemoryObject;
+    /// let shared = SharedMemoryObject::new_checked(String::from("private heap"));
+    /// ```
+    ///
+    /// # Errors
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_checked(obj: T) -> Result<Self, SyncError> {
+        Self::new(obj)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod + Sync + Send> SharedMemoryObject<T> {
+    /// Returns underlying object as bytes, without copying.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self.get())
+    }
+
+    /// Returns underlying object as mutable bytes, without copying.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::bytes_of_mut(self.get_mut())
+    }
+}
+
+impl<T: Sync + Send> SharedMemoryObject<MaybeUninit<T>> {
+    /// Converts to initialized [`SharedMemoryObject<T>`], keeping the same mapping.
+    ///
+    /// # Safety
+    /// Underlying memory must contain valid `T`, see [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> SharedMemoryObject<T> {
+        let this = ManuallyDrop::new(self);
+        SharedMemoryObject {
+            ptr: this.ptr as *mut T,
+            len: this.len,
+            unmap_on_drop: this.unmap_on_drop,
+            owner_pid: this.owner_pid,
+            fd: unsafe { std::ptr::read(&this.fd) },
+            allocator: unsafe { std::ptr::read(&this.allocator) },
+        }
+    }
+}
+
+impl<T: Sync + Send> SharedMemoryObject<[T]> {
+    /// Allocates shared memory for `len` elements and initializes `i`-th of them with `init(i)`.
+    ///
+    /// # Errors
+    /// If size of slice overflows `usize`, returns [`SyncError::InvalidInput`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new_slice(len: usize, init: impl Fn(usize) -> T) -> Result<Self, SyncError> {
+        let bytes = len
+            .checked_mul(size_of::<T>())
+            .ok_or(SyncError::InvalidInput("shared slice size overflows usize"))?;
+        let addr = allocate_shared_memory(bytes)? as *mut T;
+
+        for i in 0..len {
+            unsafe { std::ptr::write(addr.add(i), init(i)) };
+        }
+
+        Ok(Self {
+            ptr: slice_from_raw_parts_mut(addr, len),
+            len: bytes,
+            unmap_on_drop: true,
+            owner_pid: getpid(),
+            fd: None,
+            allocator: None,
+        })
+    }
+
+    /// Grows slice to `new_len` elements, initializing `i`-th of new elements with `init(i)`.
+    ///
+    /// Elements are moved to new mapping and old one is unmapped, so pointers into slice are invalidated.
+    /// `mremap()` is not used, since memory past the original size of anonymous shared mapping can't be accessed
+    /// (`SIGBUS`). Other processes keep old mapping, so **slice must be grown before it is shared** (e.g. before
+    /// `fork()`), otherwise processes stop seeing each other's updates. Growing shared slice requires coordination, e.g.
+    /// creating new object and passing it to other processes.
+    ///
+    /// # Errors
+    /// If called not from creating process, or object doesn't own its anonymous mapping (see
+    /// [`unmap_on_drop`](#method.unmap_on_drop), [`as_raw_fd`](#method.as_raw_fd) and [`new_in`](#method.new_in)),
+    /// returns [`SyncError::InvalidInput`].
+    /// If `new_len` is less than current length or size of slice overflows `usize`, returns [`SyncError::InvalidInput`].
+    /// If allocation or unmapping fails returns corresponding [`SyncError`].
+    pub fn grow(&mut self, new_len: usize, init: impl Fn(usize) -> T) -> Result<(), SyncError> {
+        if self.owner_pid != getpid()
+            || !self.unmap_on_drop
+            || self.fd.is_some()
+            || self.allocator.is_some()
+        {
+            return Err(SyncError::InvalidInput(
+                "shared slice can only be grown by creating process",
+            ));
+        }
+        let len = self.ptr.len();
+        if new_len < len {
+            return Err(SyncError::InvalidInput("shared slice can't be shrunk"));
+        }
+        let bytes = new_len
+            .checked_mul(size_of::<T>())
+            .ok_or(SyncError::InvalidInput("shared slice size overflows usize"))?;
+        let addr = allocate_shared_memory(bytes)? as *mut T;
+
+        // elements are moved bitwise, old mapping is unmapped without dropping them
+        unsafe { std::ptr::copy_nonoverlapping(self.ptr as *const T, addr, len) };
+        for i in len..new_len {
+            unsafe { std::ptr::write(addr.add(i), init(i)) };
+        }
+
+        let old_ptr = std::mem::replace(&mut self.ptr, slice_from_raw_parts_mut(addr, new_len));
+        let old_len = std::mem::replace(&mut self.len, bytes);
+        free_shared_memory(old_ptr as *mut c_void, old_len)
+    }
+
+    /// Returns underlying elements as slice.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn as_slice(&self) -> &[T] {
+        self.get()
+    }
+
+    /// Returns underlying elements as mutable slice.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [T] {
+        self.get_mut()
+    }
+
+    /// Returns iterator over underlying elements.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn iter(&self) -> Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Returns iterator over underlying elements that allows modifying them.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    /// Returns element or subslice of underlying elements.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds, same as slice indexing.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn get_index<I: SliceIndex<[T]>>(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
 
-        Ok(Self { ptr: addr })
+    /// Returns mutable element or subslice of underlying elements.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds, same as slice indexing.
+    ///
+    /// # Safety
+    /// See [`get_mut`](#method.get_mut).
+    pub unsafe fn get_index_mut<I: SliceIndex<[T]>>(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
     }
+}
 
+impl<T: ?Sized + Sync + Send> SharedMemoryObject<T> {
     /// Returns reference to underlying object.
     ///
     /// # Safety
     /// See [`get_mut`](#method.get_mut).
-    pub fn get(&self) -> &T {
-        unsafe { &*self.ptr }
+    pub unsafe fn get(&self) -> &T {
+        &*self.ptr
     }
 
     /// Returns mutable reference to underlying object.
     ///
+    /// For safe access use [`SharedMutex::with_data`](crate::SharedMutex::with_data), which gives access to data only
+    /// while mutex is locked.
+    ///
     /// # Safety
-    /// This function (and [`get`](#method.get)) is always safe to call, but access to data under returned reference
-    /// must be somehow synchronized with another processes to avoid data race.
-    pub fn get_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.ptr }
+    /// Other processes can access the same object at any time, so returned reference may alias with references in
+    /// other processes. Caller must synchronize access to data with other processes to avoid data race, e.g. by holding
+    /// some lock. Types that are safe to access concurrently, such as atomics, can be used without synchronization.
+    pub unsafe fn get_mut(&mut self) -> &mut T {
+        &mut *self.ptr
+    }
+
+    /// Returns length of underlying mapping in bytes.
+    ///
+    /// It is at least size of the object, but may be larger, e.g. for objects created with
+    /// [`with_capacity`](#method.with_capacity) or backed by huge pages.
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Returns pointer to underlying object, e.g. to pass shared memory to foreign code.
+    ///
+    /// Pointer is only valid while this object is alive, since mapping is unmapped when it is dropped.
+    /// Dereferencing it is subject to the same rules as [`get`](#method.get).
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+
+    /// Returns mutable pointer to underlying object, e.g. to pass shared memory to foreign code.
+    ///
+    /// Pointer is only valid while this object is alive, since mapping is unmapped when it is dropped.
+    /// Dereferencing it is subject to the same rules as [`get_mut`](#method.get_mut).
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr
+    }
+
+    /// Returns length of underlying mapping in bytes, starting at [`as_ptr`](#method.as_ptr).
+    ///
+    /// Same as [`capacity`](#method.capacity), so `(as_mut_ptr(), mapped_len())` describes whole mapping even for
+    /// slices and objects created with [`with_capacity`](#method.with_capacity).
+    pub fn mapped_len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns descriptor of file backing mapping, or `None` for anonymous mappings.
+    ///
+    /// Only objects created with [`new_memfd`](#method.new_memfd) or [`from_fd`](#method.from_fd) have descriptor.
+    /// It is owned by this object and must not be closed.
+    pub fn as_raw_fd(&self) -> Option<RawFd> {
+        self.fd.as_ref().map(AsRawFd::as_raw_fd)
+    }
+
+    /// Excludes mapping from child processes created with `fork()` after this call (`MADV_DONTFORK`).
+    ///
+    /// Such children don't inherit the mapping at all, so they can't see or modify the object. Children forked
+    /// earlier are not affected. Use [`set_dofork`](#method.set_dofork) to make mapping inherited again.
+    ///
+    /// For more details see [man page](https://man7.org/linux/man-pages/man2/madvise.2.html).
+    ///
+    /// # Safety
+    /// Handle is still copied to children, but points to memory not mapped in them: they must not access the object
+    /// in any way, including through primitives built on it. Their copies must be disposed of without dropping, e.g.
+    /// by exiting with [`std::process::exit`], or dropped only after disabling unmapping with
+    /// [`set_unmap_on_drop`](#method.set_unmap_on_drop), since memory at the same address may be reused by then.
+    ///
+    /// # Errors
+    /// If object is allocated with custom allocator (see [`new_in`](#method.new_in)), returns
+    /// [`SyncError::InvalidInput`]. If `madvise()` fails (e.g. object created with
+    /// [`from_raw_ptr`](#method.from_raw_ptr) is not page-aligned), returns corresponding [`SyncError`].
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub unsafe fn set_dontfork(&self) -> Result<(), SyncError> {
+        self.advise(libc::MADV_DONTFORK)
+    }
+
+    /// Makes mapping inherited by child processes created with `fork()` after this call again (`MADV_DOFORK`).
+    ///
+    /// This is the default for all mappings, and undoes [`set_dontfork`](#method.set_dontfork).
+    ///
+    /// # Errors
+    /// Same as [`set_dontfork`](#method.set_dontfork).
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_dofork(&self) -> Result<(), SyncError> {
+        self.advise(libc::MADV_DOFORK)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn advise(&self, advice: c_int) -> Result<(), SyncError> {
+        if self.allocator.is_some() {
+            return Err(SyncError::InvalidInput(
+                "madvise() can only be applied to mapped shared memory",
+            ));
+        }
+        check_libc_err("madvise", unsafe {
+            libc::madvise(self.ptr as *mut c_void, self.len, advice)
+        })?;
+        Ok(())
+    }
+
+    /// Returns `true` if mapping is unmapped when this object is dropped.
+    ///
+    /// This is the case for all objects, except created with [`from_raw_ptr`](#method.from_raw_ptr).
+    pub fn unmap_on_drop(&self) -> bool {
+        self.unmap_on_drop
+    }
+
+    /// Sets whether mapping is unmapped when this object is dropped.
+    ///
+    /// Disabling it leaves memory mapped in calling process after drop, e.g. when it is registered elsewhere and
+    /// unmapped manually. Setting affects only this handle, i.e. it is not shared with other processes.
+    ///
+    /// # Safety
+    /// If `unmap` is `true`, memory must be mapping of [`mapped_len`](#method.mapped_len) bytes, which is not unmapped
+    /// by anyone else, e.g. it must not be created with [`from_raw_ptr`](#method.from_raw_ptr) from memory owned by
+    /// other code.
+    pub unsafe fn set_unmap_on_drop(&mut self, unmap: bool) {
+        self.unmap_on_drop = unmap;
+    }
+
+    /// Creates second handle to the same object in calling process, without mapping memory again.
+    ///
+    /// Each process unmaps its mapping once, so copying handle would unmap it twice. Returned handle is therefore
+    /// non-owning: it neither unmaps memory nor drops object when dropped, like objects created with
+    /// [`from_raw_ptr`](#method.from_raw_ptr). Original handle stays responsible for both.
+    ///
+    /// # Safety
+    /// Returned handle must not be used after memory is unmapped by original handle, i.e. original handle must outlive
+    /// it. Only one handle per process must unmap memory, so if unmapping is moved to returned handle with
+    /// [`set_unmap_on_drop`](#method.set_unmap_on_drop), it must be disabled for original handle.
+    pub unsafe fn clone_handle(&self) -> Self {
+        Self {
+            ptr: self.ptr,
+            len: self.len,
+            unmap_on_drop: false,
+            owner_pid: 0,
+            fd: None,
+            allocator: None,
+        }
+    }
+
+    pub(crate) fn raw_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Sets process which drops underlying object. No process is chosen when `pid` is `0`.
+    pub(crate) fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
     }
 }
 
-impl<T> Drop for SharedMemoryObject<T> {
+// Handle only owns mapping and (optionally) descriptor and allocator, which are all thread-safe. Mapped object is
+// reachable from other threads the same way it is from other processes, so it must be both `Send` and `Sync`.
+unsafe impl<T: ?Sized + Sync + Send> Send for SharedMemoryObject<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for SharedMemoryObject<T> {}
+
+impl<T: ?Sized> Drop for SharedMemoryObject<T> {
     fn drop(&mut self) {
+        // object is shared, so it must be dropped only once
+        if std::mem::needs_drop::<T>() && getpid() == self.owner_pid {
+            unsafe { std::ptr::drop_in_place(self.ptr) };
+        }
         // every process owning shared memory object must free it individually
-        free_shared_memory(self.ptr as *mut c_void, size_of::<T>())
-            .expect("cannot munmap() shared memory");
+        if !self.unmap_on_drop {
+            return;
+        }
+        let ret = match &self.allocator {
+            Some((allocator, layout)) => unsafe {
+                allocator.deallocate(NonNull::new_unchecked(self.ptr.cast()), *layout)
+            },
+            None => free_shared_memory(self.ptr as *mut c_void, self.len),
+        };
+        if let Err(err) = ret {
+            report_drop_error("cannot munmap() shared memory", err);
+        }
+    }
+}
+
+/// Deserializes object and moves it into newly allocated shared memory, see [`SharedMemoryObject::new`].
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de> + Sync + Send> serde::Deserialize<'de>
+    for SharedMemoryObject<T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let obj = T::deserialize(deserializer)?;
+        Self::new(obj).map_err(serde::de::Error::custom)
     }
 }
 
-fn allocate_shared_memory(len: usize) -> std::io::Result<*mut c_void> {
+/// Removes named shared memory object created with [`SharedMemoryObject::create_named`] or [`SharedMemoryObject::open_named`].
+///
+/// Processes already attached to the object keep using it, it is destroyed once all of them detach.
+///
+/// For more information see [`shm_unlink`](https://man7.org/linux/man-pages/man3/shm_unlink.3p.html).
+///
+/// # Errors
+/// If call fails returns corresponding [`SyncError`].
+pub fn unlink_named(name: &str) -> Result<(), SyncError> {
+    let name = shm_name(name)?;
+    check_libc_err("shm_unlink", unsafe { shm_unlink(name.as_ptr()) })?;
+    Ok(())
+}
+
+fn allocate_shared_memory(len: usize) -> Result<*mut c_void, SyncError> {
+    map_shared_memory(len, MAP_ANONYMOUS, -1, 0)
+}
+
+pub(crate) fn allocate_aligned_shared_memory(
+    len: usize,
+    align: usize,
+) -> Result<*mut c_void, SyncError> {
+    let page_size = page_size();
+    if align <= page_size {
+        return allocate_shared_memory(len);
+    }
+    if len == 0 {
+        return Ok(dangling(align));
+    }
+
+    // map enough to contain aligned region of len bytes, then unmap parts before and after it
+    let overflow = SyncError::InvalidInput("shared memory size overflows usize");
+    let len = len
+        .checked_next_multiple_of(page_size)
+        .ok_or(overflow.clone())?;
+    let total = len.checked_add(align - page_size).ok_or(overflow)?;
+    let base = allocate_shared_memory(total)?;
+
+    let head = (base as usize).next_multiple_of(align) - base as usize;
+    let tail = total - head - len;
+    let addr = unsafe { base.cast::<u8>().add(head) };
+    if head > 0 {
+        free_shared_memory(base, head)?;
+    }
+    if tail > 0 {
+        free_shared_memory(unsafe { addr.add(len) } as *mut c_void, tail)?;
+    }
+    Ok(addr as *mut c_void)
+}
+
+/// Returns non-null pointer aligned to `align`, which is used instead of mapping for zero-sized objects, like in `Box`.
+fn dangling(align: usize) -> *mut c_void {
+    std::ptr::without_provenance_mut(align)
+}
+
+fn page_size() -> usize {
+    unsafe { sysconf(_SC_PAGESIZE) as usize }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_hugetlb(flags: c_int) -> bool {
+    flags & libc::MAP_HUGETLB != 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn is_hugetlb(_flags: c_int) -> bool {
+    false
+}
+
+/// Returns length of anonymous mapping of `len` bytes with given flags. Huge page mappings must be unmapped with length
+/// that is multiple of huge page size.
+fn mapping_len(len: usize, flags: c_int) -> Result<usize, SyncError> {
+    if !is_hugetlb(flags) {
+        return Ok(len);
+    }
+    let page_size = huge_page_size(flags)?;
+    len.checked_next_multiple_of(page_size)
+        .ok_or(SyncError::InvalidInput(
+            "shared memory size overflows usize",
+        ))
+}
+
+fn huge_page_size(flags: c_int) -> Result<usize, SyncError> {
+    // see MAP_HUGE_SHIFT and MAP_HUGE_MASK in linux/mman.h
+    let log2 = (flags >> 26) & 0x3f;
+    if log2 != 0 {
+        return 1usize
+            .checked_shl(log2 as u32)
+            .ok_or(SyncError::InvalidInput("huge page size overflows usize"));
+    }
+
+    // default huge page size, e.g. "Hugepagesize:       2048 kB"
+    let meminfo = std::fs::read_to_string("/proc/meminfo").map_err(|err| {
+        SyncError::HugePagesUnavailable(OsError::new(
+            "read /proc/meminfo",
+            err.raw_os_error().unwrap_or(ENOENT),
+        ))
+    })?;
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("Hugepagesize:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<usize>().ok())
+        .map(|kb| kb * 1024)
+        .ok_or(SyncError::HugePagesUnavailable(OsError::new(
+            "read /proc/meminfo",
+            ENOENT,
+        )))
+}
+
+fn open_named_shared_memory(
+    name: &str,
+    oflag: c_int,
+    len: usize,
+) -> Result<*mut c_void, SyncError> {
+    let name = shm_name(name)?;
+    let fd = check_libc_err("shm_open", unsafe {
+        shm_open(name.as_ptr(), O_RDWR | oflag, 0o600)
+    })?;
+
+    let ret = resize_shared_memory(fd, oflag & O_CREAT != 0, len)
+        .and_then(|_| map_shared_memory(len, 0, fd, 0));
+
+    // mapping stays valid after descriptor is closed
+    check_libc_err("close", unsafe { close(fd) }).expect("cannot close() shared memory descriptor");
+
+    ret
+}
+
+fn resize_shared_memory(fd: c_int, create: bool, len: usize) -> Result<(), SyncError> {
+    let mut stat: stat = unsafe { std::mem::zeroed() };
+    check_libc_err("fstat", unsafe { fstat(fd, &mut stat) })?;
+
+    if stat.st_size == 0 && create {
+        check_libc_err("ftruncate", unsafe { ftruncate(fd, len as off_t) })?;
+    } else if (stat.st_size as usize) < len {
+        return Err(SyncError::InvalidInput(
+            "shared memory object is smaller than requested type",
+        ));
+    }
+    Ok(())
+}
+
+fn shm_name(name: &str) -> Result<CString, SyncError> {
+    CString::new(name).map_err(|_| SyncError::InvalidInput("name contains nul byte"))
+}
+
+fn map_shared_memory(
+    len: usize,
+    flags: c_int,
+    fd: c_int,
+    offset: off_t,
+) -> Result<*mut c_void, SyncError> {
+    // mmap() rejects empty mappings, and zero-sized objects have nothing to share anyway
+    if len == 0 {
+        return Ok(dangling(page_size()));
+    }
+    // such mapping can never succeed, and objects can't be that large anyway
+    if len > isize::MAX as usize {
+        return Err(SyncError::InvalidInput(
+            "shared memory size exceeds isize::MAX",
+        ));
+    }
+    // mmap() would fail with ENOMEM anyway, but only after kernel tried to find room for the mapping
+    if len as u64 > address_space_limit() {
+        return Err(SyncError::MmapFailed(
+            OsError::new("mmap", ENOMEM).with_bytes(len),
+        ));
+    }
     let addr = unsafe {
         mmap(
             null_mut(),
             len,
             PROT_READ | PROT_WRITE,
-            MAP_SHARED | MAP_ANONYMOUS,
-            -1,
-            0,
+            MAP_SHARED | flags,
+            fd,
+            offset,
         )
     };
     if addr == MAP_FAILED {
-        return Err(std::io::Error::last_os_error());
+        return Err(SyncError::MmapFailed(
+            OsError::new("mmap", last_errno()).with_bytes(len),
+        ));
     }
     Ok(addr)
 }
 
-fn free_shared_memory(addr: *mut c_void, len: usize) -> std::io::Result<()> {
+/// Returns soft limit of process address space size (`RLIMIT_AS`), or `u64::MAX` if it is unlimited or unknown.
+fn address_space_limit() -> u64 {
+    let mut limit = rlimit {
+        rlim_cur: RLIM_INFINITY,
+        rlim_max: RLIM_INFINITY,
+    };
+    if unsafe { getrlimit(RLIMIT_AS, &mut limit) } != 0 || limit.rlim_cur == RLIM_INFINITY {
+        return u64::MAX;
+    }
+    limit.rlim_cur
+}
+
+pub(crate) fn free_shared_memory(addr: *mut c_void, len: usize) -> Result<(), SyncError> {
+    // empty regions are never mapped, see map_shared_memory()
+    if len == 0 {
+        return Ok(());
+    }
     let ret = unsafe { munmap(addr, len) };
     if ret != 0 {
-        return Err(std::io::Error::last_os_error());
+        return Err(SyncError::MmapFailed(
+            OsError::new("munmap", last_errno()).with_bytes(len),
+        ));
     }
     Ok(())
 }
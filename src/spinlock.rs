@@ -0,0 +1,180 @@
+use libc::{pid_t, EBUSY, ENOSYS, PTHREAD_PROCESS_SHARED};
+#[cfg(not(target_os = "macos"))]
+use libc::{
+    pthread_spin_destroy, pthread_spin_init, pthread_spin_lock, pthread_spin_trylock,
+    pthread_spin_unlock, pthread_spinlock_t,
+};
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::{report_primitive_drop_error, OsError, SyncError},
+    shared_memory::SharedMemoryObject,
+    util::getpid,
+};
+
+/// Spinlock that can be shared between processes.
+///
+/// Waiting process busy-loops instead of sleeping in kernel, which is faster than [`SharedMutex`](crate::SharedMutex)
+/// for very short critical sections. **Spinlock must never be held across blocking operation** (I/O, sleeping,
+/// waiting on other primitives): if holding process blocks or is preempted, all waiting processes burn CPU until it
+/// runs again. Unlike threads of one process, processes holding and waiting for the lock may be scheduled
+/// independently, which makes this even more likely. Spinlock is **NOT** recursive, so it will deadlock on relock.
+///
+/// Dropping spinlock in creating process while it being locked will cause undefined behaviour.
+/// It is recommended to drop this spinlock in creating process only after no other process has access to it.
+///
+/// For more information see [`pthread_spin_init`](https://man7.org/linux/man-pages/man3/pthread_spin_init.3.html), [`pthread_spin_lock`](https://man7.org/linux/man-pages/man3/pthread_spin_lock.3.html) and [`SharedMemoryObject`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{SharedMemoryObject, SharedSpinlock};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut spinlock = SharedSpinlock::new()?;
+/// let mut counter = SharedMemoryObject::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// // keep critical section short and never block while holding spinlock
+/// spinlock.lock()?;
+/// unsafe { *counter.get_mut() += 1 };
+/// spinlock.unlock()?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedSpinlock {
+    spinlock: SharedMemoryObject<pthread_spinlock_t>,
+    owner_pid: pid_t,
+}
+
+impl SharedSpinlock {
+    /// Creates new [`SharedSpinlock`]
+    ///
+    /// # Errors
+    /// If platform doesn't support spinlocks (e.g. macOS), returns [`SyncError::ProcessSharedUnsupported`].
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        // mapping is zeroed, which is valid value to pass to pthread_spin_init()
+        let mut spinlock = SharedMemoryObject::<pthread_spinlock_t>::new_uninit()?;
+        match unsafe { pthread_spin_init(spinlock.get_mut().as_mut_ptr(), PTHREAD_PROCESS_SHARED) }
+        {
+            0 => {}
+            ENOSYS => {
+                return Err(SyncError::ProcessSharedUnsupported(OsError::new(
+                    "pthread_spin_init",
+                    ENOSYS,
+                )))
+            }
+            err => return Err(SyncError::from_errno("pthread_spin_init", err)),
+        }
+        let spinlock = unsafe { spinlock.assume_init() };
+
+        let owner_pid = getpid();
+        Ok(Self {
+            spinlock,
+            owner_pid,
+        })
+    }
+
+    /// Locks spinlock.
+    ///
+    /// This function will spin until spinlock is locked.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_spin_lock`](https://man7.org/linux/man-pages/man3/pthread_spin_lock.3.html).
+    pub fn lock(&mut self) -> Result<(), SyncError> {
+        match unsafe { pthread_spin_lock(self.spinlock.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_spin_lock", err)),
+        }
+    }
+
+    /// Tries to lock spinlock without spinning.
+    ///
+    /// Returns `Ok(true)` if spinlock was locked by this call and `Ok(false)` if it is already locked.
+    ///
+    /// # Errors
+    /// If pthread call fails with anything other than `EBUSY`, returns corresponding [`SyncError`]. For possible errors see [`pthread_spin_trylock`](https://man7.org/linux/man-pages/man3/pthread_spin_lock.3.html).
+    pub fn try_lock(&mut self) -> Result<bool, SyncError> {
+        match unsafe { pthread_spin_trylock(self.spinlock.raw_ptr()) } {
+            0 => Ok(true),
+            EBUSY => Ok(false),
+            err => Err(SyncError::from_errno("pthread_spin_trylock", err)),
+        }
+    }
+
+    /// Unlocks spinlock.
+    ///
+    /// This function must be called from the same process that called [`lock`](#method.lock) previously.
+    ///
+    /// # Errors
+    /// If pthread call fails, returns corresponding [`SyncError`]. For possible errors see [`pthread_spin_unlock`](https://man7.org/linux/man-pages/man3/pthread_spin_unlock.3.html).
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        match unsafe { pthread_spin_unlock(self.spinlock.raw_ptr()) } {
+            0 => Ok(()),
+            err => Err(SyncError::from_errno("pthread_spin_unlock", err)),
+        }
+    }
+}
+
+impl Drop for SharedSpinlock {
+    fn drop(&mut self) {
+        if getpid() == self.owner_pid {
+            match unsafe { pthread_spin_destroy(self.spinlock.raw_ptr()) } {
+                0 => {}
+                err => report_primitive_drop_error(
+                    "destroy",
+                    "spinlock",
+                    self.spinlock.as_ptr(),
+                    self.owner_pid,
+                    SyncError::from_errno("pthread_spin_destroy", err),
+                ),
+            }
+        }
+    }
+}
+
+impl sealed::Sealed for SharedSpinlock {}
+
+impl SharedPrimitive for SharedSpinlock {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.owner_pid = pid;
+    }
+}
+
+// macOS has no POSIX spinlocks. pthread_spin_init() fails with ENOSYS, so that other functions are never reached.
+#[cfg(target_os = "macos")]
+#[allow(non_camel_case_types)]
+type pthread_spinlock_t = libc::c_int;
+
+#[cfg(target_os = "macos")]
+unsafe fn pthread_spin_init(_lock: *mut pthread_spinlock_t, _pshared: libc::c_int) -> libc::c_int {
+    ENOSYS
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn pthread_spin_lock(_lock: *mut pthread_spinlock_t) -> libc::c_int {
+    ENOSYS
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn pthread_spin_trylock(_lock: *mut pthread_spinlock_t) -> libc::c_int {
+    ENOSYS
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn pthread_spin_unlock(_lock: *mut pthread_spinlock_t) -> libc::c_int {
+    ENOSYS
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn pthread_spin_destroy(_lock: *mut pthread_spinlock_t) -> libc::c_int {
+    ENOSYS
+}
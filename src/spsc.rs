@@ -0,0 +1,163 @@
+use std::{
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{error::SyncError, shared_memory::SharedMemoryObject};
+
+/// Lock-free single-producer single-consumer queue that can be shared between processes.
+///
+/// Elements are stored in ring buffer of fixed capacity. Producer advances tail index and consumer advances head index,
+/// both are atomics in shared memory, so [`push`](#method.push) and [`pop`](#method.pop) never block and make no
+/// system calls: they return immediately if queue is full or empty. Use [`SharedChannel`](crate::SharedChannel) if
+/// waiting is needed.
+///
+/// Elements are copied bitwise into shared memory, so **`T` must be self-contained**, see
+/// [`SharedMemoryObject::new`].
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::SharedSpscQueue;
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut queue = SharedSpscQueue::<u64>::new(16)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// // parent is the only producer and child is the only consumer
+/// if pid == 0 {
+///     let mut received = 0;
+///     while received < 100 {
+///         if let Some(value) = unsafe { queue.pop() } {
+///             assert_eq!(value, received);
+///             received += 1;
+///         }
+///     }
+/// } else {
+///     for value in 0..100 {
+///         while !unsafe { queue.push(value) } {}
+///     }
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedSpscQueue<T: Copy + Send + Sync> {
+    indices: SharedMemoryObject<Indices>,
+    buffer: SharedMemoryObject<[MaybeUninit<T>]>,
+    capacity: usize,
+}
+
+struct Indices {
+    /// Number of popped elements. Element `i` is stored in slot `i % capacity`.
+    head: CachePadded,
+    /// Number of pushed elements.
+    tail: CachePadded,
+}
+
+/// Keeps indices on separate cache lines, so that producer and consumer don't contend on the same one.
+#[repr(align(64))]
+struct CachePadded(AtomicUsize);
+
+impl<T: Copy + Send + Sync> SharedSpscQueue<T> {
+    /// Creates new empty [`SharedSpscQueue`] which holds up to `capacity` elements
+    ///
+    /// # Errors
+    /// If `capacity` is zero, returns [`SyncError::InvalidInput`].
+    /// If allocation fails returns corresponding [`SyncError`].
+    pub fn new(capacity: usize) -> Result<Self, SyncError> {
+        if capacity == 0 {
+            return Err(SyncError::InvalidInput("queue capacity must be positive"));
+        }
+
+        Ok(Self {
+            indices: SharedMemoryObject::new(Indices {
+                head: CachePadded(AtomicUsize::new(0)),
+                tail: CachePadded(AtomicUsize::new(0)),
+            })?,
+            buffer: SharedMemoryObject::new_slice(capacity, |_| MaybeUninit::uninit())?,
+            capacity,
+        })
+    }
+
+    /// Returns maximum number of elements in queue.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Appends `value` to the back of queue. Returns `false` if queue is full, in which case `value` is discarded.
+    ///
+    /// # Safety
+    /// At most one process (and one thread in it) may push to queue at a time. Concurrent pushes are data races.
+    pub unsafe fn push(&mut self, value: T) -> bool {
+        let indices = self.indices();
+        // only this process writes tail
+        let tail = indices.tail.0.load(Ordering::Relaxed);
+        // acquire pairs with release in pop(), so that slot is not overwritten before it is read
+        let head = indices.head.0.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == self.capacity {
+            return false;
+        }
+        self.slot(tail).write(MaybeUninit::new(value));
+        indices
+            .tail
+            .0
+            .store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Removes element from the front of queue. Returns `None` if queue is empty.
+    ///
+    /// # Safety
+    /// At most one process (and one thread in it) may pop from queue at a time. Concurrent pops are data races.
+    pub unsafe fn pop(&mut self) -> Option<T> {
+        let indices = self.indices();
+        // only this process writes head
+        let head = indices.head.0.load(Ordering::Relaxed);
+        // acquire pairs with release in push(), so that slot is read only after it is written
+        let tail = indices.tail.0.load(Ordering::Acquire);
+        if tail == head {
+            return None;
+        }
+        let value = self.slot(head).read().assume_init();
+        indices
+            .head
+            .0
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns number of elements in queue.
+    ///
+    /// **Result is only a snapshot**, elements may be pushed or popped by another process right after this function
+    /// returns.
+    pub fn len(&self) -> usize {
+        let indices = self.indices();
+        let head = indices.head.0.load(Ordering::Acquire);
+        let tail = indices.tail.0.load(Ordering::Acquire);
+        // head is loaded first, so it never overtakes tail, but queue may be refilled in between
+        tail.wrapping_sub(head).min(self.capacity)
+    }
+
+    /// Returns `true` if queue is empty.
+    ///
+    /// **Result is only a snapshot**, see [`len`](#method.len).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn indices(&self) -> &Indices {
+        // atomics are safe to access concurrently
+        unsafe { self.indices.get() }
+    }
+
+    fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+        unsafe { (self.buffer.raw_ptr() as *mut MaybeUninit<T>).add(index % self.capacity) }
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    shared_memory::SharedMemoryObject,
+    SharedCondvar, SharedMutex,
+};
+
+/// Fair lock that can be shared between processes.
+///
+/// Default pthread mutexes give no ordering guarantee: process that just unlocked [`SharedMutex`] may reacquire it
+/// again and again while others wait. Ticket lock grants lock strictly in order of [`lock`](#method.lock) calls
+/// (first come, first served), so no waiting process is starved.
+///
+/// Each process takes ticket from shared counter and waits until its ticket is served. Uncontended lock and unlock only
+/// touch atomics, waiting processes sleep on [`SharedCondvar`]. All waiting processes are woken up on every unlock to
+/// check whether their turn came, so this lock is slower than [`SharedMutex`] under heavy contention. Ticket lock is
+/// **NOT** recursive, so it will deadlock on relock.
+///
+/// Dropping lock in creating process while it being locked or waited will cause undefined behaviour.
+/// It is recommended to drop this lock in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{SharedMemoryObject, SharedTicketLock};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut lock = SharedTicketLock::new()?;
+/// let mut counter = SharedMemoryObject::new(0)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// // lock is granted in order of lock() calls
+/// lock.lock()?;
+/// unsafe { *counter.get_mut() += 1 };
+/// lock.unlock()?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedTicketLock {
+    mutex: SharedMutex,
+    condvar: SharedCondvar,
+    tickets: SharedMemoryObject<Tickets>,
+}
+
+struct Tickets {
+    /// Ticket which will be taken by next process calling lock.
+    next: AtomicU64,
+    /// Ticket of process which holds the lock or is next to acquire it.
+    serving: AtomicU64,
+}
+
+impl SharedTicketLock {
+    /// Creates new [`SharedTicketLock`]
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Ok(Self {
+            mutex: SharedMutex::new()?,
+            condvar: SharedCondvar::new()?,
+            tickets: SharedMemoryObject::new(Tickets {
+                next: AtomicU64::new(0),
+                serving: AtomicU64::new(0),
+            })?,
+        })
+    }
+
+    /// Locks ticket lock.
+    ///
+    /// This function will block until all processes which called `lock` earlier have unlocked it.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`]. Ticket is taken before waiting, so if waiting
+    /// fails, lock can't be used by any process anymore.
+    pub fn lock(&mut self) -> Result<(), SyncError> {
+        // SeqCst pairs with unlock(): either this process sees its ticket served, or unlock sees it waiting
+        let ticket = self.tickets().next.fetch_add(1, Ordering::SeqCst);
+        if self.tickets().serving.load(Ordering::SeqCst) == ticket {
+            return Ok(());
+        }
+
+        self.mutex.lock()?;
+        // atomics are safe to access concurrently
+        let tickets = unsafe { self.tickets.get() };
+        // serving is advanced before locking mutex in unlock(), so notification can't be missed
+        let ret = self.condvar.wait_while(&mut self.mutex, || {
+            tickets.serving.load(Ordering::Acquire) != ticket
+        });
+        self.mutex.unlock()?;
+        ret.map(|_| ())
+    }
+
+    /// Tries to lock ticket lock without blocking.
+    ///
+    /// Returns `Ok(true)` if lock was acquired and `Ok(false)` if it is held or waited by another process.
+    ///
+    /// # Errors
+    /// Never fails currently, [`SyncError`] is returned for consistency with other primitives.
+    pub fn try_lock(&mut self) -> Result<bool, SyncError> {
+        let tickets = self.tickets();
+        let serving = tickets.serving.load(Ordering::Acquire);
+        // take ticket only if it would be served immediately
+        Ok(tickets
+            .next
+            .compare_exchange(serving, serving + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok())
+    }
+
+    /// Unlocks ticket lock, passing it to the process which called [`lock`](#method.lock) next.
+    ///
+    /// This function must be called from the same process that locked it previously.
+    ///
+    /// # Errors
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        let tickets = self.tickets();
+        let serving = tickets.serving.fetch_add(1, Ordering::SeqCst) + 1;
+        if tickets.next.load(Ordering::SeqCst) == serving {
+            // nobody is waiting
+            return Ok(());
+        }
+
+        self.mutex.lock()?;
+        let ret = self.condvar.notify_all();
+        self.mutex.unlock()?;
+        ret
+    }
+
+    fn tickets(&self) -> &Tickets {
+        // atomics are safe to access concurrently
+        unsafe { self.tickets.get() }
+    }
+}
+
+impl sealed::Sealed for SharedTicketLock {}
+
+impl SharedPrimitive for SharedTicketLock {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.mutex.set_owner_pid(pid);
+        self.condvar.set_owner_pid(pid);
+    }
+}
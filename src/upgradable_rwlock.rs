@@ -0,0 +1,269 @@
+use libc::pid_t;
+
+use crate::{
+    arc::{sealed, SharedPrimitive},
+    error::SyncError,
+    SharedCondvar, SharedMutex,
+};
+
+/// Reader-writer lock with upgradable reads that can be shared between processes.
+///
+/// Besides ordinary read and write locks, process may take upgradable read lock with
+/// [`upgradable_read`](#method.upgradable_read). It coexists with ordinary readers, but excludes writers and other
+/// upgradable readers, so it can later be turned into write lock with [`upgrade`](#method.upgrade) without releasing
+/// it in between: data seen while holding upgradable read lock is guaranteed to be unchanged when upgrade completes.
+/// Write lock can be turned back into read lock with [`downgrade`](#method.downgrade).
+///
+/// `pthread` rwlocks can't be upgraded atomically, so this lock is built from [`SharedMutex`] and [`SharedCondvar`]
+/// with lock state in shared memory. Use [`SharedRwLock`](crate::SharedRwLock) if upgrades are not needed.
+///
+/// # Deadlocks
+/// If two processes holding read locks both tried to upgrade, each would wait for the other to release its read lock
+/// forever. This is prevented by allowing only one upgradable reader at a time: second process calling
+/// [`upgradable_read`](#method.upgradable_read) blocks until the first one releases or downgrades its lock. Ordinary
+/// read locks can't be upgraded at all. While upgrade is pending new readers are blocked, so that upgrading process
+/// is not starved by them.
+///
+/// Lock state is tracked per handle: each process (or thread owning handle) holds at most one lock at a time, and
+/// [`unlock`](#method.unlock) releases whichever lock it holds.
+///
+/// Dropping lock in creating process while it being locked or waited will cause undefined behaviour.
+/// It is recommended to drop this lock in creating process only after no other process has access to it.
+///
+/// # Example
+/// ```rust
+/// # use std::error::Error;
+/// #
+/// # use libc::fork;
+/// #
+/// # use process_sync::{SharedMemoryObject, SharedUpgradableRwLock};
+/// #
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// #
+/// let mut rwlock = SharedUpgradableRwLock::new()?;
+/// let mut cache = SharedMemoryObject::new(0u64)?;
+///
+/// let pid = unsafe { fork() };
+/// assert!(pid >= 0);
+///
+/// // both processes refresh stale cache, but only one of them writes it
+/// rwlock.upgradable_read()?;
+/// if unsafe { *cache.get() } == 0 {
+///     rwlock.upgrade()?;
+///     unsafe { *cache.get_mut() = 42 };
+///     rwlock.downgrade()?;
+/// }
+/// assert_eq!(unsafe { *cache.get() }, 42);
+/// rwlock.unlock()?;
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+pub struct SharedUpgradableRwLock {
+    state: SharedMutex<RwState>,
+    changed: SharedCondvar,
+    /// Lock held through this handle.
+    held: Option<Held>,
+}
+
+struct RwState {
+    /// Number of ordinary readers.
+    readers: u32,
+    /// Whether upgradable read lock is held.
+    upgradable: bool,
+    /// Whether upgradable reader waits for ordinary readers to leave.
+    upgrading: bool,
+    /// Whether write lock is held.
+    writer: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Held {
+    Read,
+    UpgradableRead,
+    Write,
+}
+
+impl SharedUpgradableRwLock {
+    /// Creates new [`SharedUpgradableRwLock`]
+    ///
+    /// # Errors
+    /// If allocation or initialization fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        Ok(Self {
+            state: SharedMutex::with_data(RwState {
+                readers: 0,
+                upgradable: false,
+                upgrading: false,
+                writer: false,
+            })?,
+            changed: SharedCondvar::new()?,
+            held: None,
+        })
+    }
+
+    /// Locks for reading.
+    ///
+    /// This function will block until no process holds write lock or upgrades its upgradable read lock.
+    ///
+    /// # Errors
+    /// If lock is already held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn read(&mut self) -> Result<(), SyncError> {
+        self.acquire(
+            Held::Read,
+            |state| state.writer || state.upgrading,
+            |state| state.readers += 1,
+        )
+    }
+
+    /// Locks for upgradable reading.
+    ///
+    /// This function will block until no process holds write lock or another upgradable read lock. Ordinary readers
+    /// don't block it.
+    ///
+    /// # Errors
+    /// If lock is already held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn upgradable_read(&mut self) -> Result<(), SyncError> {
+        self.acquire(
+            Held::UpgradableRead,
+            |state| state.writer || state.upgradable,
+            |state| state.upgradable = true,
+        )
+    }
+
+    /// Locks for writing.
+    ///
+    /// This function will block until no other process holds any lock.
+    ///
+    /// # Errors
+    /// If lock is already held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn write(&mut self) -> Result<(), SyncError> {
+        self.acquire(
+            Held::Write,
+            |state| state.writer || state.upgradable || state.readers > 0,
+            |state| state.writer = true,
+        )
+    }
+
+    /// Turns upgradable read lock held through this handle into write lock.
+    ///
+    /// This function will block until all ordinary readers release their locks, new readers are blocked meanwhile.
+    /// Since no writer can acquire lock in between, data is not modified by other processes during upgrade.
+    ///
+    /// # Errors
+    /// If upgradable read lock is not held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn upgrade(&mut self) -> Result<(), SyncError> {
+        if self.held != Some(Held::UpgradableRead) {
+            return Err(SyncError::InvalidInput(
+                "upgradable read lock is not held by this handle",
+            ));
+        }
+
+        let mut state = self.state.guard()?;
+        state.upgrading = true;
+        let upgraded = self
+            .changed
+            .wait_while_guard(state, |state| state.readers > 0)
+            .map(|mut state| {
+                state.upgrading = false;
+                state.upgradable = false;
+                state.writer = true;
+            });
+        if let Err(err) = upgraded {
+            // otherwise new readers stay blocked forever; original error is reported even if this fails too
+            let _ = self.release(|state| state.upgrading = false);
+            return Err(err);
+        }
+
+        self.held = Some(Held::Write);
+        Ok(())
+    }
+
+    /// Turns write or upgradable read lock held through this handle into ordinary read lock.
+    ///
+    /// Lock is never released in between, so no writer can acquire it before this process releases read lock.
+    ///
+    /// # Errors
+    /// If write or upgradable read lock is not held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn downgrade(&mut self) -> Result<(), SyncError> {
+        let held = match self.held {
+            Some(held @ (Held::Write | Held::UpgradableRead)) => held,
+            _ => {
+                return Err(SyncError::InvalidInput(
+                    "write lock is not held by this handle",
+                ))
+            }
+        };
+
+        self.release(|state| {
+            match held {
+                Held::Write => state.writer = false,
+                _ => state.upgradable = false,
+            }
+            state.readers += 1;
+        })?;
+        self.held = Some(Held::Read);
+        Ok(())
+    }
+
+    /// Releases lock held through this handle.
+    ///
+    /// # Errors
+    /// If no lock is held through this handle, returns [`SyncError::InvalidInput`].
+    /// If any pthread call fails, returns corresponding [`SyncError`].
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        let held = self.held.ok_or(SyncError::InvalidInput(
+            "rwlock is not locked by this handle",
+        ))?;
+
+        self.release(|state| match held {
+            Held::Read => state.readers -= 1,
+            Held::UpgradableRead => state.upgradable = false,
+            Held::Write => state.writer = false,
+        })?;
+        self.held = None;
+        Ok(())
+    }
+
+    /// Waits while `blocked` returns `true`, then records lock with `update`.
+    fn acquire(
+        &mut self,
+        held: Held,
+        blocked: impl FnMut(&mut RwState) -> bool,
+        update: impl FnOnce(&mut RwState),
+    ) -> Result<(), SyncError> {
+        if self.held.is_some() {
+            return Err(SyncError::InvalidInput(
+                "rwlock is already locked by this handle",
+            ));
+        }
+
+        let state = self.state.guard()?;
+        let mut state = self.changed.wait_while_guard(state, blocked)?;
+        update(&mut state);
+        drop(state);
+
+        self.held = Some(held);
+        Ok(())
+    }
+
+    /// Updates state and wakes up waiting processes, since any of them may proceed now.
+    fn release(&mut self, update: impl FnOnce(&mut RwState)) -> Result<(), SyncError> {
+        self.state.with_locked(update)?;
+        self.changed.notify_all()
+    }
+}
+
+impl sealed::Sealed for SharedUpgradableRwLock {}
+
+impl SharedPrimitive for SharedUpgradableRwLock {
+    fn set_owner_pid(&mut self, pid: pid_t) {
+        self.state.set_owner_pid(pid);
+        self.changed.set_owner_pid(pid);
+    }
+}
@@ -1,13 +1,62 @@
-use libc::pid_t;
+use std::time::Duration;
 
+use crate::error::{OsError, SyncError};
+
+use libc::{c_int, c_long, clock_gettime, clockid_t, pid_t, time_t, timespec};
+
+const NANOS_PER_SEC: c_long = 1_000_000_000;
+
+/// Checks return value of libc call named `operation`, which sets `errno` on failure.
 #[doc(hidden)]
-pub fn check_libc_err<T: Default + Ord>(ret: T) -> std::io::Result<T> {
+pub fn check_libc_err<T: Default + Ord>(operation: &'static str, ret: T) -> Result<T, SyncError> {
     if ret < T::default() {
-        return Err(std::io::Error::last_os_error());
+        return Err(SyncError::last_os_error(operation));
+    }
+    Ok(ret)
+}
+
+/// Checks return value of pthread call named `operation`, which returns error number instead of setting `errno`.
+pub fn check_pthread_err(operation: &'static str, ret: c_int) -> Result<(), SyncError> {
+    match ret {
+        0 => Ok(()),
+        err => Err(SyncError::from_errno(operation, err)),
+    }
+}
+
+/// Checks return value of pthread call named `operation`, which makes primitive process-shared.
+///
+/// `PTHREAD_PROCESS_SHARED` is always valid value, so failure means that platform doesn't support it.
+pub fn check_pshared_err(operation: &'static str, ret: c_int) -> Result<(), SyncError> {
+    match ret {
+        0 => Ok(()),
+        err => Err(SyncError::ProcessSharedUnsupported(OsError::new(
+            operation, err,
+        ))),
     }
-    return Ok(ret);
+}
+
+pub fn last_errno() -> c_int {
+    std::io::Error::last_os_error()
+        .raw_os_error()
+        .expect("last_os_error() must have errno")
 }
 
 pub fn getpid() -> pid_t {
-    check_libc_err(unsafe { libc::getpid() }).expect("getpid() failed")
+    check_libc_err("getpid", unsafe { libc::getpid() }).expect("getpid() failed")
+}
+
+/// Returns absolute time `timeout` after current time of `clock`, saturating on overflow.
+pub fn deadline_after(clock: clockid_t, timeout: Duration) -> Result<timespec, SyncError> {
+    let mut now: timespec = unsafe { std::mem::zeroed() };
+    check_libc_err("clock_gettime", unsafe { clock_gettime(clock, &mut now) })?;
+
+    let secs = time_t::try_from(timeout.as_secs()).unwrap_or(time_t::MAX);
+    let mut tv_sec = now.tv_sec.saturating_add(secs);
+    let mut tv_nsec = now.tv_nsec + timeout.subsec_nanos() as c_long;
+    if tv_nsec >= NANOS_PER_SEC {
+        tv_nsec -= NANOS_PER_SEC;
+        tv_sec = tv_sec.saturating_add(1);
+    }
+
+    Ok(timespec { tv_sec, tv_nsec })
 }
@@ -0,0 +1,7 @@
+//! Windows backend, implemented on top of kernel objects.
+//!
+//! Only [`SharedMutex`] is available for now.
+
+mod mutex;
+
+pub use mutex::{LockStatus, SharedMutex};
@@ -0,0 +1,175 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use windows_sys::Win32::{
+    Foundation::{
+        CloseHandle, GetLastError, ERROR_NOT_OWNER, HANDLE, WAIT_ABANDONED, WAIT_OBJECT_0,
+        WAIT_TIMEOUT,
+    },
+    System::Threading::{
+        CreateMutexW, OpenMutexW, ReleaseMutex, WaitForSingleObject, INFINITE, MUTEX_ALL_ACCESS,
+    },
+};
+
+use crate::error::{report_drop_error, OsError, SyncError};
+
+/// Mutex that can be shared between processes, backed by named kernel mutex object.
+///
+/// Unlike Unix version, Windows has no `fork()`, so other processes attach to the mutex by its [`name`](#method.name)
+/// with [`open_named`](#method.open_named). Mutex object is destroyed by system when last handle to it is closed,
+/// which happens when [`SharedMutex`] is dropped.
+///
+/// Kernel mutexes are owned by threads and are recursive: thread holding the mutex can lock it again, and must unlock
+/// it the same number of times.
+///
+/// Failed calls are reported as [`SyncError::Os`] carrying name of the call and `GetLastError()` code.
+///
+/// For more information see [`CreateMutexW`](https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-createmutexw)
+/// and [`WaitForSingleObject`](https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitforsingleobject).
+pub struct SharedMutex {
+    handle: HANDLE,
+    name: String,
+}
+
+impl SharedMutex {
+    /// Creates new [`SharedMutex`] with unique name
+    ///
+    /// # Errors
+    /// If call fails returns corresponding [`SyncError`].
+    pub fn new() -> Result<Self, SyncError> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!(
+            "Local\\process-sync-mutex-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let wide_name = wide_name(&name)?;
+
+        let handle = unsafe { CreateMutexW(std::ptr::null(), 0, wide_name.as_ptr()) };
+        if handle.is_null() {
+            return Err(last_error("CreateMutexW"));
+        }
+        Ok(Self { handle, name })
+    }
+
+    /// Opens existing mutex `name`, usually created by another process with [`new`](#method.new).
+    ///
+    /// # Errors
+    /// If mutex doesn't exist, or call fails, returns corresponding [`SyncError`].
+    pub fn open_named(name: &str) -> Result<Self, SyncError> {
+        let wide_name = wide_name(name)?;
+
+        let handle = unsafe { OpenMutexW(MUTEX_ALL_ACCESS, 0, wide_name.as_ptr()) };
+        if handle.is_null() {
+            return Err(last_error("OpenMutexW"));
+        }
+        Ok(Self {
+            handle,
+            name: name.to_owned(),
+        })
+    }
+
+    /// Returns name under which this mutex can be opened with [`open_named`](#method.open_named)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Locks mutex
+    ///
+    /// This function will block until mutex is unlocked by its current owner.
+    ///
+    /// Returns [`LockStatus::AcquiredInconsistent`] if previous owner thread terminated while holding the lock.
+    ///
+    /// # Errors
+    /// If call fails returns corresponding [`SyncError`].
+    pub fn lock(&mut self) -> Result<LockStatus, SyncError> {
+        Ok(self.wait(INFINITE)?.expect("infinite wait cannot time out"))
+    }
+
+    /// Tries to lock mutex without blocking
+    ///
+    /// Returns `Ok(true)` if mutex was locked and `Ok(false)` if it is held by another thread.
+    ///
+    /// # Errors
+    /// If call fails returns corresponding [`SyncError`].
+    pub fn try_lock(&mut self) -> Result<bool, SyncError> {
+        Ok(self.wait(0)?.is_some())
+    }
+
+    /// Tries to lock mutex, giving up after `timeout` elapses.
+    ///
+    /// Returns `Ok(true)` if mutex was locked and `Ok(false)` if timeout expired.
+    ///
+    /// # Errors
+    /// If call fails returns corresponding [`SyncError`].
+    pub fn lock_timeout(&mut self, timeout: Duration) -> Result<bool, SyncError> {
+        // INFINITE is u32::MAX, so longest finite timeout is one millisecond less
+        let millis = u32::try_from(timeout.as_millis()).unwrap_or(INFINITE - 1);
+        Ok(self.wait(millis.min(INFINITE - 1))?.is_some())
+    }
+
+    /// Unlocks mutex
+    ///
+    /// # Errors
+    /// If mutex is not held by calling thread, returns [`SyncError::NotOwner`]. If call fails otherwise, returns
+    /// corresponding [`SyncError`].
+    pub fn unlock(&mut self) -> Result<(), SyncError> {
+        if unsafe { ReleaseMutex(self.handle) } == 0 {
+            return match last_error("ReleaseMutex") {
+                SyncError::Os(err) if err.errno() == ERROR_NOT_OWNER as i32 => {
+                    Err(SyncError::NotOwner(err))
+                }
+                err => Err(err),
+            };
+        }
+        Ok(())
+    }
+
+    fn wait(&mut self, millis: u32) -> Result<Option<LockStatus>, SyncError> {
+        match unsafe { WaitForSingleObject(self.handle, millis) } {
+            WAIT_OBJECT_0 => Ok(Some(LockStatus::Acquired)),
+            WAIT_ABANDONED => Ok(Some(LockStatus::AcquiredInconsistent)),
+            WAIT_TIMEOUT => Ok(None),
+            _ => Err(last_error("WaitForSingleObject")),
+        }
+    }
+}
+
+// Handle of kernel mutex object can be used from any thread. Locking methods take `&mut self`, and ownership of
+// locked mutex belongs to thread, exactly like for `pthread` mutexes on Unix.
+unsafe impl Send for SharedMutex {}
+unsafe impl Sync for SharedMutex {}
+
+impl Drop for SharedMutex {
+    fn drop(&mut self) {
+        // kernel object is destroyed when last handle to it is closed
+        if unsafe { CloseHandle(self.handle) } == 0 {
+            report_drop_error("cannot close mutex handle", last_error("CloseHandle"));
+        }
+    }
+}
+
+/// Result of successfully locking [`SharedMutex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockStatus {
+    /// Mutex was locked normally.
+    Acquired,
+    /// Mutex was locked, but its previous owner thread terminated while holding it (`WAIT_ABANDONED`).
+    ///
+    /// Data protected by mutex may be in inconsistent state.
+    AcquiredInconsistent,
+}
+
+fn wide_name(name: &str) -> Result<Vec<u16>, SyncError> {
+    if name.contains('\0') {
+        return Err(SyncError::InvalidInput("name contains nul byte"));
+    }
+    Ok(name.encode_utf16().chain(Some(0)).collect())
+}
+
+fn last_error(operation: &'static str) -> SyncError {
+    SyncError::Os(OsError::new(operation, unsafe { GetLastError() } as i32))
+}
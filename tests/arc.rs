@@ -0,0 +1,67 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedArc, SharedCondvar, SharedMutex};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn parent_drops_first() {
+    let mut test_output = TestOutput::new(&[
+        "parent ref_count 2",
+        "parent dropped",
+        "child ref_count 1",
+        "child locked",
+    ]);
+
+    let mut mutex = SharedArc::new(SharedMutex::new().expect("cannot create SharedMutex"))
+        .expect("cannot create SharedArc");
+    mutex.attach();
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.adopt();
+        sleep(40);
+        test_output.write_line(format!("child ref_count {}", mutex.ref_count()));
+        mutex.lock().expect("lock() failed");
+        test_output.write_line("child locked");
+        mutex.unlock().expect("unlock() failed");
+        // last reference, destroys mutex
+        drop(mutex);
+        std::process::exit(0);
+    }
+
+    // parent
+    test_output.write_line(format!("parent ref_count {}", mutex.ref_count()));
+    drop(mutex);
+    test_output.write_line("parent dropped");
+    wait_child(pid);
+}
+
+fn child_drops_first() {
+    let mut test_output = TestOutput::new(&["child dropped", "parent ref_count 1"]);
+
+    let condvar = SharedArc::new(SharedCondvar::new().expect("cannot create SharedCondvar"))
+        .expect("cannot create SharedArc");
+    condvar.attach();
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let mut condvar = condvar;
+        condvar.adopt();
+        drop(condvar);
+        test_output.write_line("child dropped");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    test_output.write_line(format!("parent ref_count {}", condvar.ref_count()));
+    condvar.notify_all().expect("notify_all() failed");
+}
+
+fn main() {
+    parent_drops_first();
+    child_drops_first();
+}
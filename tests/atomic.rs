@@ -0,0 +1,84 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use libc::fork;
+use process_sync::{
+    fence, private::check_libc_err, SharedAtomicI32, SharedAtomicU32, SharedAtomicU64,
+    SharedMemoryObject,
+};
+
+use common::wait_child;
+
+fn fetch_add() {
+    let counter = SharedAtomicU64::new(0).expect("cannot create SharedAtomicU64");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    for _ in 0..1000 {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    if pid == 0 {
+        // child
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(counter.load(Ordering::Relaxed), 2000);
+}
+
+fn compare_exchange() {
+    let value = SharedAtomicI32::new(-1).expect("cannot create SharedAtomicI32");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        assert_eq!(
+            value.compare_exchange(-1, 1, Ordering::AcqRel, Ordering::Acquire),
+            Ok(-1)
+        );
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(
+        value.compare_exchange(-1, 2, Ordering::AcqRel, Ordering::Acquire),
+        Err(1)
+    );
+    value.store(3, Ordering::Release);
+    assert_eq!(value.swap(4, Ordering::AcqRel), 3);
+    assert_eq!(value.fetch_sub(5, Ordering::AcqRel), 4);
+    assert_eq!(value.load(Ordering::Acquire), -1);
+}
+
+fn fence_publish() {
+    let mut data = SharedMemoryObject::new([0u64; 64]).expect("cannot create SharedMemoryObject");
+    let ready = SharedAtomicU32::new(0).expect("cannot create SharedAtomicU32");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for (i, value) in unsafe { data.get_mut() }.iter_mut().enumerate() {
+            *value = i as u64;
+        }
+        fence(Ordering::Release);
+        ready.store(1, Ordering::Relaxed);
+        std::process::exit(0);
+    }
+
+    // parent
+    while ready.load(Ordering::Relaxed) == 0 {
+        std::hint::spin_loop();
+    }
+    fence(Ordering::Acquire);
+    let data = unsafe { data.get() };
+    assert!(data.iter().enumerate().all(|(i, &value)| value == i as u64));
+    wait_child(pid);
+}
+
+fn main() {
+    fetch_add();
+    compare_exchange();
+    fence_publish();
+}
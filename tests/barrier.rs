@@ -0,0 +1,91 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedBarrier, SharedMemoryObject};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn wait() {
+    let mut test_output = TestOutput::new(&[
+        "child0 wait()",
+        "child1 wait()",
+        "parent wait()",
+        "released",
+        "released",
+        "released",
+        "leaders 1",
+    ]);
+
+    let mut barrier = SharedBarrier::new(3).expect("cannot create SharedBarrier");
+    let leaders =
+        SharedMemoryObject::new(AtomicU32::new(0)).expect("cannot create SharedMemoryObject");
+
+    let mut pids = Vec::new();
+    for i in 0..2 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            sleep(20 * i);
+            test_output.write_line(format!("child{} wait()", i));
+            if barrier.wait().expect("wait() failed").is_leader() {
+                unsafe { leaders.get() }.fetch_add(1, Ordering::SeqCst);
+            }
+            test_output.write_line("released");
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(60);
+    test_output.write_line("parent wait()");
+    if barrier.wait().expect("wait() failed").is_leader() {
+        unsafe { leaders.get() }.fetch_add(1, Ordering::SeqCst);
+    }
+    test_output.write_line("released");
+    for pid in pids {
+        wait_child(pid);
+    }
+    test_output.write_line(format!(
+        "leaders {}",
+        unsafe { leaders.get() }.load(Ordering::SeqCst)
+    ));
+}
+
+fn wait_timeout() {
+    assert!(SharedBarrier::new(0).is_err());
+
+    let mut barrier = SharedBarrier::new(2).expect("cannot create SharedBarrier");
+
+    // missing participant: parent gives up and withdraws
+    assert!(!barrier
+        .wait_timeout(Duration::from_millis(20))
+        .expect("wait_timeout() failed"));
+    assert_eq!(barrier.arrived().expect("arrived() failed"), 0);
+
+    // barrier is still usable
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let released = barrier
+            .wait_timeout(Duration::from_secs(5))
+            .expect("wait_timeout() failed");
+        assert!(released);
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(40);
+    assert_eq!(barrier.arrived().expect("arrived() failed"), 1);
+    assert!(barrier.wait().expect("wait() failed").is_leader());
+    wait_child(pid);
+    assert_eq!(barrier.arrived().expect("arrived() failed"), 0);
+}
+
+fn main() {
+    wait();
+    wait_timeout();
+}
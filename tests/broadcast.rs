@@ -0,0 +1,101 @@
+mod common;
+
+use libc::{fork, pid_t, waitpid, WEXITSTATUS, WIFEXITED};
+use process_sync::{private::check_libc_err, SharedBarrier, SharedBroadcast, SyncError};
+
+use common::wait_child;
+
+fn wait_child_success(pid: pid_t) {
+    let mut status = 0;
+    let ret = unsafe { waitpid(pid, &mut status, 0) };
+    assert_eq!(ret, pid, "waitpid() failed");
+    assert!(
+        WIFEXITED(status) && WEXITSTATUS(status) == 0,
+        "child failed"
+    );
+}
+
+fn every_consumer() {
+    let mut broadcast = SharedBroadcast::new(4, 8).expect("cannot create SharedBroadcast");
+    assert_eq!(broadcast.capacity(), 4);
+    assert_eq!(broadcast.message_size(), 8);
+    assert!(matches!(
+        SharedBroadcast::new(0, 8),
+        Err(SyncError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        broadcast.send(&[0; 9]),
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    let mut pids = Vec::new();
+    for _ in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            let mut buf = [0; 8];
+            for i in 0..100u64 {
+                let len = loop {
+                    match broadcast.recv(&mut buf) {
+                        Ok(len) => break len,
+                        // producer is faster, some messages are skipped
+                        Err(SyncError::Lagged(_)) => continue,
+                        Err(err) => panic!("recv() failed: {}", err),
+                    }
+                };
+                assert_eq!(len, 8);
+                if u64::from_ne_bytes(buf) == 99 {
+                    break;
+                }
+                assert!(i < 99);
+            }
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    for i in 0..100u64 {
+        broadcast.send(&i.to_ne_bytes()).expect("send() failed");
+    }
+    for pid in pids {
+        wait_child_success(pid);
+    }
+}
+
+fn lagged() {
+    let mut broadcast = SharedBroadcast::new(4, 8).expect("cannot create SharedBroadcast");
+    let mut barrier = SharedBarrier::new(2).expect("cannot create SharedBarrier");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for i in 0..10u8 {
+            broadcast.send(&[i]).expect("send() failed");
+        }
+        barrier.wait().expect("wait() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    barrier.wait().expect("wait() failed");
+    assert_eq!(broadcast.pending(), Ok(10));
+    let mut buf = [0; 8];
+    assert_eq!(broadcast.recv(&mut buf), Err(SyncError::Lagged(6)));
+    assert_eq!(broadcast.pending(), Ok(4));
+    assert!(matches!(
+        broadcast.recv(&mut [0; 0]),
+        Err(SyncError::InvalidInput(_))
+    ));
+    for i in 6..10u8 {
+        assert_eq!(broadcast.recv(&mut buf), Ok(1));
+        assert_eq!(buf[0], i);
+    }
+    assert_eq!(broadcast.pending(), Ok(0));
+    wait_child(pid);
+}
+
+fn main() {
+    every_consumer();
+    lagged();
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedChannel, SyncError};
+
+use common::{sleep, wait_child};
+
+fn recv_exact(channel: &mut SharedChannel, buf: &mut [u8]) {
+    let mut received = 0;
+    while received < buf.len() {
+        received += channel.recv(&mut buf[received..]).expect("recv() failed");
+    }
+}
+
+fn wraparound() {
+    let mut channel = SharedChannel::new(7).expect("cannot create SharedChannel");
+    assert_eq!(channel.capacity(), 7);
+    assert!(matches!(
+        SharedChannel::new(0),
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for chunk in data.chunks(5) {
+            channel.send(chunk).expect("send() failed");
+        }
+        // longer than capacity
+        channel.send(&data).expect("send() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    let mut received = vec![0; data.len()];
+    recv_exact(&mut channel, &mut received);
+    assert_eq!(received, data);
+    recv_exact(&mut channel, &mut received);
+    assert_eq!(received, data);
+    assert_eq!(channel.recv(&mut []), Ok(0));
+    wait_child(pid);
+}
+
+fn multiple_producers() {
+    let mut channel = SharedChannel::new(16).expect("cannot create SharedChannel");
+
+    let mut pids = Vec::new();
+    for producer in 0..3u8 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            for _ in 0..100 {
+                channel.send(&[producer; 8]).expect("send() failed");
+            }
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    let mut counts = [0; 3];
+    for _ in 0..300 {
+        // messages not longer than capacity never interleave
+        let mut message = [0; 8];
+        recv_exact(&mut channel, &mut message);
+        assert!(message.iter().all(|&byte| byte == message[0]));
+        counts[message[0] as usize] += 1;
+    }
+    assert_eq!(counts, [100; 3]);
+    for pid in pids {
+        wait_child(pid);
+    }
+}
+
+fn recv_timeout() {
+    let mut channel = SharedChannel::new(16).expect("cannot create SharedChannel");
+    let mut buf = [0; 5];
+
+    let start = Instant::now();
+    let received = channel
+        .recv_timeout(&mut buf, Duration::from_millis(20))
+        .expect("recv_timeout() failed");
+    assert_eq!(received, None);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        sleep(20);
+        channel.send(b"hello").expect("send() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    let received = channel
+        .recv_timeout(&mut buf, Duration::from_secs(5))
+        .expect("recv_timeout() failed");
+    assert_eq!(received, Some(5));
+    assert_eq!(&buf, b"hello");
+    wait_child(pid);
+}
+
+fn main() {
+    wraparound();
+    multiple_producers();
+    recv_timeout();
+}
@@ -1,14 +1,25 @@
 use std::{fs::File, io::Write, time::Duration};
 
+use libc::{pid_t, waitpid};
+
+#[allow(dead_code)]
 pub fn sleep(ms: u64) {
     std::thread::sleep(Duration::from_millis(ms));
 }
 
+#[allow(dead_code)]
+pub fn wait_child(pid: pid_t) {
+    let ret = unsafe { waitpid(pid, std::ptr::null_mut(), 0) };
+    assert_eq!(ret, pid, "waitpid() failed");
+}
+
+#[allow(dead_code)]
 pub struct TestOutput {
     expected: String,
     file: File,
 }
 
+#[allow(dead_code)]
 impl TestOutput {
     pub fn new(expected_lines: &[&str]) -> Self {
         let expected = expected_lines.join("\n") + "\n";
@@ -1,10 +1,20 @@
 mod common;
 
-use libc::fork;
+use std::{
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
+
+use libc::{
+    fork, mmap, munmap, pthread_cond_t, MAP_ANONYMOUS, MAP_FAILED, MAP_SHARED, PROT_READ,
+    PROT_WRITE,
+};
 pub use process_sync::private::SharedMemoryObject;
-use process_sync::{private::check_libc_err, SharedCondvar, SharedMutex};
+use process_sync::{
+    private::check_libc_err, ClockId, LockStatus, SharedCondvar, SharedMutex, SyncError,
+};
 
-use common::{sleep, TestOutput};
+use common::{sleep, wait_child, TestOutput};
 
 fn child0(test_output: &mut TestOutput, mutex: &mut SharedMutex, condvar: &mut SharedCondvar) -> ! {
     test_output.write_line("child0 lock()");
@@ -53,7 +63,7 @@ fn child1(test_output: &mut TestOutput, mutex: &mut SharedMutex, condvar: &mut S
     std::process::exit(0);
 }
 
-fn parent(test_output: &mut TestOutput, _mutex: &mut SharedMutex, condvar: &mut SharedCondvar) {
+fn parent(test_output: &mut TestOutput, _mutex: &mut SharedMutex, condvar: &SharedCondvar) {
     sleep(40);
     test_output.write_line("parent notify_all()");
     condvar.notify_all().expect("notify_all() failed");
@@ -70,7 +80,7 @@ fn parent(test_output: &mut TestOutput, _mutex: &mut SharedMutex, condvar: &mut
     sleep(40);
 }
 
-fn main() {
+fn notify() {
     let mut test_output = TestOutput::new(&[
         "child0 lock()",
         "child0 wait()",
@@ -97,15 +107,542 @@ fn main() {
     let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
     let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
 
-    let pid = check_libc_err(unsafe { fork() }).expect("fork failed");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
     if pid == 0 {
         child0(&mut test_output, &mut mutex, &mut condvar);
     } else {
-        let pid = check_libc_err(unsafe { fork() }).expect("fork failed");
-        if pid == 0 {
+        let pid1 = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid1 == 0 {
             child1(&mut test_output, &mut mutex, &mut condvar);
         } else {
-            parent(&mut test_output, &mut mutex, &mut condvar);
+            parent(&mut test_output, &mut mutex, &condvar);
+            wait_child(pid);
+            wait_child(pid1);
         }
     }
 }
+
+fn wait_timeout() {
+    let mut test_output = TestOutput::new(&[
+        "child wait_timeout(20ms) false",
+        "child wait_timeout(200ms)",
+        "parent notify_one()",
+        "child wait_timeout(200ms) true",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        let notified = condvar
+            .wait_timeout(&mut mutex, Duration::from_millis(20))
+            .expect("wait_timeout() failed")
+            .is_some();
+        test_output.write_line(format!("child wait_timeout(20ms) {}", notified));
+        test_output.write_line("child wait_timeout(200ms)");
+        let notified = condvar
+            .wait_timeout(&mut mutex, Duration::from_millis(200))
+            .expect("wait_timeout() failed")
+            .is_some();
+        test_output.write_line(format!("child wait_timeout(200ms) {}", notified));
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(60);
+    mutex.lock().expect("lock() failed");
+    test_output.write_line("parent notify_one()");
+    condvar.notify_one().expect("notify_one() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(pid);
+}
+
+fn wait_while() {
+    let mut test_output = TestOutput::new(&[
+        "child wait_while()",
+        "parent notify_one() without ready",
+        "parent notify_one() with ready",
+        "child woken up with ready",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+    let mut ready = SharedMemoryObject::new(false).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        test_output.write_line("child wait_while()");
+        condvar
+            .wait_while(&mut mutex, || !unsafe { *ready.get() })
+            .expect("wait_while() failed");
+        test_output.write_line("child woken up with ready");
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    mutex.lock().expect("lock() failed");
+    test_output.write_line("parent notify_one() without ready");
+    condvar.notify_one().expect("notify_one() failed");
+    mutex.unlock().expect("unlock() failed");
+    sleep(20);
+    mutex.lock().expect("lock() failed");
+    unsafe { *ready.get_mut() = true };
+    test_output.write_line("parent notify_one() with ready");
+    condvar.notify_one().expect("notify_one() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(pid);
+}
+
+fn wait_guard() {
+    let mut test_output = TestOutput::new(&[
+        "child wait_guard()",
+        "parent notify_one() without ready",
+        "parent notify_one() with ready",
+        "child woken up with ready",
+    ]);
+
+    let mut ready = SharedMutex::with_data(false).expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let mut guard = ready.guard().expect("guard() failed");
+        test_output.write_line("child wait_guard()");
+        while !*guard {
+            guard = condvar.wait_guard(guard).expect("wait_guard() failed");
+        }
+        test_output.write_line("child woken up with ready");
+        drop(guard);
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let guard = ready.guard().expect("guard() failed");
+    test_output.write_line("parent notify_one() without ready");
+    condvar.notify_one().expect("notify_one() failed");
+    drop(guard);
+    sleep(20);
+    let mut guard = ready.guard().expect("guard() failed");
+    *guard = true;
+    test_output.write_line("parent notify_one() with ready");
+    condvar.notify_one().expect("notify_one() failed");
+    drop(guard);
+    wait_child(pid);
+}
+
+fn guard_waits() {
+    let mut ready = SharedMutex::with_data(false).expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let guard = ready.guard().expect("guard() failed");
+    let (guard, woken) = condvar
+        .wait_timeout_guard(guard, Duration::from_millis(20))
+        .expect("wait_timeout_guard() failed");
+    assert!(!woken);
+    assert!(!*guard);
+    drop(guard);
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let guard = ready.guard().expect("guard() failed");
+        let guard = condvar
+            .wait_while_guard(guard, |ready| !*ready)
+            .expect("wait_while_guard() failed");
+        assert!(*guard);
+        drop(guard);
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    ready
+        .with_locked(|ready| *ready = true)
+        .expect("with_locked() failed");
+    condvar.notify_one().expect("notify_one() failed");
+    wait_child(pid);
+}
+
+fn notify_n() {
+    // (jobs available, jobs taken)
+    let mut jobs = SharedMutex::with_data((0, 0)).expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let mut pids = Vec::new();
+    for _ in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            let mut guard = jobs.guard().expect("guard() failed");
+            while guard.0 == 0 {
+                guard = condvar.wait_guard(guard).expect("wait_guard() failed");
+            }
+            guard.0 -= 1;
+            guard.1 += 1;
+            drop(guard);
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(20);
+    jobs.with_locked(|jobs| jobs.0 = 2)
+        .expect("with_locked() failed");
+    condvar.notify_n(2).expect("notify_n() failed");
+    sleep(40);
+    assert_eq!(*jobs.guard().expect("guard() failed"), (0, 2));
+    jobs.with_locked(|jobs| jobs.0 = 1)
+        .expect("with_locked() failed");
+    condvar.notify_n(1).expect("notify_n() failed");
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert_eq!(*jobs.guard().expect("guard() failed"), (0, 3));
+}
+
+fn wait_timeout_while() {
+    let mut test_output = TestOutput::new(&[
+        "parent notify_all() without ready",
+        "parent notify_all() without ready",
+        "child wait_timeout_while(100ms) false",
+        "parent notify_all() with ready",
+        "child wait_timeout_while(200ms) true",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+    let mut ready = SharedMemoryObject::new(false).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        let start = Instant::now();
+        let ok = condvar
+            .wait_timeout_while(&mut mutex, Duration::from_millis(100), || !unsafe {
+                *ready.get()
+            })
+            .expect("wait_timeout_while() failed")
+            .is_some();
+        // spurious wakeups must not extend total wait
+        assert!(start.elapsed() < Duration::from_millis(160));
+        test_output.write_line(format!("child wait_timeout_while(100ms) {}", ok));
+        let ok = condvar
+            .wait_timeout_while(&mut mutex, Duration::from_millis(200), || !unsafe {
+                *ready.get()
+            })
+            .expect("wait_timeout_while() failed")
+            .is_some();
+        test_output.write_line(format!("child wait_timeout_while(200ms) {}", ok));
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    for _ in 0..2 {
+        sleep(30);
+        mutex.lock().expect("lock() failed");
+        test_output.write_line("parent notify_all() without ready");
+        condvar.notify_all().expect("notify_all() failed");
+        mutex.unlock().expect("unlock() failed");
+    }
+    sleep(90);
+    mutex.lock().expect("lock() failed");
+    unsafe { *ready.get_mut() = true };
+    test_output.write_line("parent notify_all() with ready");
+    condvar.notify_all().expect("notify_all() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(pid);
+}
+
+fn monotonic_clock() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar =
+        SharedCondvar::new_with_clock(ClockId::Monotonic).expect("cannot create SharedCondvar");
+    assert_eq!(condvar.clock(), ClockId::Monotonic);
+
+    mutex.lock().expect("lock() failed");
+    let start = Instant::now();
+    let notified = condvar
+        .wait_timeout(&mut mutex, Duration::from_millis(20))
+        .expect("wait_timeout() failed")
+        .is_some();
+    assert!(!notified);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert!(start.elapsed() < Duration::from_millis(500));
+    mutex.unlock().expect("unlock() failed");
+}
+
+fn wait_timeout_prompt() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    mutex.lock().expect("lock() failed");
+    let start = Instant::now();
+    let notified = condvar
+        .wait_timeout(&mut mutex, Duration::from_millis(50))
+        .expect("wait_timeout() failed")
+        .is_some();
+    assert!(!notified);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(start.elapsed() < Duration::from_millis(500));
+    mutex.unlock().expect("unlock() failed");
+}
+
+fn robust() {
+    let mut test_output = TestOutput::new(&[
+        "waiter wait()",
+        "notifier notify_one()",
+        "notifier exit",
+        "waiter AcquiredInconsistent",
+    ]);
+
+    let mut mutex = SharedMutex::new_robust().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let waiter = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if waiter == 0 {
+        mutex.lock().expect("lock() failed");
+        test_output.write_line("waiter wait()");
+        let status = condvar.wait(&mut mutex).expect("wait() failed");
+        test_output.write_line(format!("waiter {:?}", status));
+        mutex.mark_consistent().expect("mark_consistent() failed");
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    let notifier = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if notifier == 0 {
+        sleep(20);
+        mutex.lock().expect("lock() failed");
+        test_output.write_line("notifier notify_one()");
+        condvar.notify_one().expect("notify_one() failed");
+        test_output.write_line("notifier exit");
+        // exit while holding the mutex
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(notifier);
+    wait_child(waiter);
+    assert_eq!(mutex.lock().expect("lock() failed"), LockStatus::Acquired);
+    mutex.unlock().expect("unlock() failed");
+}
+
+fn robust_timeout() {
+    let mut mutex = SharedMutex::new_robust().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let notifier = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if notifier == 0 {
+        sleep(20);
+        mutex.lock().expect("lock() failed");
+        condvar.notify_one().expect("notify_one() failed");
+        // exit while holding the mutex
+        std::process::exit(0);
+    }
+
+    // parent
+    mutex.lock().expect("lock() failed");
+    let status = condvar
+        .wait_timeout(&mut mutex, Duration::from_secs(5))
+        .expect("wait_timeout() failed");
+    assert_eq!(status, Some(LockStatus::AcquiredInconsistent));
+    mutex.mark_consistent().expect("mark_consistent() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(notifier);
+}
+
+fn into_destroyed() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        condvar.wait(&mut mutex).expect("wait() failed");
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    mutex.lock().expect("lock() failed");
+    condvar.notify_one().expect("notify_one() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(pid);
+    condvar.into_destroyed().expect("into_destroyed() failed");
+    mutex.into_destroyed().expect("into_destroyed() failed");
+}
+
+fn wait_unlocked() {
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+
+    let mut tracked = SharedMutex::new_tracked().expect("cannot create SharedMutex");
+    let err = condvar.wait(&mut tracked).expect_err("wait() must fail");
+    assert!(matches!(err, SyncError::NotOwner(_)));
+
+    let mut error_checking = SharedMutex::new_error_checking().expect("cannot create SharedMutex");
+    let err = condvar
+        .wait_timeout(&mut error_checking, Duration::from_millis(20))
+        .expect_err("wait_timeout() must fail");
+    assert!(matches!(err, SyncError::NotOwner(_)));
+}
+
+fn tracked_waits() {
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+    let mut mutex = SharedMutex::new_tracked().expect("cannot create SharedMutex");
+    let mut notified =
+        SharedMemoryObject::new(AtomicU32::new(0)).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        // parent locks and unlocks mutex between waits, which must not make child look like non-owner
+        for round in 1..=2 {
+            while unsafe { notified.get() }.load(Ordering::Relaxed) < round {
+                condvar.wait(&mut mutex).expect("wait() failed");
+            }
+        }
+        let notified = condvar
+            .wait_timeout(&mut mutex, Duration::from_millis(10))
+            .expect("wait_timeout() failed")
+            .is_some();
+        assert!(!notified);
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    for round in 1..=2 {
+        sleep(20);
+        mutex.lock().expect("lock() failed");
+        unsafe { notified.get_mut() }.store(round, Ordering::Relaxed);
+        condvar.notify_one().expect("notify_one() failed");
+        mutex.unlock().expect("unlock() failed");
+    }
+    wait_child(pid);
+}
+
+fn counted() {
+    let mut released = SharedMutex::with_data(false).expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new_counted().expect("cannot create SharedCondvar");
+    assert_eq!(condvar.waiters(), Some(0));
+
+    let mut pids = Vec::new();
+    for _ in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            let mut guard = released.guard().expect("guard() failed");
+            while !*guard {
+                guard = condvar.wait_guard(guard).expect("wait_guard() failed");
+            }
+            drop(guard);
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(40);
+    assert_eq!(condvar.waiters(), Some(3));
+    released
+        .with_locked(|released| *released = true)
+        .expect("with_locked() failed");
+    assert_eq!(condvar.notify_all_counted(), Ok(Some(3)));
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert_eq!(condvar.waiters(), Some(0));
+
+    let condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+    assert_eq!(condvar.waiters(), None);
+    assert_eq!(condvar.notify_all_counted(), Ok(None));
+}
+
+fn in_place() {
+    // one mapping holds mutex, condvar and flag
+    let len = 4096;
+    let base = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(base, MAP_FAILED);
+    let condvar_offset = SharedMutex::MUTEX_SIZE.next_multiple_of(SharedCondvar::CONDVAR_ALIGN);
+    let flag_offset = condvar_offset + SharedCondvar::CONDVAR_SIZE;
+    let flag = unsafe { &*(base.cast::<u8>().add(flag_offset) as *const AtomicBool) };
+
+    let misaligned = unsafe { base.cast::<u8>().add(1) }.cast::<pthread_cond_t>();
+    assert!(matches!(
+        unsafe { SharedCondvar::new_in_place(misaligned) },
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    let mut mutex =
+        unsafe { SharedMutex::new_in_place(base.cast()) }.expect("new_in_place() failed");
+    let mut condvar =
+        unsafe { SharedCondvar::new_in_place(base.cast::<u8>().add(condvar_offset).cast()) }
+            .expect("new_in_place() failed");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        condvar
+            .wait_while(&mut mutex, || !flag.load(Ordering::Relaxed))
+            .expect("wait_while() failed");
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    mutex.lock().expect("lock() failed");
+    flag.store(true, Ordering::Relaxed);
+    condvar.notify_one().expect("notify_one() failed");
+    mutex.unlock().expect("unlock() failed");
+    wait_child(pid);
+    drop(condvar);
+    drop(mutex);
+    // memory is still mapped and owned by caller
+    assert_eq!(unsafe { munmap(base, len) }, 0);
+}
+
+fn main() {
+    notify();
+    wait_timeout();
+    wait_while();
+    wait_guard();
+    guard_waits();
+    notify_n();
+    wait_timeout_while();
+    monotonic_clock();
+    wait_timeout_prompt();
+    robust();
+    robust_timeout();
+    into_destroyed();
+    wait_unlocked();
+    tracked_waits();
+    counted();
+    in_place();
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedAtomicUsize, SharedEvent};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn manual_reset() {
+    let mut test_output = TestOutput::new(&[
+        "parent set()",
+        "child released",
+        "child released",
+        "parent reset()",
+    ]);
+
+    let mut event = SharedEvent::new().expect("cannot create SharedEvent");
+    assert!(!event.is_set());
+
+    let mut pids = Vec::new();
+    for _ in 0..2 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            event.wait().expect("wait() failed");
+            test_output.write_line("child released");
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(20);
+    test_output.write_line("parent set()");
+    event.set().expect("set() failed");
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert!(event.is_set());
+    // event stays set
+    event.wait().expect("wait() failed");
+    test_output.write_line("parent reset()");
+    event.reset().expect("reset() failed");
+    assert!(!event.is_set());
+}
+
+fn auto_reset() {
+    let mut event = SharedEvent::new_auto_reset().expect("cannot create SharedEvent");
+    let released = SharedAtomicUsize::new(0).expect("cannot create SharedAtomicUsize");
+
+    let mut pids = Vec::new();
+    for _ in 0..2 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            event.wait().expect("wait() failed");
+            released.fetch_add(1, Ordering::Relaxed);
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(20);
+    event.set().expect("set() failed");
+    sleep(40);
+    // only one process is released, and it resets event
+    assert_eq!(released.load(Ordering::Relaxed), 1);
+    assert!(!event.is_set());
+    event.set().expect("set() failed");
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert_eq!(released.load(Ordering::Relaxed), 2);
+}
+
+fn main() {
+    manual_reset();
+    auto_reset();
+}
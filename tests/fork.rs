@@ -0,0 +1,92 @@
+mod common;
+
+use std::os::unix::process::ExitStatusExt;
+
+use libc::{fork, SIGKILL};
+use process_sync::{
+    fork_scope, private::check_libc_err, reap_child, try_reap, SharedCondvar, SharedMemoryObject,
+    SharedMutex, SyncError,
+};
+
+use common::{sleep, TestOutput};
+
+fn exit_status() {
+    let (ret, status) = fork_scope(&mut (), |_| {}, |_| 5).expect("fork_scope() failed");
+    assert_eq!(ret, 5);
+    assert!(status.success());
+
+    let (_, status) =
+        fork_scope(&mut (), |_| panic!("child panicked"), |_| ()).expect("fork_scope() failed");
+    assert_eq!(status.code(), Some(101));
+}
+
+fn shared_state() {
+    let mut test_output =
+        TestOutput::new(&["child wait()", "parent notify_one()", "child woken up"]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let mut condvar = SharedCondvar::new().expect("cannot create SharedCondvar");
+    let mut ready = SharedMemoryObject::new(false).expect("cannot create SharedMemoryObject");
+
+    let (_, status) = fork_scope(
+        &mut (&mut mutex, &mut condvar, &mut ready, &mut test_output),
+        |(mutex, condvar, ready, test_output)| {
+            mutex.lock().expect("lock() failed");
+            test_output.write_line("child wait()");
+            condvar
+                .wait_while(mutex, || !unsafe { *ready.get() })
+                .expect("wait_while() failed");
+            test_output.write_line("child woken up");
+            mutex.unlock().expect("unlock() failed");
+        },
+        |(mutex, condvar, ready, test_output)| {
+            sleep(20);
+            mutex.lock().expect("lock() failed");
+            unsafe { *ready.get_mut() = true };
+            test_output.write_line("parent notify_one()");
+            condvar.notify_one().expect("notify_one() failed");
+            mutex.unlock().expect("unlock() failed");
+        },
+    )
+    .expect("fork_scope() failed");
+    assert!(status.success());
+}
+
+fn reap() {
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        sleep(40);
+        std::process::exit(7);
+    }
+
+    // parent
+    assert_eq!(try_reap(pid), Ok(None));
+    let status = reap_child(pid).expect("reap_child() failed");
+    assert_eq!(status.code(), Some(7));
+    // child is already reaped
+    assert!(matches!(try_reap(pid), Err(SyncError::Os(_))));
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { libc::raise(SIGKILL) };
+        std::process::exit(0);
+    }
+
+    // parent
+    let status = loop {
+        if let Some(status) = try_reap(pid).expect("try_reap() failed") {
+            break status;
+        }
+        sleep(5);
+    };
+    assert_eq!(status.signal(), Some(SIGKILL));
+    assert_eq!(status.code(), None);
+}
+
+fn main() {
+    exit_status();
+    shared_state();
+    reap();
+}
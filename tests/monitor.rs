@@ -0,0 +1,104 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedMonitor};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn wait_notify() {
+    let mut test_output =
+        TestOutput::new(&["child wait()", "parent notify_one()", "child notified"]);
+
+    let mut monitor = SharedMonitor::new(false).expect("cannot create SharedMonitor");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let guard = monitor.lock().expect("cannot lock child");
+        test_output.write_line("child wait()");
+        let guard = guard
+            .wait_while(|ready| !*ready)
+            .expect("wait_while() failed");
+        assert!(*guard);
+        test_output.write_line("child notified");
+        drop(guard);
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let mut guard = monitor.lock().expect("cannot lock parent");
+    test_output.write_line("parent notify_one()");
+    *guard = true;
+    guard.notify_one().expect("notify_one() failed");
+    drop(guard);
+    wait_child(pid);
+}
+
+fn queue() {
+    let mut monitor = SharedMonitor::new([0u32; 3]).expect("cannot create SharedMonitor");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        // producer fills slots one by one, waiting for consumer to take previous value
+        for value in 1..=100 {
+            let guard = monitor.lock().expect("cannot lock child");
+            let mut guard = guard
+                .wait_while(|slots| slots[0] != 0)
+                .expect("wait_while() failed");
+            guard[0] = value;
+            guard[1] += value;
+            guard.notify_all().expect("notify_all() failed");
+        }
+        std::process::exit(0);
+    }
+
+    // parent
+    let mut sum = 0;
+    for _ in 0..100 {
+        let guard = monitor.lock().expect("cannot lock parent");
+        let mut guard = guard
+            .wait_while(|slots| slots[0] == 0)
+            .expect("wait_while() failed");
+        sum += guard[0];
+        guard[0] = 0;
+        guard.notify_all().expect("notify_all() failed");
+    }
+    wait_child(pid);
+    assert_eq!(sum, 5050);
+    assert_eq!(monitor.lock().expect("cannot lock parent")[1], 5050);
+}
+
+fn vm_size_kb() -> usize {
+    let status = std::fs::read_to_string("/proc/self/status").expect("cannot read status");
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("VmSize:"))
+        .expect("no VmSize");
+    line.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+fn single_mapping() {
+    const COUNT: usize = 1000;
+    let page_kb = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize / 1024;
+
+    let before = vm_size_kb();
+    let mut monitors = (0..COUNT)
+        .map(|i| SharedMonitor::new(i.to_string()).expect("cannot create SharedMonitor"))
+        .collect::<Vec<_>>();
+    // one page per monitor, not one for mutex and one for condvar
+    assert!(vm_size_kb() - before < COUNT * page_kb * 3 / 2);
+
+    for (i, monitor) in monitors.iter_mut().enumerate() {
+        let guard = monitor.lock().expect("cannot lock");
+        assert_eq!(*guard, i.to_string());
+        guard.notify_all().expect("notify_all() failed");
+    }
+}
+
+fn main() {
+    wait_notify();
+    queue();
+    single_mapping();
+}
@@ -1,12 +1,27 @@
 mod common;
 
-use libc::fork;
+use std::{
+    io::ErrorKind,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use libc::{
+    fork, mmap, munmap, pthread_mutex_lock, pthread_mutex_t, pthread_mutex_trylock,
+    pthread_mutex_unlock, pthread_mutexattr_destroy, pthread_mutexattr_init,
+    pthread_mutexattr_settype, pthread_mutexattr_t, sched_get_priority_max, EBUSY, EINVAL,
+    MAP_ANONYMOUS, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE, PTHREAD_MUTEX_RECURSIVE,
+    SCHED_FIFO,
+};
 pub use process_sync::private::SharedMemoryObject;
-use process_sync::{private::check_libc_err, SharedMutex};
+use process_sync::{
+    private::check_libc_err, reap_child, set_drop_error_hook, HeapAllocator, LockStatus, MutexKind,
+    MutexProtocol, SharedAllocator, SharedAtomicUsize, SharedMutex, SharedPrimitive, SyncError,
+};
 
-use common::{sleep, TestOutput};
+use common::{sleep, wait_child, TestOutput};
 
-fn main() {
+fn lock_unlock() {
     let mut test_output = TestOutput::new(&[
         "child lock()",
         "child locked",
@@ -18,7 +33,7 @@ fn main() {
 
     let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
 
-    let pid = check_libc_err(unsafe { fork() }).expect("fork failed");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
     if pid == 0 {
         // child
         test_output.write_line("child lock()");
@@ -38,4 +53,968 @@ fn main() {
     sleep(20);
     test_output.write_line("parent unlock()");
     mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn try_lock() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "parent try_lock() false",
+        "child unlock()",
+        "parent try_lock() true",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = mutex.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    sleep(60);
+    let locked = mutex.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn is_locked() {
+    let mut test_output = TestOutput::new(&[
+        "parent is_locked() false",
+        "child locked",
+        "parent is_locked() true",
+        "child unlocked",
+        "parent is_locked() false",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        sleep(20);
+        mutex.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(40);
+        test_output.write_line("child unlocked");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    for _ in 0..3 {
+        let locked = mutex.is_locked().expect("is_locked() failed");
+        test_output.write_line(format!("parent is_locked() {}", locked));
+        sleep(40);
+    }
+    wait_child(pid);
+}
+
+fn lock_timeout() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "parent lock_timeout(20ms) false",
+        "child unlock()",
+        "parent lock_timeout(200ms) true",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(80);
+        test_output.write_line("child unlock()");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = mutex
+        .lock_timeout(Duration::from_millis(20))
+        .expect("lock_timeout() failed");
+    test_output.write_line(format!("parent lock_timeout(20ms) {}", locked));
+    let locked = mutex
+        .lock_timeout(Duration::from_millis(200))
+        .expect("lock_timeout() failed");
+    test_output.write_line(format!("parent lock_timeout(200ms) {}", locked));
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+
+    // overflowing deadline must not fail
+    assert!(mutex
+        .lock_timeout(Duration::MAX)
+        .expect("lock_timeout() failed"));
+    mutex.unlock().expect("cannot unlock parent");
+}
+
+fn guard() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "parent guard()",
+        "child guard dropped",
+        "parent locked",
+    ]);
+
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        {
+            let _guard = mutex.guard().expect("cannot lock child");
+            test_output.write_line("child locked");
+            sleep(60);
+            test_output.write_line("child guard dropped");
+        }
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    test_output.write_line("parent guard()");
+    let guard = mutex.guard().expect("cannot lock parent");
+    test_output.write_line("parent locked");
+    drop(guard);
+    assert!(mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn robust() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "child exit",
+        "parent AcquiredInconsistent",
+        "parent Acquired",
+    ]);
+
+    let mut mutex = SharedMutex::new_robust().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(20);
+        test_output.write_line("child exit");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    let status = mutex.lock().expect("cannot lock parent");
+    test_output.write_line(format!("parent {:?}", status));
+    mutex.mark_consistent().expect("mark_consistent() failed");
+    mutex.unlock().expect("cannot unlock parent");
+
+    let guard = mutex.guard().expect("cannot lock parent");
+    test_output.write_line(format!("parent {:?}", guard.status()));
+}
+
+fn error_checking() {
+    let mut mutex = SharedMutex::new_error_checking().expect("cannot create SharedMutex");
+
+    mutex.lock().expect("cannot lock");
+    let err = mutex.lock().expect_err("relock must fail");
+    assert!(matches!(err, SyncError::Deadlock(_)));
+    assert_eq!(std::io::Error::from(err).kind(), ErrorKind::Deadlock);
+    mutex.unlock().expect("cannot unlock");
+
+    let err = mutex.unlock().expect_err("unlock by non-owner must fail");
+    assert!(matches!(err, SyncError::NotOwner(_)));
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        sleep(40);
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let err = mutex.unlock().expect_err("unlock by non-owner must fail");
+    assert!(matches!(err, SyncError::NotOwner(_)));
+    wait_child(pid);
+}
+
+fn recursive() {
+    let mut test_output = TestOutput::new(&[
+        "child locked twice",
+        "parent try_lock() false",
+        "child unlocked once",
+        "parent try_lock() false",
+        "child unlocked twice",
+        "parent try_lock() true",
+    ]);
+
+    let mut mutex = SharedMutex::new_recursive().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        mutex.lock().expect("cannot relock child");
+        test_output.write_line("child locked twice");
+        sleep(40);
+        mutex.unlock().expect("cannot unlock child");
+        test_output.write_line("child unlocked once");
+        sleep(40);
+        mutex.unlock().expect("cannot unlock child");
+        test_output.write_line("child unlocked twice");
+        std::process::exit(0);
+    }
+
+    // parent
+    for _ in 0..3 {
+        sleep(20);
+        let locked = mutex.try_lock().expect("try_lock() failed");
+        test_output.write_line(format!("parent try_lock() {}", locked));
+        sleep(20);
+    }
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn with_type() {
+    for kind in [MutexKind::Normal, MutexKind::Default] {
+        let mut mutex = SharedMutex::with_type(kind).expect("cannot create SharedMutex");
+        mutex.lock().expect("cannot lock");
+        assert!(!mutex.try_lock().expect("try_lock() failed"));
+        mutex.unlock().expect("cannot unlock");
+    }
+
+    let mut mutex =
+        SharedMutex::with_type(MutexKind::ErrorCheck).expect("cannot create SharedMutex");
+    mutex.lock().expect("cannot lock");
+    assert!(matches!(mutex.lock(), Err(SyncError::Deadlock(_))));
+    mutex.unlock().expect("cannot unlock");
+    assert!(matches!(mutex.unlock(), Err(SyncError::NotOwner(_))));
+
+    let mut mutex =
+        SharedMutex::with_type(MutexKind::Recursive).expect("cannot create SharedMutex");
+    mutex.lock().expect("cannot lock");
+    mutex.lock().expect("cannot relock");
+    mutex.unlock().expect("cannot unlock");
+    mutex.unlock().expect("cannot unlock");
+    assert!(matches!(mutex.unlock(), Err(SyncError::NotOwner(_))));
+}
+
+fn protocol() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "parent try_lock() false",
+        "child unlocked",
+        "parent locked",
+    ]);
+
+    let mut mutex = SharedMutex::new_with_protocol(MutexProtocol::PriorityInheritance)
+        .expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(40);
+        test_output.write_line("child unlocked");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = mutex.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    mutex.lock().expect("cannot lock parent");
+    test_output.write_line("parent locked");
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+
+    let ceiling = unsafe { sched_get_priority_max(SCHED_FIFO) };
+    SharedMutex::new_with_protocol(MutexProtocol::PriorityCeiling(ceiling))
+        .expect("cannot create SharedMutex");
+    let err = SharedMutex::new_with_protocol(MutexProtocol::PriorityCeiling(ceiling + 1))
+        .err()
+        .expect("invalid ceiling must fail");
+    assert_eq!(err.raw_os_error(), Some(EINVAL));
+    assert_eq!(err.operation(), Some("pthread_mutexattr_setprioceiling"));
+    assert!(err
+        .to_string()
+        .contains("pthread_mutexattr_setprioceiling failed: Invalid argument"));
+}
+
+fn with_data() {
+    let mut counter = SharedMutex::with_data(0u64).expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    for _ in 0..1000 {
+        let mut guard = counter.guard().expect("cannot lock");
+        let value = *guard;
+        std::hint::spin_loop();
+        *guard = value + 1;
+    }
+    if pid == 0 {
+        // child
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(*counter.guard().expect("cannot lock"), 2000);
+}
+
+#[cfg(feature = "serde")]
+fn serde_guard() {
+    let mut values = SharedMutex::with_data([1u32, 2, 3]).expect("cannot create SharedMutex");
+    let guard = values.guard().expect("cannot lock");
+    let json = serde_json::to_string(&guard).expect("cannot serialize SharedMutexGuard");
+    assert_eq!(json, "[1,2,3]");
+}
+
+fn with_locked() {
+    let mut counter = SharedMutex::with_data(0u64).expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    for _ in 0..1000 {
+        counter
+            .with_locked(|counter| {
+                let value = *counter;
+                std::hint::spin_loop();
+                *counter = value + 1;
+            })
+            .expect("with_locked() failed");
+    }
+    if pid == 0 {
+        // child
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(counter.with_locked(|counter| *counter), Ok(2000));
+
+    let mut mutex = SharedMutex::new_robust().expect("cannot create SharedMutex");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child exits while holding the mutex
+        mutex.lock().expect("cannot lock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    let err = mutex
+        .with_locked(|_| ())
+        .expect_err("inconsistent mutex must fail");
+    assert!(matches!(err, SyncError::OwnerDead(_)));
+    mutex.mark_consistent().expect("mark_consistent() failed");
+    mutex.unlock().expect("cannot unlock parent");
+    assert_eq!(mutex.with_locked(|_| 1), Ok(1));
+}
+
+fn lock_and() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let raw = mutex.as_raw();
+
+    let value = mutex
+        .lock_and(|| {
+            assert_eq!(unsafe { pthread_mutex_trylock(raw) }, EBUSY);
+            42
+        })
+        .expect("lock_and() failed");
+    assert_eq!(value, 42);
+    assert!(!mutex.is_locked().expect("is_locked() failed"));
+
+    // mutex is unlocked while unwinding
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        mutex.lock_and(|| panic!("panic under lock")).ok();
+    }));
+    assert!(result.is_err());
+    assert!(mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("cannot unlock");
+}
+
+fn forget_on_drop() {
+    static DROP_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+    // drop error hook is global, so test runs in separate process
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        set_drop_error_hook(|_, _| {
+            DROP_ERRORS.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // destroying locked mutex fails with EBUSY
+        let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+        mutex.lock().expect("cannot lock");
+        drop(mutex);
+        assert_eq!(DROP_ERRORS.load(Ordering::Relaxed), 1);
+
+        let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+        mutex.lock().expect("cannot lock");
+        mutex.forget_on_drop();
+        drop(mutex);
+        assert_eq!(DROP_ERRORS.load(Ordering::Relaxed), 1);
+        std::process::exit(0);
+    }
+
+    // parent
+    let status = reap_child(pid).expect("reap_child() failed");
+    assert!(status.success());
+}
+
+fn new_with_attr() {
+    let mut test_output = TestOutput::new(&[
+        "child locked twice",
+        "parent try_lock() false",
+        "child unlocked",
+        "parent locked",
+    ]);
+
+    // attributes don't set PTHREAD_PROCESS_SHARED, mutex must be shared anyway
+    let mut mutex = unsafe {
+        let mut attr: pthread_mutexattr_t = std::mem::zeroed();
+        assert_eq!(pthread_mutexattr_init(&mut attr), 0);
+        assert_eq!(
+            pthread_mutexattr_settype(&mut attr, PTHREAD_MUTEX_RECURSIVE),
+            0
+        );
+        let mutex = SharedMutex::new_with_attr(&attr);
+        assert_eq!(pthread_mutexattr_destroy(&mut attr), 0);
+        mutex.expect("cannot create SharedMutex")
+    };
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        mutex.lock().expect("cannot relock child");
+        test_output.write_line("child locked twice");
+        sleep(40);
+        mutex.unlock().expect("cannot unlock child");
+        test_output.write_line("child unlocked");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = mutex.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    mutex.lock().expect("cannot lock parent");
+    test_output.write_line("parent locked");
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+
+    // attributes are kept for reinitialization
+    unsafe { mutex.reinitialize() }.expect("reinitialize() failed");
+    mutex.lock().expect("cannot lock");
+    mutex.lock().expect("cannot relock");
+    mutex.unlock().expect("cannot unlock");
+    mutex.unlock().expect("cannot unlock");
+}
+
+fn thread_local() {
+    let mut mutex = SharedMutex::new_thread_local().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    // child locked its own copy
+    assert!(mutex.try_lock().expect("try_lock() failed"));
+    assert!(!mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("cannot unlock");
+}
+
+fn drop_error_description() {
+    static DESCRIPTION: std::sync::Mutex<String> = std::sync::Mutex::new(String::new());
+    static ERRNO: AtomicUsize = AtomicUsize::new(0);
+
+    // drop error hook is global, so test runs in separate process
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        set_drop_error_hook(|description, err| {
+            *DESCRIPTION.lock().unwrap() = description.to_string();
+            assert_eq!(err.operation(), Some("pthread_mutex_destroy"));
+            ERRNO.store(err.raw_os_error().unwrap() as usize, Ordering::Relaxed);
+        });
+
+        let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+        mutex.lock().expect("cannot lock");
+        drop(mutex);
+
+        let description = DESCRIPTION.lock().unwrap().clone();
+        let current_pid = std::process::id();
+        assert!(description.starts_with("cannot destroy mutex at 0x"));
+        assert!(description.ends_with(&format!(
+            "(owner pid {}, current pid {})",
+            current_pid, current_pid
+        )));
+        assert_eq!(ERRNO.load(Ordering::Relaxed), EBUSY as usize);
+        std::process::exit(0);
+    }
+
+    // parent
+    let status = reap_child(pid).expect("reap_child() failed");
+    assert!(status.success());
+}
+
+fn tracked() {
+    let mut mutex = SharedMutex::new_tracked().expect("cannot create SharedMutex");
+    assert_eq!(mutex.owner_pid(), None);
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        sleep(40);
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    assert_eq!(mutex.owner_pid(), Some(pid));
+    wait_child(pid);
+    assert_eq!(mutex.owner_pid(), None);
+
+    assert!(mutex.try_lock().expect("try_lock() failed"));
+    assert_eq!(mutex.owner_pid(), Some(unsafe { libc::getpid() }));
+    mutex.unlock().expect("cannot unlock parent");
+
+    let mut untracked = SharedMutex::new().expect("cannot create SharedMutex");
+    untracked.lock().expect("cannot lock");
+    assert_eq!(untracked.owner_pid(), None);
+    untracked.unlock().expect("cannot unlock");
+}
+
+fn into_destroyed() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    mutex.into_destroyed().expect("into_destroyed() failed");
+}
+
+fn drop_chain() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    let locked = SharedAtomicUsize::new(0).expect("cannot create SharedAtomicUsize");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // intermediate process
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // grandchild
+            sleep(40);
+            // mutex must not be destroyed by intermediate process
+            mutex.lock().expect("cannot lock grandchild");
+            mutex.unlock().expect("cannot unlock grandchild");
+            locked.fetch_add(1, Ordering::Relaxed);
+            drop(mutex);
+            std::process::exit(0);
+        }
+        drop(mutex);
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    // grandchild can't be waited for
+    sleep(80);
+    assert_eq!(locked.load(Ordering::Relaxed), 1);
+    mutex.lock().expect("cannot lock parent");
+    mutex.unlock().expect("cannot unlock parent");
+}
+
+fn leveled() {
+    let mut outer = SharedMutex::new_leveled(1).expect("cannot create SharedMutex");
+    let mut inner = SharedMutex::new_leveled(2).expect("cannot create SharedMutex");
+    assert_eq!(inner.level(), Some(2));
+
+    outer.lock().expect("cannot lock outer");
+    inner.lock().expect("cannot lock inner");
+    outer.unlock().expect("cannot unlock outer");
+    inner.unlock().expect("cannot unlock inner");
+
+    inner.lock().expect("cannot lock inner");
+    // try_lock() can't deadlock, so it is not checked
+    assert!(outer.try_lock().expect("try_lock() failed"));
+    outer.unlock().expect("cannot unlock outer");
+    if cfg!(debug_assertions) {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| outer.lock()));
+        assert!(result.is_err(), "lock ordering violation must panic");
+    }
+    inner.unlock().expect("cannot unlock inner");
+    outer.lock().expect("cannot lock outer");
+    outer.unlock().expect("cannot unlock outer");
+}
+
+fn zeroed_init() {
+    // mutexes are initialized in place on zeroed memory
+    let mutexes = [
+        SharedMutex::new(),
+        SharedMutex::new_robust(),
+        SharedMutex::new_error_checking(),
+        SharedMutex::new_recursive(),
+    ];
+    for mutex in mutexes {
+        let mut mutex = mutex.expect("cannot create SharedMutex");
+        assert_eq!(mutex.lock(), Ok(LockStatus::Acquired));
+        mutex.unlock().expect("cannot unlock");
+        assert!(mutex.try_lock().expect("try_lock() failed"));
+        mutex.unlock().expect("cannot unlock");
+    }
+
+    let mut data = SharedMutex::with_data([7u8; 100]).expect("cannot create SharedMutex");
+    assert_eq!(*data.guard().expect("guard() failed"), [7; 100]);
+}
+
+fn raw_layout() {
+    const _: () = assert!(SharedMutex::MUTEX_SIZE == std::mem::size_of::<pthread_mutex_t>());
+    const _: () = assert!(SharedMutex::MUTEX_ALIGN == std::mem::align_of::<pthread_mutex_t>());
+
+    let mut mutex = SharedMutex::with_data(5u64).expect("cannot create SharedMutex");
+    let raw = mutex.as_raw();
+    assert_eq!(raw as usize % SharedMutex::MUTEX_ALIGN, 0);
+
+    // raw mutex is the same mutex, locked through C API
+    assert_eq!(unsafe { pthread_mutex_lock(raw) }, 0);
+    assert!(!mutex.try_lock().expect("try_lock() failed"));
+    assert_eq!(unsafe { pthread_mutex_unlock(raw) }, 0);
+    assert_eq!(*mutex.guard().expect("guard() failed"), 5);
+}
+
+fn in_place() {
+    // one mapping holds two mutexes and a counter
+    let len = 4096;
+    let base = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(base, MAP_FAILED);
+    let stride = SharedMutex::MUTEX_SIZE.next_multiple_of(SharedMutex::MUTEX_ALIGN);
+    let counter = unsafe { &*(base.cast::<u8>().add(2 * stride) as *const AtomicUsize) };
+
+    let misaligned = unsafe { base.cast::<u8>().add(1) }.cast::<pthread_mutex_t>();
+    assert!(matches!(
+        unsafe { SharedMutex::new_in_place(misaligned) },
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    let mut first =
+        unsafe { SharedMutex::new_in_place(base.cast()) }.expect("new_in_place() failed");
+    let mut second = unsafe { SharedMutex::new_in_place(base.cast::<u8>().add(stride).cast()) }
+        .expect("new_in_place() failed");
+
+    let mut pids = Vec::new();
+    for _ in 0..2 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            for _ in 0..100 {
+                first.lock().expect("lock() failed");
+                second.lock().expect("lock() failed");
+                let value = counter.load(Ordering::Relaxed);
+                counter.store(value + 1, Ordering::Relaxed);
+                second.unlock().expect("unlock() failed");
+                first.unlock().expect("unlock() failed");
+            }
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert_eq!(counter.load(Ordering::Relaxed), 200);
+    drop(first);
+    drop(second);
+    // memory is still mapped and owned by caller
+    assert_eq!(unsafe { munmap(base, len) }, 0);
+}
+
+fn lock_timeout_monotonic() {
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    match mutex.lock_timeout_monotonic(Duration::from_millis(20)) {
+        Ok(locked) => assert!(locked),
+        // C library lacks pthread_mutex_clocklock
+        Err(SyncError::InvalidInput(_)) => return,
+        Err(err) => panic!("lock_timeout_monotonic() failed: {}", err),
+    }
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        // mutex is held by parent
+        let locked = mutex
+            .lock_timeout_monotonic(Duration::from_millis(20))
+            .expect("lock_timeout_monotonic() failed");
+        assert!(!locked);
+        let locked = mutex
+            .lock_timeout_monotonic(Duration::from_secs(5))
+            .expect("lock_timeout_monotonic() failed");
+        assert!(locked);
+        mutex.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(60);
+    mutex.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn reinitialize() {
+    let mut mutex = SharedMutex::with_data(5u32).expect("cannot create SharedMutex");
+    unsafe { mutex.reinitialize() }.expect("reinitialize() failed");
+    assert_eq!(*mutex.guard().expect("guard() failed"), 5);
+
+    let mut mutex = SharedMutex::new_robust().expect("cannot create SharedMutex");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(mutex.lock(), Ok(LockStatus::AcquiredInconsistent));
+    // unlocking without marking consistent makes mutex unrecoverable
+    mutex.unlock().expect("cannot unlock");
+    assert!(mutex.lock().is_err());
+
+    unsafe { mutex.reinitialize() }.expect("reinitialize() failed");
+    assert_eq!(mutex.lock(), Ok(LockStatus::Acquired));
+    mutex.unlock().expect("cannot unlock");
+
+    // mutex is still robust
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        std::process::exit(0);
+    }
+    wait_child(pid);
+    assert_eq!(mutex.lock(), Ok(LockStatus::AcquiredInconsistent));
+    mutex.mark_consistent().expect("mark_consistent() failed");
+    mutex.unlock().expect("cannot unlock");
+}
+
+fn poisoning() {
+    let mut mutex = SharedMutex::with_data(0u32)
+        .and_then(SharedMutex::with_poisoning)
+        .expect("cannot create SharedMutex");
+    assert!(mutex
+        .guard_with_poison()
+        .expect("guard_with_poison() failed")
+        .is_ok());
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        std::panic::set_hook(Box::new(|_| {}));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut guard = mutex.guard().expect("cannot lock child");
+            *guard = 1;
+            panic!("child panicked while holding guard");
+        }));
+        assert!(result.is_err());
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert!(mutex.is_poisoned());
+    match mutex
+        .guard_with_poison()
+        .expect("guard_with_poison() failed")
+    {
+        Ok(_) => panic!("mutex must be poisoned"),
+        Err(err) => {
+            let mut guard = err.into_inner();
+            assert_eq!(*guard, 1);
+            *guard = 0;
+        }
+    }
+    mutex.clear_poison();
+    assert!(mutex
+        .guard_with_poison()
+        .expect("guard_with_poison() failed")
+        .is_ok());
+
+    // robust mutex is poisoned by death of holding process
+    let mut mutex = SharedMutex::new_robust()
+        .and_then(SharedMutex::with_poisoning)
+        .expect("cannot create SharedMutex");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("cannot lock child");
+        std::process::exit(0);
+    }
+    wait_child(pid);
+    let mut guard = match mutex
+        .guard_with_poison()
+        .expect("guard_with_poison() failed")
+    {
+        Ok(_) => panic!("mutex must be poisoned"),
+        Err(err) => err.into_inner(),
+    };
+    assert_eq!(guard.status(), LockStatus::AcquiredInconsistent);
+    guard.mark_consistent().expect("mark_consistent() failed");
+    drop(guard);
+    assert!(mutex.is_poisoned());
+
+    // mutexes without poisoning are never poisoned
+    let mut mutex = SharedMutex::new().expect("cannot create SharedMutex");
+    assert!(!mutex.is_poisoned());
+    assert!(mutex
+        .guard_with_poison()
+        .expect("guard_with_poison() failed")
+        .is_ok());
+}
+
+fn new_in() {
+    // heap-backed mutex can be tested without forking
+    let mut mutex = SharedMutex::new_in(HeapAllocator).expect("new_in() failed");
+    mutex.lock().expect("lock() failed");
+    assert!(!mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("unlock() failed");
+    assert!(mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("unlock() failed");
+
+    let counter =
+        SharedMemoryObject::new_in(AtomicUsize::new(0), HeapAllocator).expect("new_in() failed");
+    assert!(matches!(
+        SharedMemoryObject::new_in((), HeapAllocator),
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    // shared allocator behaves like new()
+    let mut mutex = SharedMutex::new_in(SharedAllocator).expect("new_in() failed");
+    let shared =
+        SharedMemoryObject::new_in(AtomicUsize::new(0), SharedAllocator).expect("new_in() failed");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        mutex.lock().expect("lock() failed");
+        unsafe { shared.get() }.fetch_add(1, Ordering::Relaxed);
+        unsafe { counter.get() }.fetch_add(1, Ordering::Relaxed);
+        mutex.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { shared.get() }.load(Ordering::Relaxed), 1);
+    // heap memory is private to each process
+    assert_eq!(unsafe { counter.get() }.load(Ordering::Relaxed), 0);
+}
+
+fn lock_adaptive() {
+    for spin_count in [0, 1000] {
+        let mut mutex = SharedMutex::new()
+            .expect("cannot create SharedMutex")
+            .with_spin_count(spin_count);
+        let mut counter = SharedMemoryObject::new(0u64).expect("cannot create SharedMemoryObject");
+
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        for _ in 0..1000 {
+            assert_eq!(
+                mutex.lock_adaptive().expect("lock_adaptive() failed"),
+                LockStatus::Acquired
+            );
+            unsafe { *counter.get_mut() += 1 };
+            mutex.unlock().expect("unlock() failed");
+        }
+        if pid == 0 {
+            // child
+            std::process::exit(0);
+        }
+
+        // parent
+        wait_child(pid);
+        assert_eq!(unsafe { *counter.get() }, 2000);
+    }
+
+    let mut mutex = SharedMutex::new_adaptive(10).expect("cannot create SharedMutex");
+    mutex.lock_adaptive().expect("lock_adaptive() failed");
+    assert!(!mutex.try_lock().expect("try_lock() failed"));
+    mutex.unlock().expect("unlock() failed");
+}
+
+fn main() {
+    lock_unlock();
+    try_lock();
+    is_locked();
+    lock_timeout();
+    lock_timeout_monotonic();
+    guard();
+    robust();
+    error_checking();
+    recursive();
+    with_type();
+    protocol();
+    with_data();
+    #[cfg(feature = "serde")]
+    serde_guard();
+    with_locked();
+    lock_and();
+    forget_on_drop();
+    drop_error_description();
+    new_with_attr();
+    thread_local();
+    tracked();
+    into_destroyed();
+    drop_chain();
+    leveled();
+    zeroed_init();
+    raw_layout();
+    in_place();
+    new_in();
+    lock_adaptive();
+    reinitialize();
+    poisoning();
 }
@@ -0,0 +1,71 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedAtomicUsize, SharedNotify};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn wait_for() {
+    let mut test_output = TestOutput::new(&[
+        "child wait_for()",
+        "parent notify()",
+        "parent notify()",
+        "child released",
+    ]);
+
+    let mut notify = SharedNotify::new().expect("cannot create SharedNotify");
+    let counter = SharedAtomicUsize::new(0).expect("cannot create SharedAtomicUsize");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        test_output.write_line("child wait_for()");
+        notify
+            .wait_for(|| counter.load(Ordering::Acquire) == 2)
+            .expect("wait_for() failed");
+        test_output.write_line("child released");
+        std::process::exit(0);
+    }
+
+    // parent
+    for _ in 0..2 {
+        sleep(20);
+        test_output.write_line("parent notify()");
+        counter.fetch_add(1, Ordering::Release);
+        notify.notify().expect("notify() failed");
+    }
+    wait_child(pid);
+    assert_eq!(notify.generation(), 2);
+}
+
+fn no_lost_wakeup() {
+    let mut notify = SharedNotify::new().expect("cannot create SharedNotify");
+    let ready = SharedAtomicUsize::new(0).expect("cannot create SharedAtomicUsize");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        // notification is sent while predicate is being checked
+        notify
+            .wait_for(|| {
+                let ready = ready.load(Ordering::Acquire) == 1;
+                sleep(40);
+                ready
+            })
+            .expect("wait_for() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    ready.store(1, Ordering::Release);
+    notify.notify().expect("notify() failed");
+    wait_child(pid);
+}
+
+fn main() {
+    wait_for();
+    no_lost_wakeup();
+}
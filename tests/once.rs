@@ -0,0 +1,90 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedMemoryObject, SharedOnce};
+
+use common::{sleep, wait_child};
+
+fn call_once() {
+    let once = SharedOnce::new().expect("cannot create SharedOnce");
+    let calls =
+        SharedMemoryObject::new(AtomicU32::new(0)).expect("cannot create SharedMemoryObject");
+
+    let mut pids = Vec::new();
+    for _ in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            once.call_once(|| {
+                sleep(20);
+                unsafe { calls.get() }.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("call_once() failed");
+            assert!(once.is_completed());
+            assert_eq!(unsafe { calls.get() }.load(Ordering::SeqCst), 1);
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    for pid in pids {
+        wait_child(pid);
+    }
+    once.call_once(|| unreachable!("initialization must not run again"))
+        .expect("call_once() failed");
+    assert_eq!(unsafe { calls.get() }.load(Ordering::SeqCst), 1);
+}
+
+fn initializer_dies() {
+    let once = SharedOnce::new().expect("cannot create SharedOnce");
+    let calls =
+        SharedMemoryObject::new(AtomicU32::new(0)).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child dies during initialization
+        once.call_once(|| {
+            unsafe { calls.get() }.fetch_add(1, Ordering::SeqCst);
+            std::process::exit(0);
+        })
+        .expect("call_once() failed");
+        unreachable!();
+    }
+
+    // parent
+    wait_child(pid);
+    assert!(!once.is_completed());
+    once.call_once(|| {
+        unsafe { calls.get() }.fetch_add(1, Ordering::SeqCst);
+    })
+    .expect("call_once() failed");
+    assert!(once.is_completed());
+    assert_eq!(unsafe { calls.get() }.load(Ordering::SeqCst), 2);
+}
+
+fn threads() {
+    let once = SharedOnce::new().expect("cannot create SharedOnce");
+    let calls = AtomicU32::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                once.call_once(|| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                })
+                .expect("call_once() failed");
+                assert!(once.is_completed());
+            });
+        }
+    });
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+fn main() {
+    call_once();
+    initializer_dies();
+    threads();
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedRwLock, SharedUpgradableRwLock, SyncError};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn shared_readers() {
+    let mut test_output = TestOutput::new(&[
+        "child read()",
+        "parent read()",
+        "parent unlock()",
+        "child unlock()",
+    ]);
+
+    let mut rwlock = SharedRwLock::new().expect("cannot create SharedRwLock");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        rwlock.read().expect("cannot lock child");
+        test_output.write_line("child read()");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        rwlock.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    rwlock.read().expect("cannot lock parent");
+    test_output.write_line("parent read()");
+    test_output.write_line("parent unlock()");
+    rwlock.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn exclusive_writer() {
+    let mut test_output = TestOutput::new(&[
+        "child write()",
+        "parent try_read() false",
+        "parent try_write() false",
+        "child unlock()",
+        "parent write()",
+    ]);
+
+    let mut rwlock = SharedRwLock::new().expect("cannot create SharedRwLock");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        rwlock.write().expect("cannot lock child");
+        test_output.write_line("child write()");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        rwlock.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = rwlock.try_read().expect("try_read() failed");
+    test_output.write_line(format!("parent try_read() {}", locked));
+    let locked = rwlock.try_write().expect("try_write() failed");
+    test_output.write_line(format!("parent try_write() {}", locked));
+    rwlock.write().expect("cannot lock parent");
+    test_output.write_line("parent write()");
+    rwlock.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn upgrade() {
+    let mut test_output = TestOutput::new(&[
+        "child read()",
+        "parent upgradable_read()",
+        "child unlock()",
+        "parent upgrade()",
+    ]);
+
+    let mut rwlock = SharedUpgradableRwLock::new().expect("cannot create SharedUpgradableRwLock");
+    assert!(matches!(rwlock.upgrade(), Err(SyncError::InvalidInput(_))));
+    assert!(matches!(rwlock.unlock(), Err(SyncError::InvalidInput(_))));
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        rwlock.read().expect("cannot lock child");
+        test_output.write_line("child read()");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        rwlock.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    // ordinary reader doesn't block upgradable one, but blocks upgrade
+    rwlock.upgradable_read().expect("cannot lock parent");
+    test_output.write_line("parent upgradable_read()");
+    rwlock.upgrade().expect("cannot upgrade parent");
+    test_output.write_line("parent upgrade()");
+    rwlock.downgrade().expect("cannot downgrade parent");
+    assert!(matches!(rwlock.upgrade(), Err(SyncError::InvalidInput(_))));
+    rwlock.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn single_upgradable() {
+    let mut test_output = TestOutput::new(&[
+        "child upgradable_read()",
+        "child unlock()",
+        "parent upgradable_read()",
+    ]);
+
+    let mut rwlock = SharedUpgradableRwLock::new().expect("cannot create SharedUpgradableRwLock");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        rwlock.upgradable_read().expect("cannot lock child");
+        test_output.write_line("child upgradable_read()");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        rwlock.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    // readers are still allowed
+    rwlock.read().expect("cannot lock parent");
+    rwlock.unlock().expect("cannot unlock parent");
+    rwlock.upgradable_read().expect("cannot lock parent");
+    test_output.write_line("parent upgradable_read()");
+    rwlock.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+#[cfg(target_os = "linux")]
+fn writer_preferring() {
+    let mut test_output = TestOutput::new(&[
+        "child read()",
+        "writer write()",
+        "parent try_read() failed",
+        "child unlock()",
+        "writer locked",
+    ]);
+
+    let mut rwlock = SharedRwLock::new_writer_preferring().expect("cannot create SharedRwLock");
+
+    let reader = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if reader == 0 {
+        // child
+        test_output.write_line("child read()");
+        rwlock.read().expect("read() failed");
+        sleep(60);
+        test_output.write_line("child unlock()");
+        rwlock.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    let writer = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if writer == 0 {
+        // child
+        sleep(20);
+        test_output.write_line("writer write()");
+        rwlock.write().expect("write() failed");
+        test_output.write_line("writer locked");
+        rwlock.unlock().expect("unlock() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(40);
+    // waiting writer blocks new readers, even though lock is held for reading
+    assert!(!rwlock.try_read().expect("try_read() failed"));
+    test_output.write_line("parent try_read() failed");
+    wait_child(reader);
+    wait_child(writer);
+}
+
+fn main() {
+    shared_readers();
+    exclusive_writer();
+    upgrade();
+    single_upgradable();
+    #[cfg(target_os = "linux")]
+    writer_preferring();
+}
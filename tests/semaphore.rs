@@ -0,0 +1,145 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use libc::{c_int, fork, kill, sigaction, SIGUSR1};
+use process_sync::{private::check_libc_err, SharedSemaphore, SyncError};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn wait_post() {
+    let mut test_output = TestOutput::new(&[
+        "child wait() 1",
+        "child wait() 2",
+        "child try_wait() false",
+        "parent post()",
+        "child acquired",
+        "child try_wait() false",
+    ]);
+
+    let semaphore = SharedSemaphore::new(2).expect("cannot create SharedSemaphore");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        semaphore.wait().expect("wait() failed");
+        test_output.write_line("child wait() 1");
+        semaphore.wait().expect("wait() failed");
+        test_output.write_line("child wait() 2");
+        let acquired = semaphore.try_wait().expect("try_wait() failed");
+        test_output.write_line(format!("child try_wait() {}", acquired));
+        semaphore.wait().expect("wait() failed");
+        test_output.write_line("child acquired");
+        let acquired = semaphore.try_wait().expect("try_wait() failed");
+        test_output.write_line(format!("child try_wait() {}", acquired));
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(40);
+    test_output.write_line("parent post()");
+    semaphore.post().expect("post() failed");
+    wait_child(pid);
+}
+
+fn wait_timeout() {
+    let mut test_output = TestOutput::new(&[
+        "child wait_timeout(20ms) false",
+        "parent post()",
+        "child wait_timeout(200ms) true",
+    ]);
+
+    let semaphore = SharedSemaphore::new(0).expect("cannot create SharedSemaphore");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let start = Instant::now();
+        let acquired = semaphore
+            .wait_timeout(Duration::from_millis(20))
+            .expect("wait_timeout() failed");
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        test_output.write_line(format!("child wait_timeout(20ms) {}", acquired));
+        let acquired = semaphore
+            .wait_timeout(Duration::from_millis(200))
+            .expect("wait_timeout() failed");
+        test_output.write_line(format!("child wait_timeout(200ms) {}", acquired));
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(60);
+    test_output.write_line("parent post()");
+    semaphore.post().expect("post() failed");
+    wait_child(pid);
+}
+
+fn value() {
+    let semaphore = SharedSemaphore::new(3).expect("cannot create SharedSemaphore");
+    assert_eq!(semaphore.value(), Ok(3));
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        semaphore.wait().expect("wait() failed");
+        semaphore.wait().expect("wait() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(semaphore.value(), Ok(1));
+    semaphore.post().expect("post() failed");
+    assert_eq!(semaphore.value(), Ok(2));
+}
+
+extern "C" fn on_signal(_: c_int) {}
+
+fn interrupted() {
+    let mut test_output = TestOutput::new(&[
+        "parent kill()",
+        "child wait() interrupted",
+        "parent kill()",
+        "parent post()",
+        "child acquired",
+    ]);
+
+    let mut semaphore = SharedSemaphore::new(0).expect("cannot create SharedSemaphore");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let mut action: sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_signal as *const () as usize;
+        check_libc_err("sigaction", unsafe {
+            sigaction(SIGUSR1, &action, std::ptr::null_mut())
+        })
+        .expect("sigaction() failed");
+
+        let err = semaphore.wait().expect_err("wait() must be interrupted");
+        assert!(matches!(err, SyncError::Interrupted(_)));
+        test_output.write_line("child wait() interrupted");
+        semaphore.set_retry_interrupted(true);
+        semaphore.wait().expect("wait() failed");
+        test_output.write_line("child acquired");
+        std::process::exit(0);
+    }
+
+    // parent
+    for _ in 0..2 {
+        sleep(20);
+        test_output.write_line("parent kill()");
+        check_libc_err("kill", unsafe { kill(pid, SIGUSR1) }).expect("kill() failed");
+    }
+    sleep(20);
+    test_output.write_line("parent post()");
+    semaphore.post().expect("post() failed");
+    wait_child(pid);
+}
+
+fn main() {
+    wait_post();
+    wait_timeout();
+    value();
+    interrupted();
+}
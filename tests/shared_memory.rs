@@ -1,31 +1,834 @@
 mod common;
 
-use libc::fork;
-use process_sync::private::check_libc_err;
+use std::{
+    mem::size_of,
+    ptr::null_mut,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use libc::{
+    c_void, fork, getpid, memset, mmap, munmap, MAP_ANONYMOUS, MAP_FAILED, MAP_HUGETLB,
+    MAP_POPULATE, MAP_PRIVATE, MAP_SHARED, PROT_READ, PROT_WRITE,
+};
 pub use process_sync::private::SharedMemoryObject;
+use process_sync::{
+    private::check_libc_err, reap_child, set_drop_error_hook, unlink_named, HeapAllocator,
+    SyncError,
+};
 
-use common::{sleep, TestOutput};
+use common::{sleep, wait_child, TestOutput};
 
-fn main() {
+fn new() {
     let mut test_output = TestOutput::new(&["123", "123", "456", "789"]);
 
     let mut value = SharedMemoryObject::new(123).expect("cannot create SharedMemoryObject");
 
-    let pid = check_libc_err(unsafe { fork() }).expect("fork failed");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
     if pid == 0 {
         // child
-        test_output.write_line(&format!("{}", value.get()));
+        test_output.write_line(format!("{}", unsafe { value.get() }));
         sleep(20);
-        *value.get_mut() = 456;
+        unsafe { *value.get_mut() = 456 };
         sleep(40);
-        test_output.write_line(&format!("{}", value.get()));
+        test_output.write_line(format!("{}", unsafe { value.get() }));
         std::process::exit(0);
     }
 
     // parent
-    test_output.write_line(&format!("{}", value.get()));
-    sleep(40);
-    test_output.write_line(&format!("{}", value.get()));
-    *value.get_mut() = 789;
+    test_output.write_line(format!("{}", unsafe { value.get() }));
     sleep(40);
+    test_output.write_line(format!("{}", unsafe { value.get() }));
+    unsafe { *value.get_mut() = 789 };
+    wait_child(pid);
+}
+
+fn from_raw_ptr() {
+    let len = std::mem::size_of::<u64>();
+    let addr = unsafe {
+        mmap(
+            null_mut(),
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(addr, MAP_FAILED);
+    let ptr = addr as *mut u64;
+    unsafe { *ptr = 123 };
+
+    let mut value = unsafe { SharedMemoryObject::from_raw_ptr(ptr) };
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { *value.get_mut() = 456 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, 456);
+    drop(value);
+
+    // memory must still be mapped
+    assert_eq!(unsafe { *ptr }, 456);
+    check_libc_err("munmap", unsafe { munmap(addr, len) }).expect("munmap failed");
+}
+
+fn named() {
+    let name = format!("/process-sync-test-{}", unsafe { getpid() });
+
+    let mut value = SharedMemoryObject::create_named(&name, 123u64)
+        .expect("cannot create named SharedMemoryObject");
+    assert!(SharedMemoryObject::create_named(&name, 0u64).is_err());
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let mut attached = unsafe { SharedMemoryObject::<u64>::open_named(&name, false) }
+            .expect("cannot open named SharedMemoryObject");
+        assert_eq!(unsafe { *attached.get() }, 123);
+        unsafe { *attached.get_mut() = 456 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, 456);
+    unsafe { *value.get_mut() = 789 };
+    unlink_named(&name).expect("cannot unlink named SharedMemoryObject");
+    assert!(unsafe { SharedMemoryObject::<u64>::open_named(&name, false) }.is_err());
+
+    let created = unsafe { SharedMemoryObject::<u64>::open_named(&name, true) }
+        .expect("cannot open named SharedMemoryObject");
+    assert_eq!(unsafe { *created.get() }, 0);
+    unlink_named(&name).expect("cannot unlink named SharedMemoryObject");
+}
+
+#[cfg(target_os = "linux")]
+fn memfd() {
+    use std::os::fd::{BorrowedFd, OwnedFd};
+
+    let value = SharedMemoryObject::new_memfd(5u64).expect("cannot create SharedMemoryObject");
+    let fd = value
+        .as_raw_fd()
+        .expect("memfd object must have descriptor");
+    assert_eq!(
+        SharedMemoryObject::new(5u64)
+            .expect("cannot create SharedMemoryObject")
+            .as_raw_fd(),
+        None
+    );
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        // map descriptor again, like unrelated process which received it over socket
+        let fd: OwnedFd = unsafe { BorrowedFd::borrow_raw(fd) }
+            .try_clone_to_owned()
+            .expect("cannot duplicate descriptor");
+        let mut mapped =
+            unsafe { SharedMemoryObject::<u64>::from_fd(fd) }.expect("from_fd() failed");
+        assert_ne!(mapped.as_ptr(), value.as_ptr());
+        assert_eq!(unsafe { *mapped.get() }, 5);
+        unsafe { *mapped.get_mut() = 7 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, 7);
+
+    // file is smaller than requested type
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) }
+        .try_clone_to_owned()
+        .expect("cannot duplicate descriptor");
+    assert!(matches!(
+        unsafe { SharedMemoryObject::<[u64; 2]>::from_fd(fd) },
+        Err(SyncError::InvalidInput(_))
+    ));
+}
+
+fn slice() {
+    let mut test_output = TestOutput::new(&["[0, 10, 20, 30]", "[0, 10, 25, 30]"]);
+
+    let mut values =
+        SharedMemoryObject::new_slice(4, |i| i * 10).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        test_output.write_line(format!("{:?}", unsafe { values.as_slice() }));
+        unsafe { values.as_mut_slice()[2] = 25 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    test_output.write_line(format!("{:?}", unsafe { values.as_slice() }));
+
+    let err = SharedMemoryObject::<[u64]>::new_slice(usize::MAX, |_| 0)
+        .err()
+        .expect("overflowing slice must fail");
+    assert!(matches!(err, SyncError::InvalidInput(_)));
+}
+
+fn slice_access() {
+    let mut values =
+        SharedMemoryObject::new_slice(4, |i| i as u64).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for value in unsafe { values.iter_mut() } {
+            *value *= 10;
+        }
+        *unsafe { values.get_index_mut(3) } += 5;
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { values.iter() }.sum::<u64>(), 65);
+    assert_eq!(unsafe { *values.get_index(3) }, 35);
+    assert_eq!(unsafe { values.get_index(1..3) }, &[10, 20]);
+    assert_eq!(unsafe { values.iter() }.len(), 4);
+}
+
+fn grow() {
+    let mut values =
+        SharedMemoryObject::new_slice(2, |i| i * 10).expect("cannot create SharedMemoryObject");
+    values.grow(5, |i| i * 10).expect("grow() failed");
+    assert_eq!(unsafe { values.as_slice() }, &[0, 10, 20, 30, 40]);
+    assert_eq!(values.capacity(), 5 * size_of::<usize>());
+    // whole new mapping is shared
+    let many_pages = 3 * 4096 / size_of::<usize>();
+    values.grow(many_pages, |i| i).expect("grow() failed");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { values.as_mut_slice()[many_pages - 1] = 7 };
+        assert!(matches!(
+            values.grow(many_pages + 1, |_| 0),
+            Err(SyncError::InvalidInput(_))
+        ));
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { values.as_slice() }[many_pages - 1], 7);
+    assert!(matches!(
+        values.grow(1, |_| 0),
+        Err(SyncError::InvalidInput(_))
+    ));
+}
+
+fn new_default() {
+    #[derive(Default, Debug, PartialEq)]
+    struct Config {
+        name: [u8; 16],
+        retries: u32,
+        enabled: bool,
+    }
+
+    let mut config =
+        SharedMemoryObject::<Config>::new_default().expect("cannot create SharedMemoryObject");
+    assert_eq!(unsafe { config.get() }, &Config::default());
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { config.get_mut().retries = 3 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { config.get() }.retries, 3);
+}
+
+fn uninit() {
+    let mut value = SharedMemoryObject::<[u64; 1 << 16]>::new_uninit()
+        .expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let array = unsafe { value.get_mut().assume_init_mut() };
+        assert!(array.iter().all(|&x| x == 0));
+        array[100] = 123;
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    let value = unsafe { value.assume_init() };
+    assert_eq!(unsafe { value.get() }[100], 123);
+    assert_eq!(unsafe { value.get() }.iter().sum::<u64>(), 123);
+}
+
+fn with_flags() {
+    let mut test_output = TestOutput::new(&["child 123", "parent 456"]);
+
+    let mut value = SharedMemoryObject::map_anonymous_with_flags(123u64, MAP_POPULATE)
+        .expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        test_output.write_line(format!("child {}", unsafe { value.get() }));
+        unsafe { *value.get_mut() = 456 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    test_output.write_line(format!("parent {}", unsafe { value.get() }));
+
+    let err = SharedMemoryObject::map_anonymous_with_flags(0u64, MAP_PRIVATE)
+        .err()
+        .expect("private mapping must fail");
+    assert!(matches!(err, SyncError::InvalidInput(_)));
+
+    // depends on whether huge pages are reserved on the host
+    match SharedMemoryObject::map_anonymous_with_flags(7u64, MAP_HUGETLB) {
+        Ok(value) => assert_eq!(unsafe { *value.get() }, 7),
+        Err(err) => assert!(matches!(err, SyncError::HugePagesUnavailable(_))),
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+fn bytes() {
+    let mut value =
+        SharedMemoryObject::new(0x0102_0304u32).expect("cannot create SharedMemoryObject");
+    assert_eq!(unsafe { value.as_bytes() }, &0x0102_0304u32.to_ne_bytes());
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { value.as_bytes_mut().copy_from_slice(&7u32.to_ne_bytes()) };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, 7);
+}
+
+#[cfg(feature = "bytemuck")]
+fn checked() {
+    let mut value =
+        SharedMemoryObject::new_checked([1u32, 2, 3]).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { value.get_mut()[1] = 7 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, [1, 7, 3]);
+}
+
+#[cfg(feature = "bytemuck")]
+fn zeroed() {
+    let value =
+        SharedMemoryObject::<[[u64; 32]; 32]>::zeroed().expect("cannot create SharedMemoryObject");
+    assert_eq!(unsafe { value.get() }, &<[[u64; 32]; 32]>::default());
+}
+
+#[cfg(feature = "serde")]
+fn serde() {
+    let value = SharedMemoryObject::new([1u32, 2, 3]).expect("cannot create SharedMemoryObject");
+    let json = serde_json::to_string(unsafe { value.get() }).expect("cannot serialize object");
+    assert_eq!(json, "[1,2,3]");
+
+    let mut restored: SharedMemoryObject<[u32; 3]> =
+        serde_json::from_str(&json).expect("cannot deserialize SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { restored.get_mut()[1] = 20 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *restored.get() }, [1, 20, 3]);
+}
+
+fn into_raw() {
+    let value = SharedMemoryObject::new(123u64).expect("cannot create SharedMemoryObject");
+    let ptr = value.into_raw();
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { *ptr = 456 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    let value = unsafe { SharedMemoryObject::from_raw(ptr) };
+    assert_eq!(unsafe { *value.get() }, 456);
+    drop(value);
+
+    let leaked = unsafe {
+        SharedMemoryObject::new(7u64)
+            .expect("cannot create SharedMemoryObject")
+            .leak()
+    };
+    *leaked += 1;
+    assert_eq!(*leaked, 8);
+}
+
+fn with_capacity() {
+    let value =
+        SharedMemoryObject::with_capacity(5u64, 4096).expect("cannot create SharedMemoryObject");
+    assert_eq!(value.capacity(), 4096);
+    let tail = unsafe { (value.get() as *const u64 as *mut u8).add(size_of::<u64>()) };
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { tail.add(4000).write(42) };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { tail.add(4000).read() }, 42);
+
+    let small = SharedMemoryObject::with_capacity([0u8; 100], 10)
+        .expect("cannot create SharedMemoryObject");
+    assert_eq!(small.capacity(), 100);
+    assert_eq!(
+        SharedMemoryObject::new(0u32)
+            .expect("cannot create SharedMemoryObject")
+            .capacity(),
+        4
+    );
+}
+
+fn replace() {
+    let mut value = SharedMemoryObject::new(1u64).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        assert_eq!(unsafe { value.replace(2) }, 1);
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { value.take() }, 2);
+    assert_eq!(unsafe { *value.get() }, 0);
+}
+
+fn cast() {
+    #[repr(C)]
+    struct HeaderV1 {
+        version: u32,
+        value: u32,
+    }
+    #[repr(C)]
+    struct HeaderV2 {
+        version: u32,
+        value: u32,
+        extra: u64,
+    }
+
+    // layout can only be extended in place if mapping has room for it
+    let header = SharedMemoryObject::new(HeaderV1 {
+        version: 1,
+        value: 5,
+    })
+    .expect("cannot create SharedMemoryObject");
+    assert!(matches!(
+        unsafe { header.cast::<[u8; 4096 + 1]>() },
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    let header = SharedMemoryObject::with_capacity(
+        HeaderV1 {
+            version: 1,
+            value: 5,
+        },
+        size_of::<HeaderV2>(),
+    )
+    .expect("cannot create SharedMemoryObject");
+    let mut header = unsafe { header.cast::<HeaderV2>() }.expect("cast() failed");
+    unsafe {
+        let header = header.get_mut();
+        header.version = 2;
+        header.extra = 7;
+    }
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        let header = unsafe { header.get() };
+        assert_eq!((header.version, header.value, header.extra), (2, 5, 7));
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(header.capacity(), size_of::<HeaderV2>());
+}
+
+fn aligned() {
+    let align = 1 << 21;
+    let value = SharedMemoryObject::new_aligned(AtomicU64::new(0), align)
+        .expect("cannot create SharedMemoryObject");
+    assert_eq!(
+        unsafe { value.get() } as *const AtomicU64 as usize % align,
+        0
+    );
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { value.get() }.fetch_add(1, Ordering::Relaxed);
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { value.get() }.load(Ordering::Relaxed), 1);
+
+    assert!(matches!(
+        SharedMemoryObject::new_aligned(0u64, 3),
+        Err(SyncError::InvalidInput(_))
+    ));
+}
+
+fn mmap_error() {
+    assert!(matches!(
+        SharedMemoryObject::with_capacity(0u8, usize::MAX),
+        Err(SyncError::InvalidInput(_))
+    ));
+
+    // no host has that much memory
+    let bytes = isize::MAX as usize;
+    let err = SharedMemoryObject::with_capacity(0u8, bytes)
+        .err()
+        .expect("huge mapping must fail");
+    assert!(matches!(err, SyncError::MmapFailed(_)));
+    let os_error = err.os_error().expect("no OsError");
+    assert_eq!(os_error.operation(), "mmap");
+    assert_eq!(os_error.bytes(), Some(bytes));
+    assert!(err
+        .to_string()
+        .contains(&format!("mmap of {} bytes failed", bytes)));
+}
+
+fn address_space_limit() {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    check_libc_err("getrlimit", unsafe {
+        libc::getrlimit(libc::RLIMIT_AS, &mut limit)
+    })
+    .expect("getrlimit failed");
+    let lowered = libc::rlimit {
+        rlim_cur: 1 << 30,
+        ..limit
+    };
+    check_libc_err("setrlimit", unsafe {
+        libc::setrlimit(libc::RLIMIT_AS, &lowered)
+    })
+    .expect("setrlimit failed");
+    let bytes = 2 << 30;
+    let result = SharedMemoryObject::with_capacity(0u8, bytes);
+    check_libc_err("setrlimit", unsafe {
+        libc::setrlimit(libc::RLIMIT_AS, &limit)
+    })
+    .expect("setrlimit failed");
+
+    let err = result.err().expect("mapping over RLIMIT_AS must fail");
+    assert!(matches!(err, SyncError::MmapFailed(_)));
+    assert_eq!(err.raw_os_error(), Some(libc::ENOMEM));
+    assert_eq!(err.os_error().and_then(|err| err.bytes()), Some(bytes));
+}
+
+static DROP_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+fn drop_error_hook() {
+    set_drop_error_hook(|operation, err| {
+        assert_eq!(operation, "cannot munmap() shared memory");
+        assert!(matches!(err, SyncError::MmapFailed(_)));
+        DROP_ERRORS.fetch_add(1, Ordering::Relaxed);
+    });
+
+    let value = SharedMemoryObject::new([0u8; 2]).expect("cannot create SharedMemoryObject");
+    // munmap() fails for address which is not page-aligned
+    let misaligned = unsafe { (value.get() as *const u8 as *mut u8).add(1) };
+    drop(unsafe { SharedMemoryObject::from_raw(misaligned) });
+    assert_eq!(DROP_ERRORS.load(Ordering::Relaxed), 1);
+}
+
+fn ffi() {
+    let mut value =
+        SharedMemoryObject::new_slice(100, |_| 0u32).expect("cannot create SharedMemoryObject");
+    assert_eq!(value.mapped_len(), 400);
+    assert_eq!(
+        value.as_ptr() as *const u32,
+        unsafe { value.as_slice() }.as_ptr()
+    );
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { memset(value.as_mut_ptr() as *mut c_void, 0xff, value.mapped_len()) };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert!(unsafe { value.as_slice() }.iter().all(|&x| x == u32::MAX));
+}
+
+fn unmap_on_drop() {
+    let mut value = SharedMemoryObject::new(5u64).expect("cannot create SharedMemoryObject");
+    assert!(value.unmap_on_drop());
+    unsafe { value.set_unmap_on_drop(false) };
+    let ptr = value.as_mut_ptr();
+    drop(value);
+
+    // memory is still mapped
+    assert_eq!(unsafe { ptr.read() }, 5);
+    check_libc_err("munmap", unsafe {
+        munmap(ptr as *mut c_void, size_of::<u64>())
+    })
+    .expect("munmap failed");
+}
+
+struct DropCounter(&'static AtomicUsize);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn drop_object() {
+    let drops: &'static AtomicUsize = unsafe {
+        SharedMemoryObject::new(AtomicUsize::new(0))
+            .expect("cannot create SharedMemoryObject")
+            .leak()
+    };
+    let value =
+        SharedMemoryObject::new(DropCounter(drops)).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        drop(value);
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    drop(value);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+}
+
+fn zero_sized() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Zst;
+
+    impl Drop for Zst {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut unit = SharedMemoryObject::new(()).expect("cannot create SharedMemoryObject");
+    unsafe { *unit.get_mut() = () };
+    assert_eq!(unit.mapped_len(), 0);
+
+    let uninit = SharedMemoryObject::<()>::new_uninit().expect("cannot create SharedMemoryObject");
+    drop(unsafe { uninit.assume_init() });
+    let aligned =
+        SharedMemoryObject::new_aligned((), 1 << 21).expect("cannot create SharedMemoryObject");
+    assert_eq!(aligned.as_ptr() as usize % (1 << 21), 0);
+
+    let mut empty =
+        SharedMemoryObject::new_slice(0, |_| 0u64).expect("cannot create SharedMemoryObject");
+    assert!(unsafe { empty.as_slice() }.is_empty());
+    empty.grow(4, |i| i as u64).expect("grow() failed");
+    assert_eq!(unsafe { empty.as_slice() }, &[0, 1, 2, 3]);
+
+    let value = SharedMemoryObject::new(Zst).expect("cannot create SharedMemoryObject");
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        drop(value);
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    drop(value);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[cfg(target_os = "linux")]
+fn dontfork() {
+    // returns exit code of child which checks whether object is mapped in it
+    fn is_mapped_in_child(value: &SharedMemoryObject<u64>) -> bool {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            // msync() fails with ENOMEM for memory which is not mapped
+            let ret = unsafe {
+                libc::msync(
+                    value.as_ptr() as *mut c_void,
+                    value.mapped_len(),
+                    libc::MS_ASYNC,
+                )
+            };
+            // handle must not be dropped, since memory may be not mapped
+            std::process::exit(if ret == 0 { 0 } else { 1 });
+        }
+        reap_child(pid).expect("reap_child() failed").success()
+    }
+
+    let value = SharedMemoryObject::new(0u64).expect("cannot create SharedMemoryObject");
+    assert!(is_mapped_in_child(&value));
+    unsafe { value.set_dontfork() }.expect("set_dontfork() failed");
+    assert!(!is_mapped_in_child(&value));
+    value.set_dofork().expect("set_dofork() failed");
+    assert!(is_mapped_in_child(&value));
+
+    let heap =
+        SharedMemoryObject::new_in(0u64, HeapAllocator).expect("cannot create SharedMemoryObject");
+    assert!(matches!(
+        unsafe { heap.set_dontfork() },
+        Err(SyncError::InvalidInput(_))
+    ));
+}
+
+fn clone_handle() {
+    let drops: &'static AtomicUsize = unsafe {
+        SharedMemoryObject::new(AtomicUsize::new(0))
+            .expect("cannot create SharedMemoryObject")
+            .leak()
+    };
+    let mut value =
+        SharedMemoryObject::new(DropCounter(drops)).expect("cannot create SharedMemoryObject");
+    let handle = unsafe { value.clone_handle() };
+    assert!(!handle.unmap_on_drop());
+    assert_eq!(handle.as_ptr(), value.as_ptr());
+
+    // dropping second handle neither unmaps memory nor drops object
+    drop(handle);
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    unsafe { value.get_mut() }.0.fetch_add(1, Ordering::Relaxed);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+    drop(value);
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+}
+
+fn from_file() {
+    let path = std::env::temp_dir().join(format!("process-sync-test-{}", unsafe { getpid() }));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("cannot create file");
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as i64;
+
+    for offset in [-page_size, 1, page_size + 8] {
+        assert!(matches!(
+            unsafe { SharedMemoryObject::<u64>::from_file(&file, offset) },
+            Err(SyncError::InvalidInput(_))
+        ));
+    }
+
+    // file is extended with zeroes
+    let mut value = unsafe { SharedMemoryObject::<u64>::from_file(&file, page_size) }
+        .expect("from_file() failed");
+    assert_eq!(file.metadata().unwrap().len(), page_size as u64 + 8);
+    assert_eq!(unsafe { *value.get() }, 0);
+    unsafe { *value.get_mut() = 5 };
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        unsafe { *value.get_mut() = 7 };
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *value.get() }, 7);
+    drop(value);
+    drop(file);
+
+    // contents are persisted in file
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .expect("cannot open file");
+    let value = unsafe { SharedMemoryObject::<u64>::from_file(&file, page_size) }
+        .expect("from_file() failed");
+    assert_eq!(unsafe { *value.get() }, 7);
+    drop(value);
+    std::fs::remove_file(&path).expect("cannot remove file");
+}
+
+fn main() {
+    new();
+    from_raw_ptr();
+    named();
+    #[cfg(target_os = "linux")]
+    memfd();
+    slice();
+    slice_access();
+    grow();
+    new_default();
+    uninit();
+    with_flags();
+    into_raw();
+    with_capacity();
+    replace();
+    cast();
+    aligned();
+    mmap_error();
+    address_space_limit();
+    drop_error_hook();
+    ffi();
+    unmap_on_drop();
+    drop_object();
+    zero_sized();
+    #[cfg(target_os = "linux")]
+    dontfork();
+    clone_handle();
+    from_file();
+    #[cfg(feature = "bytemuck")]
+    bytes();
+    #[cfg(feature = "bytemuck")]
+    checked();
+    #[cfg(feature = "bytemuck")]
+    zeroed();
+    #[cfg(feature = "serde")]
+    serde();
 }
@@ -0,0 +1,65 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedMemoryObject, SharedSpinlock};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn try_lock() {
+    let mut test_output = TestOutput::new(&[
+        "child locked",
+        "parent try_lock() false",
+        "child unlock()",
+        "parent try_lock() true",
+    ]);
+
+    let mut spinlock = SharedSpinlock::new().expect("cannot create SharedSpinlock");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        spinlock.lock().expect("cannot lock child");
+        test_output.write_line("child locked");
+        sleep(40);
+        test_output.write_line("child unlock()");
+        spinlock.unlock().expect("cannot unlock child");
+        std::process::exit(0);
+    }
+
+    // parent
+    sleep(20);
+    let locked = spinlock.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    sleep(40);
+    let locked = spinlock.try_lock().expect("try_lock() failed");
+    test_output.write_line(format!("parent try_lock() {}", locked));
+    spinlock.unlock().expect("cannot unlock parent");
+    wait_child(pid);
+}
+
+fn counter() {
+    let mut spinlock = SharedSpinlock::new().expect("cannot create SharedSpinlock");
+    let mut counter = SharedMemoryObject::new(0u64).expect("cannot create SharedMemoryObject");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    for _ in 0..1000 {
+        spinlock.lock().expect("cannot lock");
+        let value = unsafe { *counter.get() };
+        std::hint::spin_loop();
+        unsafe { *counter.get_mut() = value + 1 };
+        spinlock.unlock().expect("cannot unlock");
+    }
+    if pid == 0 {
+        // child
+        std::process::exit(0);
+    }
+
+    // parent
+    wait_child(pid);
+    assert_eq!(unsafe { *counter.get() }, 2000);
+}
+
+fn main() {
+    try_lock();
+    counter();
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedSpscQueue};
+
+use common::wait_child;
+
+const COUNT: u64 = 100_000;
+
+fn full_and_empty() {
+    assert!(SharedSpscQueue::<u64>::new(0).is_err());
+
+    let mut queue = SharedSpscQueue::<u64>::new(2).expect("cannot create SharedSpscQueue");
+    assert_eq!(queue.capacity(), 2);
+    assert!(queue.is_empty());
+    assert_eq!(unsafe { queue.pop() }, None);
+
+    assert!(unsafe { queue.push(1) });
+    assert!(unsafe { queue.push(2) });
+    assert!(!unsafe { queue.push(3) });
+    assert_eq!(queue.len(), 2);
+
+    assert_eq!(unsafe { queue.pop() }, Some(1));
+    assert!(unsafe { queue.push(3) });
+    assert_eq!(unsafe { queue.pop() }, Some(2));
+    assert_eq!(unsafe { queue.pop() }, Some(3));
+    assert_eq!(unsafe { queue.pop() }, None);
+}
+
+fn across_processes() {
+    let mut queue = SharedSpscQueue::<u64>::new(64).expect("cannot create SharedSpscQueue");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for value in 0..COUNT {
+            while !unsafe { queue.push(value) } {
+                std::thread::yield_now();
+            }
+        }
+        std::process::exit(0);
+    }
+
+    // parent
+    let mut expected = 0;
+    while expected < COUNT {
+        match unsafe { queue.pop() } {
+            Some(value) => {
+                assert_eq!(value, expected);
+                expected += 1;
+            }
+            None => std::thread::yield_now(),
+        }
+    }
+    wait_child(pid);
+    assert!(queue.is_empty());
+}
+
+fn main() {
+    full_and_empty();
+    across_processes();
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+
+use libc::fork;
+use process_sync::{
+    private::check_libc_err, SharedArc, SharedAtomicU32, SharedBarrier, SharedBroadcast,
+    SharedChannel, SharedCondvar, SharedEvent, SharedMemoryObject, SharedMonitor, SharedMutex,
+    SharedMutexGuard, SharedNotify, SharedOnce, SharedRwLock, SharedSemaphore, SharedSpinlock,
+    SharedSpscQueue, SharedTicketLock, SharedUpgradableRwLock,
+};
+
+use common::{sleep, wait_child};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn bounds() {
+    assert_send_sync::<SharedMemoryObject<AtomicU32>>();
+    assert_send_sync::<SharedMemoryObject<[u8]>>();
+    assert_send_sync::<SharedAtomicU32>();
+    assert_send_sync::<SharedArc<SharedMutex>>();
+    assert_send_sync::<SharedBarrier>();
+    assert_send_sync::<SharedBroadcast>();
+    assert_send_sync::<SharedChannel>();
+    assert_send_sync::<SharedCondvar>();
+    assert_send_sync::<SharedEvent>();
+    assert_send_sync::<SharedMonitor<u64>>();
+    assert_send_sync::<SharedMutex<u64>>();
+    assert_send_sync::<SharedNotify>();
+    assert_send_sync::<SharedOnce>();
+    assert_send_sync::<SharedRwLock>();
+    assert_send_sync::<SharedSemaphore>();
+    assert_send_sync::<SharedSpinlock>();
+    assert_send_sync::<SharedSpscQueue<u64>>();
+    assert_send_sync::<SharedTicketLock>();
+    assert_send_sync::<SharedUpgradableRwLock>();
+
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<SharedMutexGuard<'static, u64>>();
+}
+
+fn mutex() {
+    let mut mutex = SharedMutex::with_data(0u64).expect("cannot create SharedMutex");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        for _ in 0..1000 {
+            *mutex.guard().expect("guard() failed") += 1;
+        }
+        std::process::exit(0);
+    }
+
+    // parent
+    let mut mutex = thread::spawn(move || {
+        for _ in 0..1000 {
+            *mutex.guard().expect("guard() failed") += 1;
+        }
+        mutex
+    })
+    .join()
+    .expect("thread panicked");
+    wait_child(pid);
+    assert_eq!(*mutex.guard().expect("guard() failed"), 2000);
+}
+
+fn event() {
+    let mut event = SharedEvent::new().expect("cannot create SharedEvent");
+
+    let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+    if pid == 0 {
+        // child
+        sleep(20);
+        event.set().expect("set() failed");
+        std::process::exit(0);
+    }
+
+    // parent
+    thread::spawn(move || event.wait().expect("wait() failed"))
+        .join()
+        .expect("thread panicked");
+    wait_child(pid);
+}
+
+fn shared_object() {
+    let counter =
+        SharedMemoryObject::new(AtomicU32::new(0)).expect("cannot create SharedMemoryObject");
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| unsafe { counter.get() }.fetch_add(1, Ordering::Relaxed));
+        }
+    });
+    assert_eq!(unsafe { counter.get() }.load(Ordering::Relaxed), 4);
+}
+
+fn main() {
+    bounds();
+    mutex();
+    event();
+    shared_object();
+}
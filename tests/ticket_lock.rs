@@ -0,0 +1,85 @@
+mod common;
+
+use libc::fork;
+use process_sync::{private::check_libc_err, SharedMemoryObject, SharedTicketLock};
+
+use common::{sleep, wait_child, TestOutput};
+
+fn fifo_order() {
+    let mut test_output = TestOutput::new(&[
+        "parent unlock()",
+        "child 0 locked",
+        "child 1 locked",
+        "child 2 locked",
+    ]);
+
+    let mut lock = SharedTicketLock::new().expect("cannot create SharedTicketLock");
+    lock.lock().expect("cannot lock parent");
+
+    let mut pids = Vec::new();
+    for child in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            // children take tickets one after another
+            sleep(20 * (child + 1));
+            lock.lock().expect("cannot lock child");
+            test_output.write_line(format!("child {} locked", child));
+            lock.unlock().expect("cannot unlock child");
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    sleep(100);
+    test_output.write_line("parent unlock()");
+    lock.unlock().expect("cannot unlock parent");
+    for pid in pids {
+        wait_child(pid);
+    }
+}
+
+fn try_lock() {
+    let mut lock = SharedTicketLock::new().expect("cannot create SharedTicketLock");
+
+    assert!(lock.try_lock().expect("try_lock() failed"));
+    assert!(!lock.try_lock().expect("try_lock() failed"));
+    lock.unlock().expect("cannot unlock");
+    assert!(lock.try_lock().expect("try_lock() failed"));
+    lock.unlock().expect("cannot unlock");
+}
+
+fn counter() {
+    let mut lock = SharedTicketLock::new().expect("cannot create SharedTicketLock");
+    let mut counter = SharedMemoryObject::new(0u64).expect("cannot create SharedMemoryObject");
+
+    let mut pids = Vec::new();
+    for _ in 0..3 {
+        let pid = check_libc_err("fork", unsafe { fork() }).expect("fork failed");
+        if pid == 0 {
+            // child
+            for _ in 0..1000 {
+                lock.lock().expect("cannot lock");
+                let value = unsafe { *counter.get() };
+                std::hint::spin_loop();
+                unsafe { *counter.get_mut() = value + 1 };
+                lock.unlock().expect("cannot unlock");
+            }
+            std::process::exit(0);
+        }
+        pids.push(pid);
+    }
+
+    // parent
+    for pid in pids {
+        wait_child(pid);
+    }
+    assert_eq!(unsafe { *counter.get() }, 3000);
+}
+
+fn main() {
+    fifo_order();
+    try_lock();
+    counter();
+}